@@ -0,0 +1,102 @@
+//! Compares the bounding volume hierarchy against a linear scan over a
+//! 100k triangle scene.
+//!
+//! Run with `cargo bench`.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use raytracer::material::{Lambertian, Material};
+use raytracer::ray::Ray;
+use raytracer::triangle::Triangle;
+use raytracer::vector::Vector3;
+use raytracer::world::World;
+use raytracer::Intersectable;
+
+/// Number of triangles in the benchmark scene
+const TRIANGLES: usize = 100_000;
+
+/// Number of rays traced through the benchmark scene
+const RAYS: usize = 1_000;
+
+/// Generates deterministic pseudo-random numbers in 0..1
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn vector(&mut self, scale: f32) -> Vector3 {
+        Vector3::new(self.next() - 0.5, self.next() - 0.5, self.next() - 0.5) * scale
+    }
+}
+
+/// Scatters small triangles through a cube
+fn triangle_soup(rng: &mut Lcg) -> Vec<Box<dyn Intersectable + Sync + Send>> {
+    let material: Arc<dyn Material> = Arc::new(Lambertian::new(Vector3::new_scalar(0.5)));
+    (0..TRIANGLES)
+        .map(|_| {
+            let center = rng.vector(20.0);
+            let triangle: Box<dyn Intersectable + Sync + Send> = Box::new(Triangle::new(
+                center + rng.vector(0.2),
+                center + rng.vector(0.2),
+                center + rng.vector(0.2),
+                Arc::clone(&material),
+            ));
+            triangle
+        })
+        .collect()
+}
+
+fn main() {
+    let mut rng = Lcg(11);
+    let rays: Vec<Ray> = (0..RAYS)
+        .map(|_| {
+            Ray::new(
+                Vector3::new(0.0, 0.0, -30.0),
+                (rng.vector(1.0) + Vector3::new(0.0, 0.0, 1.0)).normalize(),
+            )
+        })
+        .collect();
+
+    let build_start = Instant::now();
+    let world = World::new(triangle_soup(&mut Lcg(7)));
+    let build = build_start.elapsed();
+
+    let linear = triangle_soup(&mut Lcg(7));
+    let linear_start = Instant::now();
+    let linear_hits: Vec<Option<f32>> = rays
+        .iter()
+        .map(|ray| {
+            linear
+                .iter()
+                .filter_map(|object| object.intersect(*ray))
+                .map(|hit| hit.distance())
+                .fold(None, |closest: Option<f32>, d| {
+                    Some(closest.map_or(d, |c| c.min(d)))
+                })
+        })
+        .collect();
+    let linear_time = linear_start.elapsed();
+
+    let bvh_start = Instant::now();
+    let bvh_hits: Vec<Option<f32>> = rays
+        .iter()
+        .map(|ray| world.intersect(*ray).map(|hit| hit.distance()))
+        .collect();
+    let bvh_time = bvh_start.elapsed();
+
+    println!("BVH build:   {:?}", build);
+    println!("Linear scan: {:?} for {} rays", linear_time, RAYS);
+    println!("BVH:         {:?} for {} rays", bvh_time, RAYS);
+    println!(
+        "Speedup:     {:.1}x",
+        linear_time.as_secs_f64() / bvh_time.as_secs_f64()
+    );
+    assert_eq!(linear_hits, bvh_hits, "BVH and linear scan disagree");
+}
@@ -0,0 +1,108 @@
+use crate::ray::Ray;
+use crate::vector::Vector3;
+
+/// An axis-aligned bounding box described by its minimum and maximum corners
+#[derive(Debug, Copy, Clone)]
+pub struct Aabb {
+    min: Vector3,
+    max: Vector3,
+}
+
+impl Aabb {
+    /// Creates a bounding box from its minimum and maximum corners
+    pub fn new(min: Vector3, max: Vector3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// Creates an empty bounding box that contains nothing
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: Vector3::new_scalar(f32::INFINITY),
+            max: Vector3::new_scalar(f32::NEG_INFINITY),
+        }
+    }
+
+    /// Creates a bounding box that contains all of space, for unbounded objects
+    pub fn infinite() -> Aabb {
+        Aabb {
+            min: Vector3::new_scalar(f32::NEG_INFINITY),
+            max: Vector3::new_scalar(f32::INFINITY),
+        }
+    }
+
+    /// Gets the minimum corner of the box
+    pub fn min(&self) -> Vector3 {
+        self.min
+    }
+
+    /// Gets the maximum corner of the box
+    pub fn max(&self) -> Vector3 {
+        self.max
+    }
+
+    /// Gets the center point of the box
+    pub fn centroid(&self) -> Vector3 {
+        (self.min + self.max) * 0.5
+    }
+
+    /// Checks whether the box has finite extents on every axis
+    pub fn is_finite(&self) -> bool {
+        (0..3).all(|axis| self.min[axis].is_finite() && self.max[axis].is_finite())
+    }
+
+    /// Takes the smallest box containing both boxes
+    pub fn union(self, other: Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Takes the smallest box containing the box and a point
+    pub fn grow(self, point: Vector3) -> Aabb {
+        Aabb {
+            min: self.min.min(point),
+            max: self.max.max(point),
+        }
+    }
+
+    /// Calculates the surface area of the box, or zero if it is empty
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Gets the distance at which the ray enters the box, if it does so
+    /// before `max_distance`
+    ///
+    /// `inv_direction` is the componentwise reciprocal of the ray direction,
+    /// which callers precompute once per ray.
+    pub fn intersect(&self, ray: Ray, inv_direction: Vector3, max_distance: f32) -> Option<f32> {
+        let mut t_near = 0.0f32;
+        let mut t_far = max_distance;
+        for axis in 0..3 {
+            // Rays parallel to a slab are inside it or miss the box, which
+            // is checked directly since a ray starting on the slab's face
+            // would otherwise give 0 times infinity
+            if inv_direction[axis].is_infinite() {
+                let origin = ray.origin()[axis];
+                if origin < self.min[axis] || origin > self.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (self.min[axis] - ray.origin()[axis]) * inv_direction[axis];
+            let t1 = (self.max[axis] - ray.origin()[axis]) * inv_direction[axis];
+            t_near = t_near.max(t0.min(t1));
+            t_far = t_far.min(t0.max(t1));
+        }
+        if t_near <= t_far {
+            Some(t_near)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use crate::material::{average, BsdfSample, Material, SpecularLobe};
+use crate::rayhit::RayHit;
+use crate::texture::Texture;
+use crate::vector::Vector3;
+
+/// Step in texture coordinates, and in distance along the tangent frame,
+/// used to estimate the slope of a height map
+const HEIGHT_DELTA: f32 = 1e-3;
+
+/// Describes how a texture adds detail to a surface's normal
+#[derive(Debug, Clone)]
+pub enum Bump {
+    /// A tangent-space normal map, whose red, green, and blue channels hold
+    /// the normal's components along the tangent, bitangent, and surface
+    /// normal, mapped from -1..1 into 0..1
+    NormalMap(Arc<dyn Texture>),
+    /// A grayscale height map, whose brighter areas are raised
+    HeightMap(Arc<dyn Texture>),
+}
+
+/// A material whose shading normal is bent by a normal or height map, adding
+/// surface detail without adding geometry
+///
+/// Only shading is changed, so the outline of an object and the shadows it
+/// casts stay those of its actual geometry.
+#[derive(Debug, Clone)]
+pub struct Bumped {
+    material: Arc<dyn Material>,
+    bump: Bump,
+    strength: f32,
+}
+
+impl Bumped {
+    /// Creates a new bumped copy of a material
+    ///
+    /// `strength` scales the tilt of a normal map's normals and the slope of
+    /// a height map, with 0 leaving the surface flat.
+    pub fn new(material: Arc<dyn Material>, bump: Bump, strength: f32) -> Bumped {
+        Bumped {
+            material,
+            bump,
+            strength,
+        }
+    }
+
+    /// Calculates the bent normal at a hit, in world space
+    fn bent_normal(&self, hit: &RayHit) -> Vector3 {
+        let (tangent, bitangent, normal) = (hit.tangent(), hit.bitangent(), hit.normal());
+        let bent = match &self.bump {
+            Bump::NormalMap(texture) => {
+                let mapped = texture.evaluate(hit) * 2.0 - Vector3::new_scalar(1.0);
+                tangent * (mapped.x * self.strength)
+                    + bitangent * (mapped.y * self.strength)
+                    + normal * mapped.z
+            }
+            Bump::HeightMap(texture) => {
+                // Estimates the slope by stepping along u and v, moving the
+                // position along with them for textures mapped by position
+                let (u, v) = hit.uv();
+                let height = |offset: Vector3, uv: (f32, f32)| {
+                    let shifted = RayHit::new(
+                        hit.position() + offset,
+                        normal,
+                        uv,
+                        hit.distance(),
+                        hit.material(),
+                    )
+                    .with_tangents(tangent, bitangent);
+                    average(texture.evaluate(&shifted))
+                };
+                let base = height(Vector3::origin(), (u, v));
+                let du =
+                    (height(tangent * HEIGHT_DELTA, (u + HEIGHT_DELTA, v)) - base) / HEIGHT_DELTA;
+                let dv =
+                    (height(bitangent * HEIGHT_DELTA, (u, v + HEIGHT_DELTA)) - base) / HEIGHT_DELTA;
+                normal - (tangent * du + bitangent * dv) * self.strength
+            }
+        };
+
+        // Keeps the surface's own normal if the map gives no usable direction
+        if bent.len() > 1e-6 {
+            bent.normalize()
+        } else {
+            normal
+        }
+    }
+}
+
+impl Material for Bumped {
+    fn evaluate(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> Vector3 {
+        self.material.evaluate(hit, outgoing, incoming)
+    }
+
+    fn sample(
+        &self,
+        hit: &RayHit,
+        outgoing: Vector3,
+        lobe: f32,
+        u: f32,
+        v: f32,
+    ) -> Option<BsdfSample> {
+        self.material.sample(hit, outgoing, lobe, u, v)
+    }
+
+    fn pdf(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> f32 {
+        self.material.pdf(hit, outgoing, incoming)
+    }
+
+    fn specular_lobes(&self, hit: &RayHit, outgoing: Vector3) -> Vec<SpecularLobe> {
+        self.material.specular_lobes(hit, outgoing)
+    }
+
+    fn emission(&self, hit: &RayHit, outgoing: Vector3) -> Vector3 {
+        self.material.emission(hit, outgoing)
+    }
+
+    /// Bends the normal the wrapped material shades with, after the wrapped
+    /// material has bent it, so bumps can be layered
+    fn shading_hit<'a>(&self, hit: &RayHit<'a>) -> RayHit<'a> {
+        let hit = self.material.shading_hit(hit);
+        hit.with_normal(self.bent_normal(&hit))
+    }
+}
@@ -0,0 +1,261 @@
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::vector::Vector3;
+
+/// Number of buckets primitive centroids are sorted into when searching for
+/// the cheapest split
+const BUCKETS: usize = 12;
+
+/// Largest number of primitives stored in a single leaf
+const MAX_LEAF_SIZE: usize = 4;
+
+/// Relative cost of traversing a node compared to intersecting a primitive
+const TRAVERSAL_COST: f32 = 0.5;
+
+/// A node of the flattened tree
+///
+/// Leaves have a nonzero `count` and own the primitives `first..first + count`
+/// of the index list. Interior nodes store their first child directly after
+/// themselves and their second child at `first`.
+#[derive(Debug, Copy, Clone)]
+struct BvhNode {
+    bounds: Aabb,
+    first: u32,
+    count: u32,
+}
+
+/// Bounding volume hierarchy over a list of primitives, built with the
+/// surface area heuristic
+///
+/// The tree only stores primitive indices, so it can be used to accelerate
+/// any list of bounded objects.
+#[derive(Debug)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<u32>,
+}
+
+/// Bounding box and centroid of a primitive, used while building
+#[derive(Copy, Clone)]
+struct BuildPrimitive {
+    bounds: Aabb,
+    centroid: Vector3,
+}
+
+impl Bvh {
+    /// Builds a hierarchy over primitives with the given bounding boxes
+    pub fn new(bounds: &[Aabb]) -> Bvh {
+        let primitives: Vec<BuildPrimitive> = bounds
+            .iter()
+            .map(|bounds| BuildPrimitive {
+                bounds: *bounds,
+                centroid: bounds.centroid(),
+            })
+            .collect();
+
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(2 * bounds.len()),
+            indices: (0..bounds.len() as u32).collect(),
+        };
+        if !bounds.is_empty() {
+            bvh.build(&primitives, 0, bounds.len());
+        }
+        bvh
+    }
+
+    /// Recursively builds the subtree over `indices[start..end]` and returns
+    /// the index of its root node
+    fn build(&mut self, primitives: &[BuildPrimitive], start: usize, end: usize) -> usize {
+        let node = self.nodes.len();
+        let count = end - start;
+
+        // Bounds of all primitives, and of their centroids for bucketing
+        let mut bounds = Aabb::empty();
+        let mut centroid_bounds = Aabb::empty();
+        for &i in &self.indices[start..end] {
+            bounds = bounds.union(primitives[i as usize].bounds);
+            centroid_bounds = centroid_bounds.grow(primitives[i as usize].centroid);
+        }
+        self.nodes.push(BvhNode {
+            bounds,
+            first: start as u32,
+            count: count as u32,
+        });
+
+        if count == 1 {
+            return node;
+        }
+
+        // Picks the cheapest bucket split across all three axes
+        let mut best: Option<(usize, usize, f32)> = None;
+        let extent = centroid_bounds.max() - centroid_bounds.min();
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
+            let mut bucket_bounds = [Aabb::empty(); BUCKETS];
+            let mut bucket_counts = [0usize; BUCKETS];
+            for &i in &self.indices[start..end] {
+                let b = bucket(primitives[i as usize].centroid, &centroid_bounds, axis);
+                bucket_bounds[b] = bucket_bounds[b].union(primitives[i as usize].bounds);
+                bucket_counts[b] += 1;
+            }
+
+            for split in 1..BUCKETS {
+                let (mut left, mut left_count) = (Aabb::empty(), 0);
+                let (mut right, mut right_count) = (Aabb::empty(), 0);
+                for b in 0..split {
+                    left = left.union(bucket_bounds[b]);
+                    left_count += bucket_counts[b];
+                }
+                for b in split..BUCKETS {
+                    right = right.union(bucket_bounds[b]);
+                    right_count += bucket_counts[b];
+                }
+                let cost = left.surface_area() * left_count as f32
+                    + right.surface_area() * right_count as f32;
+                if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                    best = Some((axis, split, cost));
+                }
+            }
+        }
+
+        // Keeps a leaf when splitting would not pay for the extra traversal
+        let leaf_cost = count as f32;
+        let (axis, split) = match best {
+            Some((axis, split, cost)) => {
+                let split_cost =
+                    TRAVERSAL_COST + cost / bounds.surface_area().max(f32::MIN_POSITIVE);
+                if count <= MAX_LEAF_SIZE && split_cost >= leaf_cost {
+                    return node;
+                }
+                (axis, split)
+            }
+            // All centroids coincide, so no split can separate them
+            None if count <= MAX_LEAF_SIZE => return node,
+            None => {
+                let mid = (start + end) / 2;
+                return self.finish_interior(primitives, node, start, mid, end);
+            }
+        };
+
+        // Partitions the indices by bucket, falling back to a median split
+        // if every primitive landed on one side
+        let mut mid = start;
+        for i in start..end {
+            let centroid = primitives[self.indices[i] as usize].centroid;
+            if bucket(centroid, &centroid_bounds, axis) < split {
+                self.indices.swap(i, mid);
+                mid += 1;
+            }
+        }
+        if mid == start || mid == end {
+            mid = (start + end) / 2;
+        }
+        self.finish_interior(primitives, node, start, mid, end)
+    }
+
+    /// Builds both children of an interior node
+    fn finish_interior(
+        &mut self,
+        primitives: &[BuildPrimitive],
+        node: usize,
+        start: usize,
+        mid: usize,
+        end: usize,
+    ) -> usize {
+        self.build(primitives, start, mid);
+        let second = self.build(primitives, mid, end);
+        self.nodes[node].first = second as u32;
+        self.nodes[node].count = 0;
+        node
+    }
+
+    /// Finds the closest primitive hit along the ray
+    ///
+    /// `intersect` tests a single primitive by index and `distance` reads
+    /// the hit distance back out of its result, so only primitives whose
+    /// bounds are entered before the closest hit so far are ever tested.
+    pub fn closest_hit<H, I, D>(&self, ray: Ray, mut intersect: I, distance: D) -> Option<H>
+    where
+        I: FnMut(usize) -> Option<H>,
+        D: Fn(&H) -> f32,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let direction = ray.direction();
+        let inv_direction = Vector3::new(1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z);
+
+        let mut closest: Option<H> = None;
+        let mut closest_distance = f32::INFINITY;
+
+        // Nodes waiting to be visited along with the distance their bounds
+        // are entered at
+        let mut stack: Vec<(usize, f32)> = Vec::with_capacity(64);
+        if let Some(near) = self.nodes[0]
+            .bounds
+            .intersect(ray, inv_direction, closest_distance)
+        {
+            stack.push((0, near));
+        }
+
+        while let Some((index, near)) = stack.pop() {
+            // Skips nodes that start behind a hit found since they were pushed
+            if near >= closest_distance {
+                continue;
+            }
+
+            let node = &self.nodes[index];
+            if node.count > 0 {
+                // Tests every primitive in the leaf
+                let first = node.first as usize;
+                for &primitive in &self.indices[first..first + node.count as usize] {
+                    if let Some(hit) = intersect(primitive as usize) {
+                        let d = distance(&hit);
+                        if d < closest_distance {
+                            closest_distance = d;
+                            closest = Some(hit);
+                        }
+                    }
+                }
+            } else {
+                // Pushes the nearer child last so it is visited first
+                let first = index + 1;
+                let second = node.first as usize;
+                let first_near =
+                    self.nodes[first]
+                        .bounds
+                        .intersect(ray, inv_direction, closest_distance);
+                let second_near =
+                    self.nodes[second]
+                        .bounds
+                        .intersect(ray, inv_direction, closest_distance);
+                match (first_near, second_near) {
+                    (Some(a), Some(b)) if b < a => {
+                        stack.push((first, a));
+                        stack.push((second, b));
+                    }
+                    (Some(a), Some(b)) => {
+                        stack.push((second, b));
+                        stack.push((first, a));
+                    }
+                    (Some(a), None) => stack.push((first, a)),
+                    (None, Some(b)) => stack.push((second, b)),
+                    (None, None) => {}
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+/// Gets the bucket a centroid falls into along an axis
+fn bucket(centroid: Vector3, centroid_bounds: &Aabb, axis: usize) -> usize {
+    let min = centroid_bounds.min()[axis];
+    let extent = centroid_bounds.max()[axis] - min;
+    let b = ((centroid[axis] - min) / extent * BUCKETS as f32) as usize;
+    b.min(BUCKETS - 1)
+}
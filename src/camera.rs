@@ -1,34 +1,131 @@
+use std::f32::consts::PI;
+
+use crate::ray::Ray;
+use crate::sampling::{concentric_disk, orthonormal_basis, regular_polygon};
 use crate::vector::Vector3;
 
+/// Describes how a camera maps the image onto rays
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Projection {
+    /// Rays spread out from the camera's position through a flat image
+    /// spanning the field of view, like a pinhole camera
+    #[default]
+    Perspective,
+    /// Parallel rays leave from a flat image `width` units across, so
+    /// objects keep their size however far away they are
+    Orthographic { width: f32 },
+    /// An equidistant fisheye, whose angle from the view direction grows in
+    /// step with the distance from the image center, with the field of view
+    /// across a circle filling the image's shorter side
+    Fisheye,
+    /// A full panorama, with longitude across the image and latitude up it,
+    /// ignoring the field of view
+    Equirectangular,
+}
+
 /// Represents state of the Viewport Camera
+///
+/// The camera looks along its direction with an orthonormal basis of right
+/// and up vectors, so the image never skews however the camera is turned.
+/// It is a perspective pinhole camera with everything in focus until it is
+/// given another projection with `with_projection` or a lens with
+/// `with_lens`.
 #[derive(Debug, Copy, Clone)]
 pub struct Camera {
     position: Vector3,
+    direction: Vector3,
+    right: Vector3,
     up: Vector3,
     fov: f32,
-    pitch: f32,
-    yaw: f32,
+    projection: Projection,
+    aperture: f32,
+    focus_distance: f32,
+    blades: u32,
 }
 
 impl Camera {
-    /// Creates a new camera with given parameters
+    /// Creates a new camera turned by `pitch` degrees up and `yaw` degrees
+    /// to the right of the z axis, with `up` as the rough up direction of
+    /// the image
     pub fn new(position: Vector3, up: Vector3, fov: f32, pitch: f32, yaw: f32) -> Camera {
+        let x = yaw.to_radians().sin() * pitch.to_radians().cos();
+        let y = pitch.to_radians().sin();
+        let z = yaw.to_radians().cos() * pitch.to_radians().cos();
+        Camera::with_direction(position, Vector3::new(x, y, z), up, fov)
+    }
+
+    /// Creates a new camera at `eye` looking towards `target`, with `up` as
+    /// the rough up direction of the image
+    pub fn look_at(eye: Vector3, target: Vector3, up: Vector3, fov: f32) -> Camera {
+        Camera::with_direction(eye, target - eye, up, fov)
+    }
+
+    /// Creates a new camera looking along `direction`, building its basis
+    /// from the part of `up` perpendicular to the direction
+    ///
+    /// If `up` is parallel to the direction, any perpendicular up is used.
+    pub fn with_direction(position: Vector3, direction: Vector3, up: Vector3, fov: f32) -> Camera {
+        let direction = direction.normalize();
+        let right = up.cross(direction);
+        let right = if right.len() > 1e-6 {
+            right.normalize()
+        } else {
+            orthonormal_basis(direction).0
+        };
         Camera {
             position,
-            up,
+            direction,
+            right,
+            up: direction.cross(right),
             fov,
-            pitch,
-            yaw,
+            projection: Projection::Perspective,
+            aperture: 0.0,
+            focus_distance: 1.0,
+            blades: 0,
+        }
+    }
+
+    /// Gets a copy of the camera rolled `degrees` clockwise about its view
+    /// direction, turning the image counter-clockwise
+    pub fn with_roll(self, degrees: f32) -> Camera {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Camera {
+            right: self.right * cos - self.up * sin,
+            up: self.up * cos + self.right * sin,
+            ..self
+        }
+    }
+
+    /// Gets a copy of the camera with a different projection
+    pub fn with_projection(self, projection: Projection) -> Camera {
+        Camera { projection, ..self }
+    }
+
+    /// Gets a copy of the camera with a thin lens of radius `aperture`,
+    /// focused on the plane `focus_distance` in front of it
+    ///
+    /// Points off the focal plane blur more the wider the aperture is, and
+    /// an aperture of 0 keeps everything in focus. Lenses only affect
+    /// perspective and orthographic projections.
+    pub fn with_lens(self, aperture: f32, focus_distance: f32) -> Camera {
+        Camera {
+            aperture,
+            focus_distance,
+            ..self
         }
     }
 
-    /// Calculates the direction vector of the Camera
+    /// Gets a copy of the camera whose aperture is a regular polygon with
+    /// `blades` sides, shaping out of focus highlights like a real iris
+    ///
+    /// Fewer than 3 blades give a round aperture.
+    pub fn with_blades(self, blades: u32) -> Camera {
+        Camera { blades, ..self }
+    }
+
+    /// Gets the unit direction the Camera looks along
     pub fn direction(&self) -> Vector3 {
-        let x = self.yaw.to_radians().sin() * self.pitch.to_radians().cos();
-        let y = self.pitch.to_radians().sin();
-        let z = self.yaw.to_radians().cos() * self.pitch.to_radians().cos();
-        let out = Vector3::new(x, y, z);
-        out.normalize()
+        self.direction
     }
 
     /// Gets the position of the Camera
@@ -36,13 +133,126 @@ impl Camera {
         self.position
     }
 
-    /// Gets the position of the Camera
+    /// Gets the unit vector pointing right across the image
+    pub fn right(&self) -> Vector3 {
+        self.right
+    }
+
+    /// Gets the unit vector pointing up the image, which is perpendicular
+    /// to the view direction
     pub fn up(&self) -> Vector3 {
         self.up
     }
 
-    /// Gets the position of the Camera
+    /// Gets the horizontal field of view of the Camera in degrees
     pub fn fov(&self) -> f32 {
         self.fov
     }
+
+    /// Gets how the Camera maps the image onto rays
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Gets the radius of the lens, which is 0 for a pinhole camera
+    pub fn aperture(&self) -> f32 {
+        self.aperture
+    }
+
+    /// Gets the distance from the camera to the plane in perfect focus
+    pub fn focus_distance(&self) -> f32 {
+        self.focus_distance
+    }
+
+    /// Gets the number of sides of the aperture, or 0 if it is round
+    pub fn blades(&self) -> u32 {
+        self.blades
+    }
+
+    /// Generates the ray through a point of a `width` by `height` image, or
+    /// returns None if the point is outside a fisheye's image circle
+    ///
+    /// `x` and `y` are measured in pixels from the top left pixel's center,
+    /// so the image center is at ((width - 1) / 2, (height - 1) / 2).
+    pub fn ray(&self, x: f32, y: f32, width: u32, height: u32) -> Option<Ray> {
+        // Measures from the image center, running from -1 to 1 across the
+        // image and keeping pixels square, so single pixel images look
+        // straight ahead
+        let aspect = width as f32 / height as f32;
+        let sx = (x - (width - 1) as f32 / 2.0) * 2.0 / (width - 1).max(1) as f32;
+        let sy = ((height - 1) as f32 / 2.0 - y) * 2.0 / aspect / (height - 1).max(1) as f32;
+
+        match self.projection {
+            Projection::Perspective => {
+                let half_width = (self.fov / 2.0).to_radians().tan();
+                let offset = (self.right * sx + self.up * sy) * half_width;
+                Some(Ray::new(
+                    self.position,
+                    (self.direction + offset).normalize(),
+                ))
+            }
+            Projection::Orthographic { width } => {
+                let offset = (self.right * sx + self.up * sy) * (width / 2.0);
+                Some(Ray::new(self.position + offset, self.direction))
+            }
+            Projection::Fisheye => {
+                // Scales so the image circle fits inside the shorter side
+                let radius = (sx * sx + sy * sy).sqrt() / (1.0 / aspect).min(1.0);
+                if radius > 1.0 {
+                    return None;
+                }
+                let theta = radius * (self.fov / 2.0).to_radians();
+                let across = self.right * sx + self.up * sy;
+                let across = if across.len() > 0.0 {
+                    across.normalize()
+                } else {
+                    across
+                };
+                Some(Ray::new(
+                    self.position,
+                    self.direction * theta.cos() + across * theta.sin(),
+                ))
+            }
+            Projection::Equirectangular => {
+                // Pixels cover the panorama edge to edge, with the view
+                // direction at its center
+                let longitude = ((x + 0.5) / width as f32 - 0.5) * 2.0 * PI;
+                let latitude = (0.5 - (y + 0.5) / height as f32) * PI;
+                let level = self.direction * longitude.cos() + self.right * longitude.sin();
+                Some(Ray::new(
+                    self.position,
+                    level * latitude.cos() + self.up * latitude.sin(),
+                ))
+            }
+        }
+    }
+
+    /// Generates a ray through a point of the image from a point on the
+    /// lens picked by `u` and `v` in 0..1, or returns None if the point is
+    /// outside a fisheye's image circle
+    ///
+    /// Every ray through the same image point meets on the focal plane, so
+    /// only objects on it are sharp. Without a lens, or for fisheye and
+    /// panoramic projections, this is the same as `ray`.
+    pub fn lens_ray(&self, x: f32, y: f32, width: u32, height: u32, u: f32, v: f32) -> Option<Ray> {
+        let pinhole = self.ray(x, y, width, height)?;
+        let focusable = match self.projection {
+            Projection::Perspective | Projection::Orthographic { .. } => true,
+            Projection::Fisheye | Projection::Equirectangular => false,
+        };
+        if self.aperture <= 0.0 || !focusable {
+            return Some(pinhole);
+        }
+
+        let (lens_x, lens_y) = if self.blades >= 3 {
+            regular_polygon(self.blades, u, v)
+        } else {
+            concentric_disk(u, v)
+        };
+        let direction = pinhole.direction();
+        let focus =
+            pinhole.origin() + direction * (self.focus_distance / direction.dot(self.direction));
+        let origin = pinhole.origin() + (self.right * lens_x + self.up * lens_y) * self.aperture;
+        Some(Ray::new(origin, (focus - origin).normalize()))
+    }
 }
@@ -0,0 +1,257 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::film::{Film, FilmPixel};
+use crate::renderer::Progress;
+use crate::scene::Scene;
+use crate::settings::RenderSettings;
+
+/// Identifies checkpoint files and the version of their layout
+const MAGIC: &[u8; 8] = b"RTCKPT02";
+
+/// Describes why a checkpoint could not be saved or loaded
+#[derive(Debug)]
+pub enum CheckpointError {
+    /// The file could not be read or written
+    Io(PathBuf, io::Error),
+    /// The file is not a checkpoint, or is cut short
+    Invalid(PathBuf),
+    /// The checkpoint was saved from a different scene, or with settings
+    /// that sample differently
+    Mismatch,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckpointError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            CheckpointError::Invalid(path) => {
+                write!(f, "{}: not a valid checkpoint", path.display())
+            }
+            CheckpointError::Mismatch => write!(
+                f,
+                "checkpoint was saved from a different scene, or with a different \
+                 size, seed, filter, integrator, bounce limit, or pass size"
+            ),
+        }
+    }
+}
+
+impl Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CheckpointError::Io(_, err) => Some(err),
+            CheckpointError::Invalid(_) | CheckpointError::Mismatch => None,
+        }
+    }
+}
+
+/// The state of a progressive render between passes, which a render can be
+/// resumed from after the process stops
+///
+/// Every pass draws its random numbers from streams picked by the seed,
+/// the pass, and the pixel, so the film's sums and the number of passes are
+/// all the random state there is. Resuming with the same scene and settings
+/// adds exactly the passes an uninterrupted render would have, giving a
+/// bit-identical film.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    fingerprint: u64,
+    film: Film,
+    progress: Progress,
+}
+
+impl Checkpoint {
+    /// Creates a checkpoint of a render of the scene with the given settings
+    pub fn new(
+        settings: &RenderSettings,
+        scene: &Scene,
+        film: Film,
+        progress: Progress,
+    ) -> Checkpoint {
+        Checkpoint {
+            fingerprint: fingerprint(settings, scene),
+            film,
+            progress,
+        }
+    }
+
+    /// Gets the film rendered so far
+    pub fn film(&self) -> &Film {
+        &self.film
+    }
+
+    /// Gets how far the render had come
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    /// Checks whether resuming the scene with the given settings would
+    /// continue the render exactly
+    ///
+    /// The scene must have the same fingerprint, so scenes loaded from files
+    /// must be loaded from the same bytes. The sample count, time limit, and
+    /// noise threshold may change, so a finished render can be resumed to
+    /// take more samples.
+    pub fn matches(&self, settings: &RenderSettings, scene: &Scene) -> bool {
+        self.fingerprint == fingerprint(settings, scene)
+    }
+
+    /// Splits the checkpoint into its film and progress
+    pub fn into_parts(self) -> (Film, Progress) {
+        (self.film, self.progress)
+    }
+
+    /// Saves the checkpoint to a file
+    ///
+    /// The checkpoint is written next to the file and then moved over it,
+    /// so the previous checkpoint survives if the process stops halfway.
+    pub fn save(&self, path: &Path) -> Result<(), CheckpointError> {
+        let io_error = |err| CheckpointError::Io(path.to_path_buf(), err);
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+
+        let mut w = BufWriter::new(File::create(&partial).map_err(io_error)?);
+        self.write(&mut w).map_err(io_error)?;
+        w.into_inner()
+            .map_err(|err| io_error(err.into_error()))?
+            .sync_all()
+            .map_err(io_error)?;
+        fs::rename(&partial, path).map_err(io_error)
+    }
+
+    /// Loads a checkpoint saved by `save`
+    pub fn load(path: &Path) -> Result<Checkpoint, CheckpointError> {
+        let file = File::open(path).map_err(|err| CheckpointError::Io(path.to_path_buf(), err))?;
+        Checkpoint::read(BufReader::new(file)).map_err(|err| match err.kind() {
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+                CheckpointError::Invalid(path.to_path_buf())
+            }
+            _ => CheckpointError::Io(path.to_path_buf(), err),
+        })
+    }
+
+    /// Writes the checkpoint, with every number in little-endian order
+    pub fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let progress = &self.progress;
+        let elapsed = u64::try_from(progress.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        w.write_all(MAGIC)?;
+        w.write_all(&self.fingerprint.to_le_bytes())?;
+        for value in &[
+            self.film.width(),
+            self.film.height(),
+            progress.passes(),
+            progress.samples(),
+        ] {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        w.write_all(&elapsed.to_le_bytes())?;
+        for pixel in self.film.pixels() {
+            for bits in &pixel.to_bits() {
+                w.write_all(&bits.to_le_bytes())?;
+            }
+        }
+        w.flush()
+    }
+
+    /// Reads a checkpoint written by `write`
+    pub fn read<R: Read>(mut r: R) -> io::Result<Checkpoint> {
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a checkpoint",
+            ));
+        }
+        let fingerprint = read_u64(&mut r)?;
+        let width = read_u32(&mut r)?;
+        let height = read_u32(&mut r)?;
+        let passes = read_u32(&mut r)?;
+        let samples = read_u32(&mut r)?;
+        let elapsed = Duration::from_nanos(read_u64(&mut r)?);
+
+        let count = u64::from(width) * u64::from(height);
+        if count > u64::from(u32::MAX) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "film is too large",
+            ));
+        }
+        // Grows the film as pixels are read, so a corrupt size can't
+        // allocate more memory than the file holds
+        let mut pixels = Vec::with_capacity(count.min(1 << 20) as usize);
+        for _ in 0..count {
+            let mut bits = [0; FilmPixel::BITS];
+            for value in &mut bits {
+                *value = read_u32(&mut r)?;
+            }
+            pixels.push(FilmPixel::from_bits(bits));
+        }
+
+        let film = Film::from_pixels(width, height, pixels);
+        let progress = Progress::new(passes, samples, elapsed, film.noise());
+        Ok(Checkpoint {
+            fingerprint,
+            film,
+            progress,
+        })
+    }
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    r.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    r.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Hashes the scene and the settings that decide which samples each pass
+/// takes
+fn fingerprint(settings: &RenderSettings, scene: &Scene) -> u64 {
+    let description = format!(
+        "{}x{} seed {} pass {} bounces {} {:?} {:?} scene {:016x}",
+        settings.width,
+        settings.height,
+        settings.seed,
+        settings.pass_samples,
+        settings.bounces,
+        settings.filter,
+        settings.integrator,
+        scene.fingerprint()
+    );
+    let mut hash = Fnv::new();
+    hash.write(description.as_bytes());
+    hash.finish()
+}
+
+/// 64-bit FNV-1a, which hashes the same on every run and platform
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    pub(crate) fn finish(self) -> u64 {
+        self.0
+    }
+}
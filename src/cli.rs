@@ -0,0 +1,386 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use raytracer::{Filter, Integrator, OutputFormat, RenderSettings, ToneMap};
+
+/// Help text printed for `--help`
+pub const USAGE: &str = "\
+Renders a scene description file to an image.
+
+Usage: raytracer [OPTIONS] <SCENE>
+
+Arguments:
+  <SCENE>  Path to the TOML scene description file
+
+Options:
+  -W, --width <PIXELS>        Output image width
+  -H, --height <PIXELS>       Output image height
+  -t, --threads <COUNT>       Number of render threads [default: available cores]
+      --tile-size <PIXELS>    Width and height of the tiles threads render
+  -d, --max-depth <BOUNCES>   Maximum number of bounces per ray
+  -s, --samples <COUNT>       Number of samples per pixel, rounded up to a square
+  -p, --progressive           Render in passes until a limit is reached, with
+                              --samples as the most samples per pixel
+      --pass-samples <COUNT>  Number of samples per pixel in each pass
+      --time-limit <SECONDS>  Stop starting passes after this long [implies -p]
+      --noise <THRESHOLD>     Stop once the estimated relative noise of every
+                              pixel is at or below this [implies -p]
+      --preview-interval <SECONDS>
+                              Write the image so far this often while rendering
+      --checkpoint <FILE>     Save progressive renders to this file as they go
+                              and when they finish [implies -p]
+      --checkpoint-interval <SECONDS>
+                              Time between checkpoints, with --checkpoint
+                              [default: 60]
+      --resume <FILE>         Continue a progressive render from a checkpoint
+                              [implies -p]
+  -f, --filter <FILTER>       Pixel filter: box, tent, gaussian, or mitchell
+  -i, --integrator <NAME>     Light transport algorithm: whitted or path
+      --seed <SEED>           Seed for random sampling
+  -e, --exposure <STOPS>      Brightens PNG images by this many stops
+      --tone-map <NAME>       PNG tone mapper: clip, reinhard, extended_reinhard,
+                              hable, aces, or agx
+  -o, --output <FILE>         Output image path
+      --tile-times <FILE>     Write how long each tile took as CSV, for renders
+                              in a single pass
+      --format <FORMAT>       Output format: png, exr, exr-float, hdr, or pfm
+                              [default: from the output's extension]
+  -h, --help                  Print this help and exit
+
+Options given on the command line override the scene file's settings.";
+
+/// Describes why the command line could not be parsed
+#[derive(Debug)]
+pub enum CliError {
+    /// Help was requested instead of a render
+    Help,
+    /// The arguments were invalid
+    Invalid(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Help => write!(f, "help requested"),
+            CliError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Options given on the command line
+#[derive(Debug, Default)]
+pub struct Options {
+    pub scene: PathBuf,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub threads: Option<u32>,
+    pub tile_size: Option<u32>,
+    pub bounces: Option<u32>,
+    pub samples: Option<u32>,
+    pub progressive: bool,
+    pub pass_samples: Option<u32>,
+    pub time_limit: Option<Duration>,
+    pub noise_threshold: Option<f32>,
+    pub preview_interval: Option<Duration>,
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: Option<Duration>,
+    pub resume: Option<PathBuf>,
+    pub filter: Option<Filter>,
+    pub integrator: Option<Integrator>,
+    pub seed: Option<u64>,
+    pub exposure: Option<f32>,
+    pub tone_map: Option<ToneMap>,
+    pub output: Option<PathBuf>,
+    pub format: Option<OutputFormat>,
+    pub tile_times: Option<PathBuf>,
+}
+
+impl Options {
+    /// Parses the command line arguments, not including the program name
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, CliError> {
+        let mut options = Options::default();
+        let mut scene: Option<PathBuf> = None;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Splits `--flag=value` into a flag and an inline value
+            let (flag, inline) = match arg.find('=') {
+                Some(i) if arg.starts_with("--") => {
+                    (arg[..i].to_string(), Some(arg[i + 1..].to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || -> Result<String, CliError> {
+                match inline.clone().or_else(|| args.next()) {
+                    Some(value) => Ok(value),
+                    None => Err(CliError::Invalid(format!("{} needs a value", flag))),
+                }
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => return Err(CliError::Help),
+                "-W" | "--width" => options.width = Some(parse_positive(&flag, &value()?)?),
+                "-H" | "--height" => options.height = Some(parse_positive(&flag, &value()?)?),
+                "-t" | "--threads" => options.threads = Some(parse_positive(&flag, &value()?)?),
+                "--tile-size" => options.tile_size = Some(parse_positive(&flag, &value()?)?),
+                "-d" | "--max-depth" => options.bounces = Some(parse_value(&flag, &value()?)?),
+                "-s" | "--samples" => options.samples = Some(parse_positive(&flag, &value()?)?),
+                "-p" | "--progressive" => options.progressive = true,
+                "--pass-samples" => options.pass_samples = Some(parse_positive(&flag, &value()?)?),
+                "--time-limit" => {
+                    let seconds = parse_positive_float(&flag, &value()?)?;
+                    options.time_limit =
+                        Some(Duration::try_from_secs_f32(seconds).unwrap_or(Duration::MAX));
+                }
+                "--noise" => {
+                    options.noise_threshold = Some(parse_positive_float(&flag, &value()?)?)
+                }
+                "--checkpoint" => options.checkpoint = Some(PathBuf::from(value()?)),
+                "--checkpoint-interval" => {
+                    let seconds = parse_positive_float(&flag, &value()?)?;
+                    options.checkpoint_interval =
+                        Some(Duration::try_from_secs_f32(seconds).unwrap_or(Duration::MAX));
+                }
+                "--resume" => options.resume = Some(PathBuf::from(value()?)),
+                "--preview-interval" => {
+                    let seconds = parse_positive_float(&flag, &value()?)?;
+                    options.preview_interval =
+                        Some(Duration::try_from_secs_f32(seconds).unwrap_or(Duration::MAX));
+                }
+                "-f" | "--filter" => {
+                    let name = value()?;
+                    options.filter =
+                        Some(Filter::from_name(&name).ok_or_else(|| {
+                            CliError::Invalid(format!("unknown filter `{}`", name))
+                        })?);
+                }
+                "-i" | "--integrator" => {
+                    let name = value()?;
+                    options.integrator = Some(Integrator::from_name(&name).ok_or_else(|| {
+                        CliError::Invalid(format!("unknown integrator `{}`", name))
+                    })?);
+                }
+                "--seed" => options.seed = Some(parse_value(&flag, &value()?)?),
+                "-e" | "--exposure" => {
+                    options.exposure = Some(parse_finite_float(&flag, &value()?)?)
+                }
+                "--tone-map" => {
+                    let name = value()?;
+                    options.tone_map = Some(ToneMap::from_name(&name).ok_or_else(|| {
+                        CliError::Invalid(format!("unknown tone map `{}`", name))
+                    })?);
+                }
+                "-o" | "--output" => options.output = Some(PathBuf::from(value()?)),
+                "--tile-times" => options.tile_times = Some(PathBuf::from(value()?)),
+                "--format" => {
+                    let name = value()?;
+                    options.format = Some(OutputFormat::from_name(&name).ok_or_else(|| {
+                        CliError::Invalid(format!("unknown output format `{}`", name))
+                    })?);
+                }
+                _ if flag.starts_with('-') && flag.len() > 1 => {
+                    return Err(CliError::Invalid(format!("unknown option {}", flag)))
+                }
+                _ => {
+                    if scene.is_some() {
+                        return Err(CliError::Invalid(format!("unexpected argument {}", arg)));
+                    }
+                    scene = Some(PathBuf::from(arg));
+                }
+            }
+        }
+
+        // Options that only matter alongside another one
+        if options.checkpoint_interval.is_some() && options.checkpoint.is_none() {
+            return Err(CliError::Invalid(
+                "--checkpoint-interval needs --checkpoint".to_string(),
+            ));
+        }
+
+        options.scene =
+            scene.ok_or_else(|| CliError::Invalid("no scene file given".to_string()))?;
+        Ok(options)
+    }
+
+    /// Overrides the given render settings with any options that were set
+    pub fn apply(&self, settings: &mut RenderSettings) {
+        if let Some(width) = self.width {
+            settings.width = width;
+        }
+        if let Some(height) = self.height {
+            settings.height = height;
+        }
+        if let Some(threads) = self.threads {
+            settings.threads = threads;
+        }
+        if let Some(tile_size) = self.tile_size {
+            settings.tile_size = tile_size;
+        }
+        if let Some(bounces) = self.bounces {
+            settings.bounces = bounces;
+        }
+        if let Some(samples) = self.samples {
+            settings.samples = samples;
+        }
+        if self.progressive
+            || self.time_limit.is_some()
+            || self.noise_threshold.is_some()
+            || self.checkpoint.is_some()
+            || self.resume.is_some()
+        {
+            settings.progressive = true;
+        }
+        if let Some(pass_samples) = self.pass_samples {
+            settings.pass_samples = pass_samples;
+        }
+        if let Some(time_limit) = self.time_limit {
+            settings.time_limit = Some(time_limit);
+        }
+        if let Some(noise_threshold) = self.noise_threshold {
+            settings.noise_threshold = Some(noise_threshold);
+        }
+        if let Some(preview_interval) = self.preview_interval {
+            settings.preview_interval = Some(preview_interval);
+        }
+        if let Some(filter) = self.filter {
+            settings.filter = filter;
+        }
+        if let Some(integrator) = self.integrator {
+            settings.integrator = integrator;
+        }
+        if let Some(seed) = self.seed {
+            settings.seed = seed;
+        }
+        if let Some(exposure) = self.exposure {
+            settings.output.exposure = exposure;
+        }
+        if let Some(tone_map) = self.tone_map {
+            settings.output.tone_map = tone_map;
+        }
+    }
+}
+
+/// Parses an option's value
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Invalid(format!("invalid value `{}` for {}", value, flag)))
+}
+
+/// Parses an option's value, requiring it to be at least 1
+fn parse_positive(flag: &str, value: &str) -> Result<u32, CliError> {
+    match parse_value(flag, value)? {
+        0 => Err(CliError::Invalid(format!("{} must be at least 1", flag))),
+        n => Ok(n),
+    }
+}
+
+/// Parses an option's value, requiring it to be a finite number
+fn parse_finite_float(flag: &str, value: &str) -> Result<f32, CliError> {
+    match parse_value::<f32>(flag, value)? {
+        n if n.is_finite() => Ok(n),
+        _ => Err(CliError::Invalid(format!(
+            "{} must be a finite number",
+            flag
+        ))),
+    }
+}
+
+/// Parses an option's value, requiring it to be a number greater than 0
+fn parse_positive_float(flag: &str, value: &str) -> Result<f32, CliError> {
+    match parse_value::<f32>(flag, value)? {
+        n if n.is_finite() && n > 0.0 => Ok(n),
+        _ => Err(CliError::Invalid(format!(
+            "{} must be greater than 0",
+            flag
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, CliError> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn error(args: &[&str]) -> String {
+        match parse(args) {
+            Err(CliError::Invalid(message)) => message,
+            other => panic!("expected an error for {:?}, got {:?}", args, other),
+        }
+    }
+
+    #[test]
+    fn values_can_follow_the_flag_or_an_equals_sign() {
+        let options = parse(&["--width=640", "-H", "480", "--seed=7", "scene.toml"]).unwrap();
+        assert_eq!(options.width, Some(640));
+        assert_eq!(options.height, Some(480));
+        assert_eq!(options.seed, Some(7));
+        assert_eq!(options.scene, PathBuf::from("scene.toml"));
+
+        // Only the first equals sign splits the flag from its value
+        let options = parse(&["--output=a=b.png", "scene.toml"]).unwrap();
+        assert_eq!(options.output, Some(PathBuf::from("a=b.png")));
+    }
+
+    #[test]
+    fn missing_values_are_rejected() {
+        assert_eq!(error(&["scene.toml", "--width"]), "--width needs a value");
+        assert_eq!(error(&["scene.toml", "-o"]), "-o needs a value");
+        assert_eq!(
+            error(&["--width=", "scene.toml"]),
+            "invalid value `` for --width"
+        );
+    }
+
+    #[test]
+    fn unknown_options_are_rejected() {
+        assert_eq!(
+            error(&["--widht", "640", "scene.toml"]),
+            "unknown option --widht"
+        );
+        assert_eq!(error(&["scene.toml", "-x"]), "unknown option -x");
+        assert_eq!(
+            error(&["--colour=red", "scene.toml"]),
+            "unknown option --colour"
+        );
+    }
+
+    #[test]
+    fn only_one_scene_is_accepted() {
+        assert_eq!(error(&["a.toml", "b.toml"]), "unexpected argument b.toml");
+        assert_eq!(error(&["--width", "640"]), "no scene file given");
+    }
+
+    #[test]
+    fn exposure_must_be_finite() {
+        let options = parse(&["-e", "-1.5", "scene.toml"]).unwrap();
+        assert_eq!(options.exposure, Some(-1.5));
+        for value in &["NaN", "inf", "-inf"] {
+            assert_eq!(
+                error(&["--exposure", value, "scene.toml"]),
+                "--exposure must be a finite number"
+            );
+        }
+    }
+
+    #[test]
+    fn checkpoint_intervals_need_a_checkpoint() {
+        assert_eq!(
+            error(&["--checkpoint-interval", "30", "scene.toml"]),
+            "--checkpoint-interval needs --checkpoint"
+        );
+        let options = parse(&[
+            "--checkpoint-interval=30",
+            "--checkpoint",
+            "render.ckpt",
+            "scene.toml",
+        ])
+        .unwrap();
+        assert_eq!(options.checkpoint_interval, Some(Duration::from_secs(30)));
+    }
+}
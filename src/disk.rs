@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::intersectable::Intersectable;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::rayhit::RayHit;
+use crate::sampling::orthonormal_basis;
+use crate::vector::Vector3;
+
+/// Smallest cosine between a ray and the disk's plane before the ray is
+/// considered parallel to it
+const EPSILON: f32 = 1e-7;
+
+/// A flat circular disk with a center, normal, radius, and material, which
+/// can be hit from either side
+#[derive(Debug)]
+pub struct Disk {
+    center: Vector3,
+    normal: Vector3,
+    radius: f32,
+    material: Arc<dyn Material>,
+}
+
+impl Disk {
+    /// Creates a new disk facing along the given normal
+    pub fn new(center: Vector3, normal: Vector3, radius: f32, material: Arc<dyn Material>) -> Disk {
+        Disk {
+            center,
+            normal: normal.normalize(),
+            radius,
+            material,
+        }
+    }
+}
+
+impl Intersectable for Disk {
+    /// Determines whether the given ray has intersected with the disk
+    /// and generates a RayHit
+    fn intersect(&self, ray: Ray) -> Option<RayHit<'_>> {
+        let denom = self.normal.dot(ray.direction());
+        if denom.abs() < EPSILON {
+            return None;
+        }
+        let t = (self.center - ray.origin()).dot(self.normal) / denom;
+        if t < 0.0 {
+            return None;
+        }
+
+        // Checks the hit on the disk's plane lies within its radius
+        let position = ray.origin() + ray.direction() * t;
+        let offset = position - self.center;
+        if offset.dot(offset) > self.radius * self.radius {
+            return None;
+        }
+
+        // Fits the texture to the square around the disk
+        let (tangent, bitangent) = orthonormal_basis(self.normal);
+        let scale = 0.5 / self.radius;
+        let uv = (
+            0.5 + offset.dot(tangent) * scale,
+            0.5 + offset.dot(bitangent) * scale,
+        );
+
+        let hit = RayHit::new(position, self.normal, uv, t, &*self.material);
+        Some(hit.with_tangents(tangent, bitangent))
+    }
+
+    /// Gets the box around the disk, which is flat along its normal
+    fn bounds(&self) -> Aabb {
+        let n = self.normal;
+        let extent = Vector3::new(
+            (1.0 - n.x * n.x).max(0.0).sqrt(),
+            (1.0 - n.y * n.y).max(0.0).sqrt(),
+            (1.0 - n.z * n.z).max(0.0).sqrt(),
+        ) * self.radius;
+        Aabb::new(self.center - extent, self.center + extent)
+    }
+}
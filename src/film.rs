@@ -0,0 +1,319 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use crate::image::Image;
+use crate::output::{self, ExrChannel, OutputFormat};
+use crate::pixel::Pixel;
+use crate::tile::Tile;
+use crate::tonemap::{luminance, OutputTransform};
+use crate::vector::Vector3;
+
+/// The filtered samples gathered for a single pixel
+///
+/// Samples with positive and negative filter weights are summed apart, so
+/// the pixel's color stays well defined however few samples it has.
+/// Alongside them, the pixel keeps the count, sum, and sum of squares of its
+/// samples' luminance, to estimate how noisy it still is.
+#[derive(Debug, Copy, Clone)]
+pub struct FilmPixel {
+    color_sum: Vector3,
+    weight_sum: f32,
+    negative_color_sum: Vector3,
+    negative_weight_sum: f32,
+    samples: u32,
+    luminance_sum: f32,
+    luminance_squares: f32,
+}
+
+impl FilmPixel {
+    /// Creates a pixel without any samples
+    pub fn new() -> FilmPixel {
+        FilmPixel {
+            color_sum: Vector3::origin(),
+            weight_sum: 0.0,
+            negative_color_sum: Vector3::origin(),
+            negative_weight_sum: 0.0,
+            samples: 0,
+            luminance_sum: 0.0,
+            luminance_squares: 0.0,
+        }
+    }
+
+    /// Adds a sample with the given weight from a `FilterSampler`, which is
+    /// negative for samples in the filter's negative lobes
+    pub fn add_sample(&mut self, color: Vector3, weight: f32) {
+        if weight >= 0.0 {
+            self.color_sum = self.color_sum + color * weight;
+            self.weight_sum += weight;
+        } else {
+            self.negative_color_sum = self.negative_color_sum - color * weight;
+            self.negative_weight_sum -= weight;
+        }
+
+        let luminance = luminance(color);
+        self.samples += 1;
+        self.luminance_sum += luminance;
+        self.luminance_squares += luminance * luminance;
+    }
+
+    /// Adds all the samples gathered by another pixel, such as those from
+    /// a later pass
+    pub fn merge(&mut self, other: &FilmPixel) {
+        self.color_sum = self.color_sum + other.color_sum;
+        self.weight_sum += other.weight_sum;
+        self.negative_color_sum = self.negative_color_sum + other.negative_color_sum;
+        self.negative_weight_sum += other.negative_weight_sum;
+        self.samples += other.samples;
+        self.luminance_sum += other.luminance_sum;
+        self.luminance_squares += other.luminance_squares;
+    }
+
+    /// Number of words in the raw bits of a pixel
+    pub(crate) const BITS: usize = 11;
+
+    /// Gets the pixel's sums as raw bits, which restore it exactly with
+    /// `from_bits`
+    pub(crate) fn to_bits(self) -> [u32; FilmPixel::BITS] {
+        [
+            self.color_sum.x.to_bits(),
+            self.color_sum.y.to_bits(),
+            self.color_sum.z.to_bits(),
+            self.weight_sum.to_bits(),
+            self.negative_color_sum.x.to_bits(),
+            self.negative_color_sum.y.to_bits(),
+            self.negative_color_sum.z.to_bits(),
+            self.negative_weight_sum.to_bits(),
+            self.samples,
+            self.luminance_sum.to_bits(),
+            self.luminance_squares.to_bits(),
+        ]
+    }
+
+    /// Restores a pixel from the bits given by `to_bits`
+    pub(crate) fn from_bits(bits: [u32; FilmPixel::BITS]) -> FilmPixel {
+        let vector = |i: usize| {
+            Vector3::new(
+                f32::from_bits(bits[i]),
+                f32::from_bits(bits[i + 1]),
+                f32::from_bits(bits[i + 2]),
+            )
+        };
+        FilmPixel {
+            color_sum: vector(0),
+            weight_sum: f32::from_bits(bits[3]),
+            negative_color_sum: vector(4),
+            negative_weight_sum: f32::from_bits(bits[7]),
+            samples: bits[8],
+            luminance_sum: f32::from_bits(bits[9]),
+            luminance_squares: f32::from_bits(bits[10]),
+        }
+    }
+
+    /// Gets the number of samples taken of the pixel
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Estimates the standard error of the pixel's mean luminance relative
+    /// to the luminance itself, or returns None with fewer than 2 samples
+    ///
+    /// Pixels darker than [`DARK_LUMINANCE`] are measured against it, so
+    /// faint noise in near black pixels doesn't count as large errors.
+    pub fn relative_error(&self) -> Option<f32> {
+        if self.samples < 2 {
+            return None;
+        }
+        let n = self.samples as f32;
+        let mean = self.luminance_sum / n;
+        let variance = ((self.luminance_squares / n - mean * mean) * n / (n - 1.0)).max(0.0);
+        Some((variance / n).sqrt() / mean.max(DARK_LUMINANCE))
+    }
+
+    /// Calculates the filtered color of the pixel, never negative
+    ///
+    /// The weighted averages of the positive and negative samples are
+    /// combined in proportion to the filter's positive and negative parts,
+    /// recovered from the average weight. When only one kind of sample has
+    /// been taken, it stands in for the other.
+    pub fn color(&self) -> Vector3 {
+        let average = |sum: Vector3, weight: f32| {
+            if weight > 0.0 {
+                Some(sum * (1.0 / weight))
+            } else {
+                None
+            }
+        };
+        let positive = average(self.color_sum, self.weight_sum);
+        let negative = average(self.negative_color_sum, self.negative_weight_sum);
+        let color = match (positive, negative) {
+            (Some(positive), Some(negative)) => {
+                // Weights average to the ratio of the filter's magnitude to
+                // its integral, which is 1 plus twice its negative part
+                let total = self.weight_sum + self.negative_weight_sum;
+                let ratio = (total / self.samples as f32).max(1.0);
+                positive * ((ratio + 1.0) * 0.5) - negative * ((ratio - 1.0) * 0.5)
+            }
+            (Some(color), None) | (None, Some(color)) => color,
+            (None, None) => Vector3::origin(),
+        };
+        Vector3::new(color.x.max(0.0), color.y.max(0.0), color.z.max(0.0))
+    }
+}
+
+impl Default for FilmPixel {
+    fn default() -> FilmPixel {
+        FilmPixel::new()
+    }
+}
+
+/// Luminance below which pixels' noise is measured as if they were this
+/// bright
+pub const DARK_LUMINANCE: f32 = 0.01;
+
+/// Floating point buffer that pixel samples are accumulated into before
+/// being converted into an image
+#[derive(Debug, Clone)]
+pub struct Film {
+    width: u32,
+    height: u32,
+    pixels: Vec<FilmPixel>,
+}
+
+impl Film {
+    /// Creates an empty film of the given size
+    pub fn new(width: u32, height: u32) -> Film {
+        Film {
+            width,
+            height,
+            pixels: vec![FilmPixel::new(); (width * height) as usize],
+        }
+    }
+
+    /// Creates a film from its pixels in row-major order
+    pub(crate) fn from_pixels(width: u32, height: u32, pixels: Vec<FilmPixel>) -> Film {
+        assert_eq!(pixels.len(), (width * height) as usize);
+        Film {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Gets all pixels in row-major order
+    pub(crate) fn pixels(&self) -> &[FilmPixel] {
+        &self.pixels
+    }
+
+    /// Gets the width of the film in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Gets the height of the film in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Gets the filtered color of the pixel at the given column and row
+    pub fn color(&self, x: u32, y: u32) -> Vector3 {
+        self.pixels[(y * self.width + x) as usize].color()
+    }
+
+    /// Gets the samples gathered for the pixel at the given column and row
+    pub fn pixel(&self, x: u32, y: u32) -> &FilmPixel {
+        &self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Adds the samples of the pixels covered by a tile, given in rows from
+    /// the top left
+    pub(crate) fn add_tile(&mut self, tile: Tile, pixels: &[FilmPixel]) {
+        let rows = pixels.chunks(tile.width() as usize);
+        for (y, row) in (tile.y()..tile.y() + tile.height()).zip(rows) {
+            let start = (y * self.width + tile.x()) as usize;
+            for (pixel, sample) in self.pixels[start..start + row.len()].iter_mut().zip(row) {
+                pixel.merge(sample);
+            }
+        }
+    }
+
+    /// Estimates how noisy the film still is, as the largest relative error
+    /// of any pixel's luminance, or returns None until every pixel has at
+    /// least 2 samples
+    ///
+    /// Taking the noisiest pixel rather than an average means a render that
+    /// stops below a threshold has no pixel above it, however small the
+    /// noisy part of the image is.
+    pub fn noise(&self) -> Option<f32> {
+        let mut noise = 0.0f32;
+        for pixel in &self.pixels {
+            noise = noise.max(pixel.relative_error()?);
+        }
+        Some(noise)
+    }
+
+    /// Converts the film into an image, mapping colors to display colors
+    /// with the output transform
+    pub fn to_image(&self, transform: &OutputTransform) -> Image {
+        let colors: Vec<Vector3> = self.pixels.iter().map(FilmPixel::color).collect();
+        let white = match transform.white_point {
+            Some(white) => white,
+            None => colors.iter().copied().map(luminance).fold(0.0, f32::max),
+        } * transform.scale();
+
+        let mut image = Image::new(self.width, self.height);
+        for (pixel, &color) in image.pixels_mut().iter_mut().zip(&colors) {
+            let color = transform.apply(color, white) * 255.0;
+            *pixel = Pixel::new(
+                color.x.round() as u8,
+                color.y.round() as u8,
+                color.z.round() as u8,
+                255,
+            );
+        }
+        image
+    }
+
+    /// Writes the film to a file in the given format
+    ///
+    /// Only PNG images go through the output transform, so the other
+    /// formats keep the linear colors for compositing and grading later.
+    pub fn write(
+        &self,
+        path: &Path,
+        format: OutputFormat,
+        transform: &OutputTransform,
+    ) -> io::Result<()> {
+        let create = || File::create(path).map(BufWriter::new);
+        match format {
+            OutputFormat::Png => self.to_image(transform).write_png(path),
+            OutputFormat::Exr(precision) => {
+                let colors: Vec<Vector3> = self.pixels.iter().map(FilmPixel::color).collect();
+                let channel = |f: fn(&Vector3) -> f32| colors.iter().map(f).collect::<Vec<f32>>();
+                let (r, g, b) = (channel(|c| c.x), channel(|c| c.y), channel(|c| c.z));
+                let a = vec![1.0; colors.len()];
+                let channels = [
+                    ExrChannel {
+                        name: "R",
+                        values: &r,
+                    },
+                    ExrChannel {
+                        name: "G",
+                        values: &g,
+                    },
+                    ExrChannel {
+                        name: "B",
+                        values: &b,
+                    },
+                    ExrChannel {
+                        name: "A",
+                        values: &a,
+                    },
+                ];
+                output::write_exr(create()?, self.width, self.height, &channels, precision)
+            }
+            OutputFormat::Hdr => output::write_hdr(create()?, self),
+            OutputFormat::Pfm => output::write_pfm(create()?, self),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+/// Number of steps the profile of a filter is tabulated in for sampling
+const SAMPLER_STEPS: usize = 256;
+
+/// Reconstruction filter used to weight the samples of each pixel by their
+/// offset from the pixel center
+///
+/// Radii are measured in pixels. Samples are spread over the whole filter
+/// footprint, so wider filters blur more but alias less. Every filter is
+/// the product of the same profile along x and y.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Filter {
+    /// Weights every sample equally
+    Box { radius: f32 },
+    /// Weights samples linearly less the further they are from the center
+    Tent { radius: f32 },
+    /// Weights samples with a Gaussian bell curve of falloff `alpha`,
+    /// shifted to reach zero at the radius
+    Gaussian { radius: f32, alpha: f32 },
+    /// The Mitchell-Netravali cubic with parameters `b` and `c`, which has
+    /// negative lobes that keep edges sharp
+    Mitchell { radius: f32, b: f32, c: f32 },
+}
+
+impl Default for Filter {
+    fn default() -> Filter {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+impl Filter {
+    /// Creates a filter of the given kind with its usual parameters
+    pub fn from_name(name: &str) -> Option<Filter> {
+        match name {
+            "box" => Some(Filter::Box { radius: 0.5 }),
+            "tent" => Some(Filter::Tent { radius: 1.0 }),
+            "gaussian" => Some(Filter::Gaussian {
+                radius: 1.5,
+                alpha: 2.0,
+            }),
+            "mitchell" => Some(Filter::Mitchell {
+                radius: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Gets the distance from the pixel center at which the filter reaches zero
+    pub fn radius(&self) -> f32 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. } => radius,
+        }
+    }
+
+    /// Calculates the weight of a sample offset from the pixel center by
+    /// `dx` and `dy` pixels
+    pub fn evaluate(&self, dx: f32, dy: f32) -> f32 {
+        self.profile(dx) * self.profile(dy)
+    }
+
+    /// Sums the filter's weight along one axis, which is 0 or less, or not
+    /// finite, for filters that can't weight samples
+    pub fn integral(&self) -> f32 {
+        let step = 2.0 * self.radius() / SAMPLER_STEPS as f32;
+        self.tabulate().iter().sum::<f32>() * step
+    }
+
+    /// Gets the profile at the middle of each step across the filter
+    fn tabulate(&self) -> Vec<f32> {
+        let radius = self.radius();
+        let step = 2.0 * radius / SAMPLER_STEPS as f32;
+        (0..SAMPLER_STEPS)
+            .map(|i| self.profile(-radius + (i as f32 + 0.5) * step))
+            .collect()
+    }
+
+    /// Calculates the filter's weight along one axis at an offset of `d`
+    /// pixels from the pixel center
+    pub fn profile(&self, d: f32) -> f32 {
+        match *self {
+            Filter::Box { radius } => {
+                if d.abs() <= radius {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Tent { radius } => (radius - d.abs()).max(0.0),
+            Filter::Gaussian { radius, alpha } => {
+                let edge = (-alpha * radius * radius).exp();
+                ((-alpha * d * d).exp() - edge).max(0.0)
+            }
+            Filter::Mitchell { radius, b, c } => mitchell(d / radius, b, c),
+        }
+    }
+}
+
+/// Picks sample offsets in proportion to the magnitude of a filter
+///
+/// Weighting evenly spread samples by the filter leaves pixels whose few
+/// samples landed in a Mitchell filter's negative lobes with weights summing
+/// to zero or less. Instead, samples are placed where the filter is
+/// strongest and carry only its sign, scaled so they average to 1 for
+/// filters that are never negative.
+#[derive(Debug, Clone)]
+pub struct FilterSampler {
+    filter: Filter,
+    /// Magnitude of the profile at the middle of each step
+    steps: Vec<f32>,
+    /// Fraction of the profile's magnitude before each step, and 1 at the end
+    cdf: Vec<f32>,
+    /// Ratio of the filter's total magnitude to its integral
+    scale: f32,
+}
+
+impl FilterSampler {
+    /// Creates a sampler for the filter by tabulating its profile
+    pub fn new(filter: Filter) -> FilterSampler {
+        let profile = filter.tabulate();
+        let steps: Vec<f32> = profile.iter().map(|value| value.abs()).collect();
+        let total: f32 = steps.iter().sum();
+        let integral: f32 = profile.iter().sum();
+
+        let mut cdf = Vec::with_capacity(SAMPLER_STEPS + 1);
+        let mut sum = 0.0;
+        cdf.push(0.0);
+        for value in &steps {
+            sum += value;
+            cdf.push(sum / total);
+        }
+        cdf[SAMPLER_STEPS] = 1.0;
+
+        FilterSampler {
+            filter,
+            steps,
+            cdf,
+            scale: (total / integral).powi(2),
+        }
+    }
+
+    /// Gets the filter being sampled
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    /// Maps a point in the unit square to an offset from the pixel center,
+    /// returning the offset along x and y and the sample's weight
+    ///
+    /// Weights average to 1, and are negative for samples in the negative
+    /// lobes of the filter. Box filters spread samples evenly with a weight
+    /// of exactly 1.
+    pub fn sample(&self, u: f32, v: f32) -> (f32, f32, f32) {
+        if let Filter::Box { radius } = self.filter {
+            return ((2.0 * u - 1.0) * radius, (2.0 * v - 1.0) * radius, 1.0);
+        }
+        let (dx, wx) = self.sample_profile(u);
+        let (dy, wy) = self.sample_profile(v);
+        (dx, dy, wx * wy * self.scale)
+    }
+
+    /// Picks an offset along one axis, returning it and the ratio of the
+    /// profile there to the step it was picked from
+    fn sample_profile(&self, u: f32) -> (f32, f32) {
+        let radius = self.filter.radius();
+        let step = 2.0 * radius / SAMPLER_STEPS as f32;
+        let i = (self.cdf.partition_point(|&cdf| cdf <= u) - 1).min(SAMPLER_STEPS - 1);
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let along = if width > 0.0 {
+            ((u - self.cdf[i]) / width).min(1.0)
+        } else {
+            0.5
+        };
+        let d = -radius + (i as f32 + along) * step;
+        let weight = if self.steps[i] > 0.0 {
+            self.filter.profile(d) / self.steps[i]
+        } else {
+            0.0
+        };
+        (d, weight)
+    }
+}
+
+/// Evaluates the 1D Mitchell-Netravali cubic over -1..1
+fn mitchell(x: f32, b: f32, c: f32) -> f32 {
+    let x = (2.0 * x).abs();
+    let weight = if x > 2.0 {
+        0.0
+    } else if x > 1.0 {
+        (-b - 6.0 * c) * x * x * x
+            + (6.0 * b + 30.0 * c) * x * x
+            + (-12.0 * b - 48.0 * c) * x
+            + (8.0 * b + 24.0 * c)
+    } else {
+        (12.0 - 9.0 * b - 6.0 * c) * x * x * x
+            + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+            + (6.0 - 2.0 * b)
+    };
+    weight / 6.0
+}
@@ -0,0 +1,1050 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::material::{Emissive, Material};
+use crate::mesh::TriangleMesh;
+use crate::principled::{Principled, PrincipledParameters};
+use crate::texture::{ImageTexture, Texture, TextureError, Wrap};
+use crate::transform::{Matrix4, Transform};
+use crate::vector::Vector3;
+
+/// Extensions the loader understands well enough to honor when a file
+/// requires them
+const SUPPORTED_EXTENSIONS: [&str; 5] = [
+    "KHR_materials_clearcoat",
+    "KHR_materials_emissive_strength",
+    "KHR_materials_ior",
+    "KHR_materials_sheen",
+    "KHR_materials_transmission",
+];
+
+/// Component types of accessors
+const BYTE: u32 = 5120;
+const UNSIGNED_BYTE: u32 = 5121;
+const SHORT: u32 = 5122;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// Describes why a glTF file could not be loaded
+#[derive(Debug)]
+pub enum GltfError {
+    /// The file, or a buffer or image it references, could not be read
+    Io(PathBuf, io::Error),
+    /// The file is not valid glTF 2.0, or uses a feature that isn't
+    /// supported
+    Invalid(PathBuf, String),
+    /// A texture used by a material could not be decoded
+    Texture(TextureError),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            GltfError::Invalid(path, message) => write!(f, "{}: {}", path.display(), message),
+            GltfError::Texture(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for GltfError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GltfError::Io(_, err) => Some(err),
+            GltfError::Invalid(..) => None,
+            GltfError::Texture(err) => Some(err),
+        }
+    }
+}
+
+impl From<TextureError> for GltfError {
+    fn from(err: TextureError) -> GltfError {
+        GltfError::Texture(err)
+    }
+}
+
+/// Surface parameters read from the `pbrMetallicRoughness` model of a glTF
+/// material and its extensions
+#[derive(Debug, Clone)]
+pub struct GltfMaterial {
+    /// Base color (`baseColorFactor`), without its alpha
+    pub base_color: Vector3,
+    /// Base color texture, already decoded from sRGB and multiplied by the
+    /// base color
+    pub base_color_texture: Option<Arc<ImageTexture>>,
+    /// Metalness (`metallicFactor`)
+    pub metallic: f32,
+    /// Perceptual roughness (`roughnessFactor`)
+    pub roughness: f32,
+    /// Emitted color (`emissiveFactor`), scaled by
+    /// `KHR_materials_emissive_strength`
+    pub emission: Vector3,
+    /// Transmission (`KHR_materials_transmission`)
+    pub transmission: f32,
+    /// Index of refraction (`KHR_materials_ior`)
+    pub ior: f32,
+    /// Clearcoat strength and roughness (`KHR_materials_clearcoat`)
+    pub clearcoat: f32,
+    pub clearcoat_roughness: f32,
+    /// Strength of the sheen, the brightest channel of its color
+    /// (`KHR_materials_sheen`)
+    pub sheen: f32,
+}
+
+impl Default for GltfMaterial {
+    /// Gets the material glTF uses for primitives without one
+    fn default() -> GltfMaterial {
+        GltfMaterial {
+            base_color: Vector3::new_scalar(1.0),
+            base_color_texture: None,
+            metallic: 1.0,
+            roughness: 1.0,
+            emission: Vector3::origin(),
+            transmission: 0.0,
+            ior: 1.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.0,
+            sheen: 0.0,
+        }
+    }
+}
+
+impl GltfMaterial {
+    /// Creates the tracer's material, which is an emitter when the material
+    /// emits light and principled otherwise
+    pub fn to_material(&self) -> Arc<dyn Material> {
+        let emission = self.emission;
+        if emission.x > 0.0 || emission.y > 0.0 || emission.z > 0.0 {
+            Arc::new(Emissive::new(emission, 1.0))
+        } else {
+            Arc::new(Principled::new(self.principled_parameters()))
+        }
+    }
+
+    /// Converts the metallic-roughness parameters into principled ones
+    ///
+    /// Both models use perceptual roughness, and the index of refraction
+    /// sets the strength of dielectric reflections, so the default of 1.5
+    /// reflects the same 4% at normal incidence.
+    pub fn principled_parameters(&self) -> PrincipledParameters {
+        let defaults = PrincipledParameters::default();
+        let clamp = |value: f32| value.clamp(0.0, 1.0);
+        let base_color: Arc<dyn Texture> = match &self.base_color_texture {
+            Some(texture) => texture.clone(),
+            None => self.base_color.into(),
+        };
+        let reflectance = ((self.ior - 1.0) / (self.ior + 1.0)).powi(2);
+        PrincipledParameters {
+            base_color,
+            metallic: clamp(self.metallic),
+            roughness: clamp(self.roughness),
+            specular: clamp(reflectance / 0.08),
+            sheen: clamp(self.sheen),
+            clearcoat: clamp(self.clearcoat),
+            clearcoat_roughness: clamp(self.clearcoat_roughness),
+            transmission: clamp(self.transmission),
+            ior: if self.ior > 1.0 {
+                self.ior
+            } else {
+                defaults.ior
+            },
+            ..defaults
+        }
+    }
+}
+
+/// A triangle mesh placed by a node of a glTF scene
+#[derive(Debug, Clone)]
+pub struct GltfMesh {
+    /// Triangles of one primitive, shared by every node that places it
+    pub mesh: Arc<TriangleMesh>,
+    /// Material the triangles were created with
+    pub material: GltfMaterial,
+    /// Transform from the mesh into the file's space, through the node and
+    /// all of its parents
+    pub transform: Transform,
+}
+
+/// Loads the meshes of a glTF 2.0 file, either JSON (`.gltf`) or binary
+/// (`.glb`), placed by the nodes of its default scene
+///
+/// Buffers and images may be separate files, base64 data URIs, or the
+/// binary chunk of a `.glb` file. Only triangle primitives and PNG images
+/// are supported. Materials use the base color, metallic, roughness, and
+/// emissive factors, the base color texture, and the transmission, index of
+/// refraction, clearcoat, sheen, and emissive strength extensions; other
+/// textures are ignored.
+pub fn load_gltf(path: &Path) -> Result<Vec<GltfMesh>, GltfError> {
+    let bytes = fs::read(path).map_err(|err| GltfError::Io(path.to_path_buf(), err))?;
+    let (document, binary) = read_document(path, &bytes)?;
+    let invalid = |message: String| GltfError::Invalid(path.to_path_buf(), message);
+
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut buffers = Vec::with_capacity(document.buffers.len());
+    for (index, buffer) in document.buffers.iter().enumerate() {
+        let data = match (&buffer.uri, binary) {
+            (Some(uri), _) => read_uri(directory, uri).map_err(|err| match err {
+                UriError::Io(file, err) => GltfError::Io(file, err),
+                UriError::Invalid(message) => invalid(message),
+            })?,
+            (None, Some(binary)) if index == 0 => binary.to_vec(),
+            (None, _) => return Err(invalid(format!("buffer {} has no data", index))),
+        };
+        if data.len() < buffer.byte_length {
+            return Err(invalid(format!("buffer {} is cut short", index)));
+        }
+        buffers.push(data);
+    }
+
+    let mut loader = Loader {
+        path,
+        directory,
+        document: &document,
+        buffers,
+        images: HashMap::new(),
+        materials: HashMap::new(),
+        primitives: HashMap::new(),
+    };
+    let roots = match document.scenes.get(document.scene.unwrap_or(0)) {
+        Some(scene) => scene.nodes.clone(),
+        None => loader.root_nodes(),
+    };
+    let mut meshes = Vec::new();
+    for node in roots {
+        loader.place_node(node, Transform::identity(), 0, &mut meshes)?;
+    }
+    Ok(meshes)
+}
+
+/// Gets the files loading a glTF file reads besides the file itself: the
+/// buffers and images that aren't embedded in it
+pub fn gltf_dependencies(path: &Path) -> Result<Vec<PathBuf>, GltfError> {
+    let bytes = fs::read(path).map_err(|err| GltfError::Io(path.to_path_buf(), err))?;
+    let (document, _) = read_document(path, &bytes)?;
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let buffers = document.buffers.iter().map(|buffer| &buffer.uri);
+    let images = document.images.iter().map(|image| &image.uri);
+    Ok(buffers
+        .chain(images)
+        .flatten()
+        .filter(|uri| !uri.starts_with("data:"))
+        .map(|uri| directory.join(percent_decode(uri)))
+        .collect())
+}
+
+/// Parses the JSON of a glTF file, along with the binary chunk of a `.glb`
+/// file, and checks that it can be loaded
+fn read_document<'a>(
+    path: &Path,
+    bytes: &'a [u8],
+) -> Result<(Document, Option<&'a [u8]>), GltfError> {
+    let invalid = |message: String| GltfError::Invalid(path.to_path_buf(), message);
+    let (json, binary) = if bytes.starts_with(b"glTF") {
+        split_glb(bytes).map_err(invalid)?
+    } else {
+        (bytes, None)
+    };
+    let document: Document =
+        serde_json::from_slice(json).map_err(|err| invalid(err.to_string()))?;
+    if !document.asset.version.starts_with("2.") {
+        return Err(invalid(format!(
+            "glTF {} is not supported, only 2.0",
+            document.asset.version
+        )));
+    }
+    if let Some(extension) = document
+        .extensions_required
+        .iter()
+        .find(|extension| !SUPPORTED_EXTENSIONS.contains(&extension.as_str()))
+    {
+        return Err(invalid(format!(
+            "required extension `{}` is not supported",
+            extension
+        )));
+    }
+    Ok((document, binary))
+}
+
+/// Splits a binary glTF file into its JSON chunk and optional binary chunk
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), String> {
+    let word = |at: usize| -> Option<u32> {
+        let bytes = bytes.get(at..at + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let chunk = |at: usize| -> Option<(u32, &[u8])> {
+        let length = word(at)? as usize;
+        let kind = word(at + 4)?;
+        Some((kind, bytes.get(at + 8..(at + 8).checked_add(length)?)?))
+    };
+
+    if word(4) != Some(2) {
+        return Err("only version 2 binary glTF files are supported".to_string());
+    }
+    let (kind, json) = chunk(12).ok_or("binary glTF file is cut short")?;
+    if kind != 0x4E4F_534A {
+        return Err("binary glTF file doesn't start with JSON".to_string());
+    }
+    let binary = match chunk(20 + json.len()) {
+        Some((0x004E_4942, binary)) => Some(binary),
+        _ => None,
+    };
+    Ok((json, binary))
+}
+
+/// Describes why a URI could not be read
+enum UriError {
+    Io(PathBuf, io::Error),
+    Invalid(String),
+}
+
+/// Reads the data a URI refers to, either a base64 data URI or a file
+/// relative to the glTF file
+fn read_uri(directory: &Path, uri: &str) -> Result<Vec<u8>, UriError> {
+    if uri.starts_with("data:") {
+        return match uri.find(";base64,") {
+            Some(i) => decode_base64(&uri[i + 8..])
+                .ok_or_else(|| UriError::Invalid("data URI is not valid base64".to_string())),
+            None => Err(UriError::Invalid(
+                "only base64 data URIs are supported".to_string(),
+            )),
+        };
+    }
+    let file = directory.join(percent_decode(uri));
+    fs::read(&file).map_err(|err| UriError::Io(file, err))
+}
+
+/// Decodes standard base64, ignoring padding
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for c in text.bytes().take_while(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | u32::from(value);
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((bits >> count) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+/// Decodes the `%XX` escapes of a relative URI into a path
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escape) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Triangles of a primitive and the material they were created with
+type BuiltPrimitive = (Arc<TriangleMesh>, GltfMaterial);
+
+/// Builds meshes from a parsed file, creating each primitive, material, and
+/// image once however many nodes use it
+struct Loader<'a> {
+    path: &'a Path,
+    directory: &'a Path,
+    document: &'a Document,
+    buffers: Vec<Vec<u8>>,
+    images: HashMap<usize, ImageTexture>,
+    materials: HashMap<usize, (GltfMaterial, Arc<dyn Material>)>,
+    primitives: HashMap<(usize, usize), Option<BuiltPrimitive>>,
+}
+
+impl<'a> Loader<'a> {
+    fn invalid(&self, message: String) -> GltfError {
+        GltfError::Invalid(self.path.to_path_buf(), message)
+    }
+
+    /// Gets the nodes that aren't the child of any other node, for files
+    /// without scenes
+    fn root_nodes(&self) -> Vec<usize> {
+        let nodes = &self.document.nodes;
+        (0..nodes.len())
+            .filter(|&i| !nodes.iter().any(|node| node.children.contains(&i)))
+            .collect()
+    }
+
+    /// Adds the meshes of a node and its children, placed by `parent`
+    ///
+    /// Nodes scaled flat can't be inverted and hold nothing visible, so they
+    /// are skipped along with their children.
+    fn place_node(
+        &mut self,
+        index: usize,
+        parent: Transform,
+        depth: usize,
+        meshes: &mut Vec<GltfMesh>,
+    ) -> Result<(), GltfError> {
+        let document = self.document;
+        let node = document
+            .nodes
+            .get(index)
+            .ok_or_else(|| self.invalid(format!("node {} doesn't exist", index)))?;
+        if depth > document.nodes.len() {
+            return Err(self.invalid(format!("node {} is its own ancestor", index)));
+        }
+        let transform = match Transform::new(node.matrix()) {
+            Some(local) => local.then(parent),
+            None => return Ok(()),
+        };
+
+        if let Some(mesh) = node.mesh {
+            let primitives = document
+                .meshes
+                .get(mesh)
+                .ok_or_else(|| self.invalid(format!("mesh {} doesn't exist", mesh)))?
+                .primitives
+                .len();
+            for primitive in 0..primitives {
+                if let Some((mesh, material)) = self.primitive(mesh, primitive)? {
+                    meshes.push(GltfMesh {
+                        mesh,
+                        material,
+                        transform,
+                    });
+                }
+            }
+        }
+        for &child in &node.children {
+            self.place_node(child, transform, depth + 1, meshes)?;
+        }
+        Ok(())
+    }
+
+    /// Gets the triangles of a primitive, or None if it has none
+    fn primitive(
+        &mut self,
+        mesh: usize,
+        index: usize,
+    ) -> Result<Option<BuiltPrimitive>, GltfError> {
+        if let Some(built) = self.primitives.get(&(mesh, index)) {
+            return Ok(built.clone());
+        }
+        let primitive = &self.document.meshes[mesh].primitives[index];
+        if primitive.mode != 4 {
+            return Err(self.invalid(format!(
+                "mesh {} uses primitive mode {}, but only triangles (4) are supported",
+                mesh, primitive.mode
+            )));
+        }
+
+        let attribute = |name: &str| primitive.attributes.get(name).copied();
+        let position = attribute("POSITION")
+            .ok_or_else(|| self.invalid(format!("mesh {} has no positions", mesh)))?;
+        let positions = vectors(&self.floats(position, "VEC3")?);
+        let normals = match attribute("NORMAL") {
+            Some(normal) => Some(vectors(&self.floats(normal, "VEC3")?)),
+            None => None,
+        };
+        // glTF puts the origin of texture coordinates at the top left
+        let uvs = match attribute("TEXCOORD_0") {
+            Some(texcoord) => Some(
+                self.floats(texcoord, "VEC2")?
+                    .chunks(2)
+                    .map(|uv| (uv[0], 1.0 - uv[1]))
+                    .collect::<Vec<(f32, f32)>>(),
+            ),
+            None => None,
+        };
+
+        let corners = match primitive.indices {
+            Some(indices) => self.indices(indices)?,
+            None => (0..positions.len() as u32).collect(),
+        };
+        if corners.len() % 3 != 0 {
+            return Err(self.invalid(format!(
+                "mesh {} has {} indices, which isn't a whole number of triangles",
+                mesh,
+                corners.len()
+            )));
+        }
+        if let Some(&corner) = corners.iter().find(|&&i| i as usize >= positions.len()) {
+            return Err(self.invalid(format!(
+                "mesh {} indexes vertex {} of {}",
+                mesh,
+                corner,
+                positions.len()
+            )));
+        }
+        let same_length = |len: usize| len == positions.len();
+        if !normals.as_ref().is_none_or(|n| same_length(n.len()))
+            || !uvs.as_ref().is_none_or(|uv| same_length(uv.len()))
+        {
+            return Err(self.invalid(format!("mesh {} has attributes of different lengths", mesh)));
+        }
+
+        let built = if corners.is_empty() {
+            None
+        } else {
+            let (material, shared) = self.material(primitive.material)?;
+            let indices = corners.chunks(3).map(|t| [t[0], t[1], t[2]]).collect();
+            let triangles = TriangleMesh::with_attributes(positions, normals, uvs, indices, shared);
+            Some((Arc::new(triangles), material))
+        };
+        self.primitives.insert((mesh, index), built.clone());
+        Ok(built)
+    }
+
+    /// Gets a material and the tracer's material created from it, or the
+    /// default material for primitives without one
+    fn material(
+        &mut self,
+        index: Option<usize>,
+    ) -> Result<(GltfMaterial, Arc<dyn Material>), GltfError> {
+        let index = match index {
+            Some(index) => index,
+            None => {
+                let material = GltfMaterial::default();
+                let shared = material.to_material();
+                return Ok((material, shared));
+            }
+        };
+        if let Some((material, shared)) = self.materials.get(&index) {
+            return Ok((material.clone(), Arc::clone(shared)));
+        }
+
+        let description = self
+            .document
+            .materials
+            .get(index)
+            .ok_or_else(|| self.invalid(format!("material {} doesn't exist", index)))?;
+        let pbr = &description.pbr_metallic_roughness;
+        let extensions = &description.extensions;
+        let [r, g, b, _] = pbr.base_color_factor;
+        let base_color = Vector3::new(r, g, b);
+        let base_color_texture = match &pbr.base_color_texture {
+            Some(info) => Some(Arc::new(
+                self.texture(info.index)?.decode_srgb().tint(base_color),
+            )),
+            None => None,
+        };
+        let strength = extensions
+            .emissive_strength
+            .as_ref()
+            .map_or(1.0, |e| e.emissive_strength);
+        let [er, eg, eb] = description.emissive_factor;
+        let clearcoat = extensions.clearcoat.as_ref();
+        let material = GltfMaterial {
+            base_color,
+            base_color_texture,
+            metallic: pbr.metallic_factor,
+            roughness: pbr.roughness_factor,
+            emission: Vector3::new(er, eg, eb) * strength,
+            transmission: extensions
+                .transmission
+                .as_ref()
+                .map_or(0.0, |t| t.transmission_factor),
+            ior: extensions.ior.as_ref().map_or(1.5, |ior| ior.ior),
+            clearcoat: clearcoat.map_or(0.0, |c| c.clearcoat_factor),
+            clearcoat_roughness: clearcoat.map_or(0.0, |c| c.clearcoat_roughness_factor),
+            sheen: extensions.sheen.as_ref().map_or(0.0, |sheen| {
+                let [r, g, b] = sheen.sheen_color_factor;
+                r.max(g).max(b)
+            }),
+        };
+        let shared = material.to_material();
+        self.materials
+            .insert(index, (material.clone(), Arc::clone(&shared)));
+        Ok((material, shared))
+    }
+
+    /// Gets the still encoded image of a texture, wrapped as its sampler
+    /// says
+    fn texture(&mut self, index: usize) -> Result<ImageTexture, GltfError> {
+        let document = self.document;
+        let texture = document
+            .textures
+            .get(index)
+            .ok_or_else(|| self.invalid(format!("texture {} doesn't exist", index)))?;
+        let source = texture
+            .source
+            .ok_or_else(|| self.invalid(format!("texture {} has no image", index)))?;
+        let wrap = match texture.sampler.and_then(|i| document.samplers.get(i)) {
+            Some(sampler) => match sampler.wrap_s {
+                33071 => Wrap::Clamp,
+                33648 => Wrap::Mirror,
+                _ => Wrap::Repeat,
+            },
+            None => Wrap::Repeat,
+        };
+
+        if !self.images.contains_key(&source) {
+            let image = self.image(source)?;
+            self.images.insert(source, image);
+        }
+        Ok(self.images[&source].clone().with_wrap(wrap))
+    }
+
+    /// Decodes an image from a file, data URI, or buffer view
+    fn image(&self, index: usize) -> Result<ImageTexture, GltfError> {
+        let image = self
+            .document
+            .images
+            .get(index)
+            .ok_or_else(|| self.invalid(format!("image {} doesn't exist", index)))?;
+        let png = match &image.mime_type {
+            Some(mime_type) => mime_type == "image/png",
+            None => image.uri.as_ref().is_some_and(|uri| {
+                uri.starts_with("data:image/png") || uri.to_lowercase().ends_with(".png")
+            }),
+        };
+        if !png {
+            return Err(self.invalid(format!(
+                "image {} is not a PNG image, the only format supported",
+                index
+            )));
+        }
+
+        match (&image.uri, image.buffer_view) {
+            (Some(uri), _) if !uri.starts_with("data:") => {
+                let file = self.directory.join(percent_decode(uri));
+                Ok(ImageTexture::load(&file, Wrap::Repeat)?)
+            }
+            (Some(uri), _) => {
+                let data = read_uri(self.directory, uri).map_err(|err| match err {
+                    UriError::Io(file, err) => GltfError::Io(file, err),
+                    UriError::Invalid(message) => self.invalid(message),
+                })?;
+                Ok(ImageTexture::read(&data[..], self.path, Wrap::Repeat)?)
+            }
+            (None, Some(view)) => {
+                let data = self.view(view)?;
+                Ok(ImageTexture::read(data, self.path, Wrap::Repeat)?)
+            }
+            (None, None) => Err(self.invalid(format!("image {} has no data", index))),
+        }
+    }
+
+    /// Gets the bytes of a buffer view
+    fn view(&self, index: usize) -> Result<&[u8], GltfError> {
+        let view = self
+            .document
+            .buffer_views
+            .get(index)
+            .ok_or_else(|| self.invalid(format!("buffer view {} doesn't exist", index)))?;
+        self.buffers
+            .get(view.buffer)
+            .zip(view.byte_offset.checked_add(view.byte_length))
+            .and_then(|(buffer, end)| buffer.get(view.byte_offset..end))
+            .ok_or_else(|| self.invalid(format!("buffer view {} is out of range", index)))
+    }
+
+    /// Gets an accessor
+    fn accessor(&self, index: usize) -> Result<&'a Accessor, GltfError> {
+        self.document
+            .accessors
+            .get(index)
+            .ok_or_else(|| self.invalid(format!("accessor {} doesn't exist", index)))
+    }
+
+    /// Gets the bytes of every element of an accessor of the given type,
+    /// along with its component type and number of components
+    fn elements(&self, index: usize, kind: &str) -> Result<(u32, usize, Vec<&[u8]>), GltfError> {
+        let accessor = self.accessor(index)?;
+        if accessor.kind != kind {
+            return Err(self.invalid(format!(
+                "accessor {} holds {} but {} was expected",
+                index, accessor.kind, kind
+            )));
+        }
+        if accessor.sparse.is_some() {
+            return Err(self.invalid(format!(
+                "accessor {} is sparse, which isn't supported",
+                index
+            )));
+        }
+        let view_index = accessor
+            .buffer_view
+            .ok_or_else(|| self.invalid(format!("accessor {} has no buffer view", index)))?;
+
+        let components = match kind {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            _ => 4,
+        };
+        let size = match accessor.component_type {
+            BYTE | UNSIGNED_BYTE => 1,
+            SHORT | UNSIGNED_SHORT => 2,
+            _ => 4,
+        } * components;
+        let data = self.view(view_index)?;
+        let stride = self
+            .document
+            .buffer_views
+            .get(view_index)
+            .and_then(|view| view.byte_stride)
+            .unwrap_or(size);
+
+        // Checks the last element fits before trusting the count
+        let end = match accessor.count.checked_sub(1) {
+            Some(last) => last
+                .checked_mul(stride)
+                .and_then(|offset| offset.checked_add(accessor.byte_offset)?.checked_add(size)),
+            None => Some(0),
+        };
+        if end.is_none_or(|end| end > data.len()) {
+            return Err(self.invalid(format!("accessor {} runs past its buffer view", index)));
+        }
+
+        let elements = (0..accessor.count)
+            .map(|i| {
+                let start = accessor.byte_offset + i * stride;
+                &data[start..start + size]
+            })
+            .collect();
+        Ok((accessor.component_type, components, elements))
+    }
+
+    /// Reads an accessor of floats, or of normalized integers as texture
+    /// coordinates may be, as a flat list of components
+    fn floats(&self, index: usize, kind: &str) -> Result<Vec<f32>, GltfError> {
+        let normalized = self.accessor(index)?.normalized;
+        let (component_type, components, elements) = self.elements(index, kind)?;
+        let read: fn(&[u8]) -> f32 = match (component_type, normalized) {
+            (FLOAT, _) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            (UNSIGNED_BYTE, true) => |b| f32::from(b[0]) / 255.0,
+            (UNSIGNED_SHORT, true) => |b| f32::from(u16::from_le_bytes([b[0], b[1]])) / 65535.0,
+            _ => {
+                return Err(self.invalid(format!(
+                    "accessor {} has component type {}, which isn't supported here",
+                    index, component_type
+                )))
+            }
+        };
+        let size = elements.first().map_or(0, |e| e.len() / components);
+        Ok(elements
+            .iter()
+            .flat_map(|element| element.chunks(size).map(read))
+            .collect())
+    }
+
+    /// Reads an accessor of triangle corner indices
+    fn indices(&self, index: usize) -> Result<Vec<u32>, GltfError> {
+        let (component_type, _, elements) = self.elements(index, "SCALAR")?;
+        let read: fn(&[u8]) -> u32 = match component_type {
+            UNSIGNED_BYTE => |b| u32::from(b[0]),
+            UNSIGNED_SHORT => |b| u32::from(u16::from_le_bytes([b[0], b[1]])),
+            UNSIGNED_INT => |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            _ => {
+                return Err(self.invalid(format!(
+                    "accessor {} has component type {}, which can't hold indices",
+                    index, component_type
+                )))
+            }
+        };
+        Ok(elements.into_iter().map(read).collect())
+    }
+}
+
+/// Groups a flat list of components into vectors
+fn vectors(components: &[f32]) -> Vec<Vector3> {
+    components
+        .chunks(3)
+        .map(|v| Vector3::new(v[0], v[1], v[2]))
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    asset: Asset,
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<SceneDescription>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    meshes: Vec<MeshDescription>,
+    #[serde(default)]
+    accessors: Vec<Accessor>,
+    #[serde(default)]
+    buffer_views: Vec<BufferView>,
+    #[serde(default)]
+    buffers: Vec<Buffer>,
+    #[serde(default)]
+    materials: Vec<MaterialDescription>,
+    #[serde(default)]
+    textures: Vec<TextureDescription>,
+    #[serde(default)]
+    images: Vec<ImageDescription>,
+    #[serde(default)]
+    samplers: Vec<Sampler>,
+    #[serde(default)]
+    extensions_required: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct SceneDescription {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    #[serde(default)]
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+impl Node {
+    /// Gets the node's local matrix, either given in column-major order or
+    /// built from its translation, rotation quaternion, and scale
+    fn matrix(&self) -> Matrix4 {
+        if let Some(m) = self.matrix {
+            let mut rows = [[0.0; 4]; 4];
+            for (i, row) in rows.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    *value = m[j * 4 + i];
+                }
+            }
+            return Matrix4::new(rows);
+        }
+
+        let [tx, ty, tz] = self.translation.unwrap_or([0.0; 3]);
+        let [x, y, z, w] = self.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+        let [sx, sy, sz] = self.scale.unwrap_or([1.0; 3]);
+        let rotation = Matrix4::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - z * w),
+                2.0 * (x * z + y * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - x * w),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - y * w),
+                2.0 * (y * z + x * w),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        Matrix4::translation(Vector3::new(tx, ty, tz))
+            * rotation
+            * Matrix4::scaling(Vector3::new(sx, sy, sz))
+    }
+}
+
+#[derive(Deserialize)]
+struct MeshDescription {
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Deserialize)]
+struct Primitive {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    #[serde(default = "default_mode")]
+    mode: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    uri: Option<String>,
+    byte_length: usize,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialDescription {
+    #[serde(default)]
+    pbr_metallic_roughness: MetallicRoughness,
+    #[serde(default)]
+    emissive_factor: [f32; 3],
+    #[serde(default)]
+    extensions: MaterialExtensions,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MetallicRoughness {
+    #[serde(default = "default_base_color")]
+    base_color_factor: [f32; 4],
+    base_color_texture: Option<TextureInfo>,
+    #[serde(default = "default_factor")]
+    metallic_factor: f32,
+    #[serde(default = "default_factor")]
+    roughness_factor: f32,
+}
+
+impl Default for MetallicRoughness {
+    fn default() -> MetallicRoughness {
+        MetallicRoughness {
+            base_color_factor: default_base_color(),
+            base_color_texture: None,
+            metallic_factor: default_factor(),
+            roughness_factor: default_factor(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TextureInfo {
+    index: usize,
+}
+
+#[derive(Deserialize, Default)]
+struct MaterialExtensions {
+    #[serde(rename = "KHR_materials_transmission")]
+    transmission: Option<Transmission>,
+    #[serde(rename = "KHR_materials_ior")]
+    ior: Option<Ior>,
+    #[serde(rename = "KHR_materials_clearcoat")]
+    clearcoat: Option<Clearcoat>,
+    #[serde(rename = "KHR_materials_sheen")]
+    sheen: Option<Sheen>,
+    #[serde(rename = "KHR_materials_emissive_strength")]
+    emissive_strength: Option<EmissiveStrength>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Transmission {
+    #[serde(default)]
+    transmission_factor: f32,
+}
+
+#[derive(Deserialize)]
+struct Ior {
+    #[serde(default = "default_ior")]
+    ior: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Clearcoat {
+    #[serde(default)]
+    clearcoat_factor: f32,
+    #[serde(default)]
+    clearcoat_roughness_factor: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Sheen {
+    #[serde(default)]
+    sheen_color_factor: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmissiveStrength {
+    #[serde(default = "default_factor")]
+    emissive_strength: f32,
+}
+
+#[derive(Deserialize)]
+struct TextureDescription {
+    source: Option<usize>,
+    sampler: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageDescription {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+    mime_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Sampler {
+    #[serde(default = "default_wrap")]
+    wrap_s: u32,
+}
+
+fn default_mode() -> u32 {
+    4
+}
+
+fn default_base_color() -> [f32; 4] {
+    [1.0; 4]
+}
+
+fn default_factor() -> f32 {
+    1.0
+}
+
+fn default_ior() -> f32 {
+    1.5
+}
+
+fn default_wrap() -> u32 {
+    10497
+}
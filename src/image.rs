@@ -0,0 +1,71 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use png::{BitDepth, ColorType, Encoder, HasParameters};
+
+use crate::pixel::{IntoPixelData, Pixel};
+
+/// A rendered image held in memory as rows of pixels, top to bottom
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<Pixel>,
+}
+
+impl Image {
+    /// Creates a transparent black image of the given size
+    pub fn new(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![Pixel::new(0, 0, 0, 0); (width * height) as usize],
+        }
+    }
+
+    /// Gets the width of the image in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Gets the height of the image in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Gets the pixel at the given column and row
+    pub fn pixel(&self, x: u32, y: u32) -> Pixel {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Gets all pixels in row-major order
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.pixels
+    }
+
+    /// Gets all pixels in row-major order for writing
+    pub(crate) fn pixels_mut(&mut self) -> &mut [Pixel] {
+        &mut self.pixels
+    }
+
+    /// Converts the image into RGBA bytes with 8 bits per channel
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.pixels.as_slice().into_pixel_data()
+    }
+
+    /// Writes the image to a PNG file
+    pub fn write_png(&self, path: &Path) -> io::Result<()> {
+        let file = File::create(path)?;
+        let w = BufWriter::new(file);
+
+        let mut encoder = Encoder::new(w, self.width, self.height);
+        encoder.set(ColorType::RGBA).set(BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+
+        let pixel_data = self.to_rgba8();
+
+        writer.write_image_data(&pixel_data)?;
+        Ok(())
+    }
+}
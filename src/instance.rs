@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::intersectable::Intersectable;
+use crate::ray::Ray;
+use crate::rayhit::RayHit;
+use crate::transform::Transform;
+
+/// A transformed copy of a shared object
+///
+/// Rays are moved into the object's own space to be intersected, and hits
+/// are moved back out, so many instances can share one object, such as a
+/// large mesh, without copying it.
+pub struct Instance {
+    object: Arc<dyn Intersectable + Sync + Send>,
+    transform: Transform,
+    bounds: Aabb,
+}
+
+impl Instance {
+    /// Creates a new instance placing `object` by `transform`
+    pub fn new(object: Arc<dyn Intersectable + Sync + Send>, transform: Transform) -> Instance {
+        let bounds = transform.bounds(object.bounds());
+        Instance {
+            object,
+            transform,
+            bounds,
+        }
+    }
+
+    /// Gets the transform from the object's space into the world
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+}
+
+impl Intersectable for Instance {
+    /// Intersects the ray with the object in its own space and transforms
+    /// the hit back into the world
+    fn intersect(&self, ray: Ray) -> Option<RayHit<'_>> {
+        // Objects expect unit directions, so distances found along the
+        // object space ray are scaled back by its length
+        let local = self.transform.inverse().ray(ray);
+        let length = local.direction().len();
+        let local = Ray::new(local.origin(), local.direction() * (1.0 / length));
+        let hit = self.object.intersect(local)?;
+
+        // Both normals are moved out, so a separate geometric normal
+        // survives for smooth objects
+        let distance = hit.distance() / length;
+        let hit = RayHit::new(
+            ray.origin() + ray.direction() * distance,
+            self.transform.normal(hit.geometric_normal()),
+            hit.uv(),
+            distance,
+            hit.material(),
+        )
+        .with_normal(self.transform.normal(hit.normal()))
+        .with_tangents(
+            self.transform.vector(hit.tangent()),
+            self.transform.vector(hit.bitangent()),
+        );
+        Some(hit)
+    }
+
+    /// Gets the box around the transformed object
+    fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+}
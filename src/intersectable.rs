@@ -1,11 +1,29 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
 use crate::ray::Ray;
 use crate::rayhit::RayHit;
 
 /// Intersectable defines behavior for objects that can be seen
 /// by the Ray Tracer
 pub trait Intersectable {
-
     /// Returns whether the ray hit the Intersectable in the form of
     /// a RayHit or None if there was no intersection
-    fn intersect(&self, ray: Ray) -> Option<RayHit>;
-}
\ No newline at end of file
+    fn intersect(&self, ray: Ray) -> Option<RayHit<'_>>;
+
+    /// Returns the axis-aligned box enclosing the Intersectable, or an
+    /// infinite box if it is unbounded
+    fn bounds(&self) -> Aabb;
+}
+
+/// Shared objects can be placed in a world directly, as well as through
+/// instances
+impl<T: Intersectable + ?Sized> Intersectable for Arc<T> {
+    fn intersect(&self, ray: Ray) -> Option<RayHit<'_>> {
+        (**self).intersect(ray)
+    }
+
+    fn bounds(&self) -> Aabb {
+        (**self).bounds()
+    }
+}
@@ -0,0 +1,113 @@
+//! A simple ray tracer supporting lighting, reflection, and refraction.
+//!
+//! Scenes can be loaded from TOML description files with [`SceneFile`] or
+//! built in code, and are rendered into an in-memory [`Image`] by a
+//! [`Renderer`].
+//!
+//! ```
+//! use std::sync::Arc;
+//!
+//! use raytracer::light::DirectionalLight;
+//! use raytracer::material::{Lambertian, Phong};
+//! use raytracer::plane::Plane;
+//! use raytracer::sphere::Sphere;
+//! use raytracer::vector::Vector3;
+//! use raytracer::world::World;
+//! use raytracer::{Camera, Intersectable, RenderSettings, Renderer, Scene};
+//!
+//! // A red mirrored sphere resting on a gray floor
+//! let objects: Vec<Box<dyn Intersectable + Sync + Send>> = vec![
+//!     Box::new(Sphere::new(
+//!         Vector3::new(0.0, 0.0, 2.0),
+//!         1.0,
+//!         Arc::new(Phong::new(
+//!             Vector3::new(1.0, 0.0, 0.0),
+//!             Vector3::new_scalar(0.5),
+//!             64.0,
+//!             0.5,
+//!         )),
+//!     )),
+//!     Box::new(Plane::new(
+//!         Vector3::new(0.0, -1.0, 0.0),
+//!         Vector3::new(0.0, 1.0, 0.0),
+//!         Arc::new(Lambertian::new(Vector3::new_scalar(0.5))),
+//!     )),
+//! ];
+//!
+//! let scene = Scene::new(
+//!     Camera::new(Vector3::new(0.0, 0.0, -2.0), Vector3::new(0.0, 1.0, 0.0), 60.0, 0.0, 0.0),
+//!     World::new(objects),
+//!     vec![Box::new(DirectionalLight::new(
+//!         Vector3::new(-1.0, -1.0, 1.0),
+//!         Vector3::new_scalar(1.0),
+//!         1.0,
+//!     ))],
+//!     0.1,
+//!     Vector3::new(0.529, 0.808, 0.98),
+//! );
+//!
+//! let settings = RenderSettings {
+//!     width: 64,
+//!     height: 48,
+//!     ..RenderSettings::default()
+//! };
+//! let image = Renderer::new(settings).render(&scene);
+//!
+//! assert_eq!((image.width(), image.height()), (64, 48));
+//!
+//! // The sphere fills the center of the image
+//! let [r, g, b, _] = image.pixel(32, 24).rgba();
+//! assert!(r > g && r > b);
+//! ```
+
+pub mod aabb;
+pub mod bump;
+pub mod bvh;
+pub mod camera;
+pub mod checkpoint;
+pub mod disk;
+pub mod film;
+pub mod filter;
+pub mod gltf;
+pub mod image;
+pub mod instance;
+pub mod intersectable;
+pub mod light;
+pub mod material;
+pub mod mesh;
+pub mod microfacet;
+pub mod noise;
+pub mod obj;
+pub mod output;
+pub mod pixel;
+pub mod plane;
+pub mod principled;
+pub mod procedural;
+pub mod ray;
+pub mod rayhit;
+pub mod renderer;
+mod rng;
+pub mod sampling;
+pub mod scene;
+pub mod settings;
+pub mod sphere;
+pub mod texture;
+pub mod tile;
+pub mod tonemap;
+mod trace;
+pub mod transform;
+pub mod triangle;
+pub mod vector;
+pub mod world;
+
+pub use crate::camera::Camera;
+pub use crate::film::Film;
+pub use crate::filter::Filter;
+pub use crate::image::Image;
+pub use crate::intersectable::Intersectable;
+pub use crate::material::Material;
+pub use crate::output::OutputFormat;
+pub use crate::renderer::Renderer;
+pub use crate::scene::{Scene, SceneError, SceneFile};
+pub use crate::settings::{Integrator, RenderSettings};
+pub use crate::tonemap::{OutputTransform, ToneMap};
@@ -0,0 +1,384 @@
+use std::f32::consts::PI;
+use std::fmt::Debug;
+
+use crate::sampling::{concentric_disk, orthonormal_basis};
+use crate::vector::Vector3;
+
+/// Light arriving at a point from a single light source
+#[derive(Debug, Copy, Clone)]
+pub struct LightSample {
+    /// Normalized direction from the point towards the light
+    pub direction: Vector3,
+    /// Distance to the light, or infinity for lights infinitely far away
+    pub distance: f32,
+    /// Color and strength of the light reaching the point
+    pub radiance: Vector3,
+}
+
+/// Light defines behavior for sources of direct illumination
+pub trait Light: Debug + Send + Sync {
+    /// Returns the light reaching the given point from the position on the
+    /// light selected by `u` and `v` in 0..1, or None if the point is outside
+    /// of the light's reach
+    ///
+    /// Averaging samples over the unit square estimates the total light
+    /// reaching the point. Lights with a single position ignore `u` and `v`.
+    fn sample(&self, point: Vector3, u: f32, v: f32) -> Option<LightSample>;
+
+    /// Returns how many shadow rays should be traced towards the light from
+    /// each shading point
+    ///
+    /// The tracer rounds this up to a square number so the samples can be
+    /// stratified on a grid over the unit square.
+    fn samples(&self) -> u32 {
+        1
+    }
+}
+
+/// A light infinitely far away that shines in a single direction
+#[derive(Debug, Copy, Clone)]
+pub struct DirectionalLight {
+    direction: Vector3,
+    color: Vector3,
+    intensity: f32,
+}
+
+impl DirectionalLight {
+    /// Creates a new light shining along the given direction
+    pub fn new(direction: Vector3, color: Vector3, intensity: f32) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.normalize(),
+            color,
+            intensity,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    /// Lights every point equally from the same direction
+    fn sample(&self, _point: Vector3, _u: f32, _v: f32) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -self.direction,
+            distance: f32::INFINITY,
+            radiance: self.color * self.intensity,
+        })
+    }
+}
+
+/// A light shining equally in all directions from a single point
+#[derive(Debug, Copy, Clone)]
+pub struct PointLight {
+    position: Vector3,
+    color: Vector3,
+    intensity: f32,
+}
+
+impl PointLight {
+    /// Creates a new light at the given position
+    pub fn new(position: Vector3, color: Vector3, intensity: f32) -> PointLight {
+        PointLight {
+            position,
+            color,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    /// Lights points with inverse-square falloff
+    fn sample(&self, point: Vector3, _u: f32, _v: f32) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance = to_light.len();
+        Some(LightSample {
+            direction: to_light * (1.0 / distance),
+            distance,
+            radiance: self.color * (self.intensity / (distance * distance)),
+        })
+    }
+}
+
+/// A point light that only shines inside a cone
+#[derive(Debug, Copy, Clone)]
+pub struct SpotLight {
+    position: Vector3,
+    direction: Vector3,
+    color: Vector3,
+    intensity: f32,
+    cos_cone: f32,
+    cos_falloff: f32,
+}
+
+impl SpotLight {
+    /// Creates a new spot light at the given position shining along the
+    /// given direction
+    ///
+    /// `cone_angle` is the angle in degrees between the direction and the
+    /// edge of the cone. The light fades out smoothly over the outermost
+    /// `falloff_angle` degrees of the cone.
+    pub fn new(
+        position: Vector3,
+        direction: Vector3,
+        color: Vector3,
+        intensity: f32,
+        cone_angle: f32,
+        falloff_angle: f32,
+    ) -> SpotLight {
+        let falloff_start = (cone_angle - falloff_angle).max(0.0);
+        SpotLight {
+            position,
+            direction: direction.normalize(),
+            color,
+            intensity,
+            cos_cone: cone_angle.to_radians().cos(),
+            cos_falloff: falloff_start.to_radians().cos(),
+        }
+    }
+}
+
+impl Light for SpotLight {
+    /// Lights points inside the cone with inverse-square falloff, fading
+    /// towards the cone's edge
+    fn sample(&self, point: Vector3, _u: f32, _v: f32) -> Option<LightSample> {
+        let to_light = self.position - point;
+        let distance = to_light.len();
+        let direction = to_light * (1.0 / distance);
+
+        // Checks how far the point is from the center of the cone
+        let cos_angle = (-direction).dot(self.direction);
+        if cos_angle <= self.cos_cone {
+            return None;
+        }
+        let attenuation = if cos_angle >= self.cos_falloff {
+            1.0
+        } else {
+            let t = (cos_angle - self.cos_cone) / (self.cos_falloff - self.cos_cone);
+            t * t * (3.0 - 2.0 * t)
+        };
+
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.color * (self.intensity * attenuation / (distance * distance)),
+        })
+    }
+}
+
+/// Calculates the light reaching a point from a sampled position on the
+/// surface of an area light
+///
+/// The light's radiance is converted from the area of the light to the
+/// solid angle it covers as seen from the point, which weights closer and
+/// more directly facing parts of the light more strongly.
+fn area_sample(
+    point: Vector3,
+    light_point: Vector3,
+    light_normal: Vector3,
+    area: f32,
+    radiance: Vector3,
+) -> Option<LightSample> {
+    let to_light = light_point - point;
+    let distance = to_light.len();
+    let direction = to_light * (1.0 / distance);
+
+    // Area lights only emit from their front side
+    let cos_light = -direction.dot(light_normal);
+    if cos_light <= 0.0 {
+        return None;
+    }
+
+    Some(LightSample {
+        direction,
+        distance,
+        radiance: radiance * (cos_light * area / (distance * distance)),
+    })
+}
+
+/// A rectangular area light that emits from one side
+#[derive(Debug, Copy, Clone)]
+pub struct RectangleLight {
+    corner: Vector3,
+    edge_u: Vector3,
+    edge_v: Vector3,
+    normal: Vector3,
+    area: f32,
+    color: Vector3,
+    intensity: f32,
+    samples: u32,
+}
+
+impl RectangleLight {
+    /// Creates a new rectangle light centered on `position` and spanned by
+    /// the edge vectors `edge_u` and `edge_v`
+    ///
+    /// The light emits towards the side `edge_u.cross(edge_v)` points to.
+    pub fn new(
+        position: Vector3,
+        edge_u: Vector3,
+        edge_v: Vector3,
+        color: Vector3,
+        intensity: f32,
+        samples: u32,
+    ) -> RectangleLight {
+        let cross = edge_u.cross(edge_v);
+        RectangleLight {
+            corner: position - (edge_u + edge_v) * 0.5,
+            edge_u,
+            edge_v,
+            normal: cross.normalize(),
+            area: cross.len(),
+            color,
+            intensity,
+            samples,
+        }
+    }
+}
+
+impl Light for RectangleLight {
+    /// Lights points from a position chosen uniformly over the rectangle
+    fn sample(&self, point: Vector3, u: f32, v: f32) -> Option<LightSample> {
+        let light_point = self.corner + self.edge_u * u + self.edge_v * v;
+        area_sample(
+            point,
+            light_point,
+            self.normal,
+            self.area,
+            self.color * self.intensity,
+        )
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+/// A circular area light that emits from one side
+#[derive(Debug, Copy, Clone)]
+pub struct DiskLight {
+    position: Vector3,
+    normal: Vector3,
+    radius: f32,
+    tangent: Vector3,
+    bitangent: Vector3,
+    color: Vector3,
+    intensity: f32,
+    samples: u32,
+}
+
+impl DiskLight {
+    /// Creates a new disk light centered on `position` that emits towards
+    /// `normal`
+    pub fn new(
+        position: Vector3,
+        normal: Vector3,
+        radius: f32,
+        color: Vector3,
+        intensity: f32,
+        samples: u32,
+    ) -> DiskLight {
+        let normal = normal.normalize();
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        DiskLight {
+            position,
+            normal,
+            radius,
+            tangent,
+            bitangent,
+            color,
+            intensity,
+            samples,
+        }
+    }
+}
+
+impl Light for DiskLight {
+    /// Lights points from a position chosen uniformly over the disk
+    fn sample(&self, point: Vector3, u: f32, v: f32) -> Option<LightSample> {
+        let (x, y) = concentric_disk(u, v);
+        let light_point = self.position + (self.tangent * x + self.bitangent * y) * self.radius;
+        area_sample(
+            point,
+            light_point,
+            self.normal,
+            PI * self.radius * self.radius,
+            self.color * self.intensity,
+        )
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+/// A spherical area light that emits in every direction
+#[derive(Debug, Copy, Clone)]
+pub struct SphereLight {
+    position: Vector3,
+    radius: f32,
+    color: Vector3,
+    intensity: f32,
+    samples: u32,
+}
+
+impl SphereLight {
+    /// Creates a new sphere light centered on `position`
+    pub fn new(
+        position: Vector3,
+        radius: f32,
+        color: Vector3,
+        intensity: f32,
+        samples: u32,
+    ) -> SphereLight {
+        SphereLight {
+            position,
+            radius,
+            color,
+            intensity,
+            samples,
+        }
+    }
+}
+
+impl Light for SphereLight {
+    /// Lights points from a direction chosen uniformly inside the cone the
+    /// sphere covers as seen from the point
+    fn sample(&self, point: Vector3, u: f32, v: f32) -> Option<LightSample> {
+        let to_center = self.position - point;
+        let center_distance = to_center.len();
+        if center_distance <= self.radius {
+            return None;
+        }
+        let axis = to_center * (1.0 / center_distance);
+
+        // Picks a direction inside the cone around the sphere's center
+        let sin_max = self.radius / center_distance;
+        let cos_max = (1.0 - sin_max * sin_max).max(0.0).sqrt();
+        let cos_theta = 1.0 - u * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        let (tangent, bitangent) = orthonormal_basis(axis);
+        let direction = (tangent * (phi.cos() * sin_theta)
+            + bitangent * (phi.sin() * sin_theta)
+            + axis * cos_theta)
+            .normalize();
+
+        // Finds where the direction enters the sphere
+        let projected = center_distance * cos_theta;
+        let offset = (self.radius * self.radius - center_distance * center_distance
+            + projected * projected)
+            .max(0.0)
+            .sqrt();
+
+        // Every direction in the cone is equally likely, so each sample
+        // carries the radiance times the cone's solid angle
+        let solid_angle = 2.0 * PI * (1.0 - cos_max);
+        Some(LightSample {
+            direction,
+            distance: projected - offset,
+            radiance: self.color * (self.intensity * solid_angle),
+        })
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+}
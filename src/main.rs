@@ -1,129 +1,199 @@
+use std::env;
+use std::error::Error;
 use std::fs::File;
-use std::io::BufWriter;
-use std::time::Instant;
-
-use png::{BitDepth, ColorType, Encoder, HasParameters};
-use scoped_threadpool::Pool;
-
-use camera::Camera;
-use intersectable::Intersectable;
-use pixel::{IntoPixelData, Pixel};
-use plane::Plane;
-use sphere::Sphere;
-use trace::trace_chunk;
-use vector::Vector3;
-
-mod camera;
-mod intersectable;
-mod pixel;
-mod plane;
-mod ray;
-mod rayhit;
-mod sphere;
-mod trace;
-mod vector;
-
-// Image output size
-const WIDTH: u32 = 1024;
-const HEIGHT: u32 = 1024;
-
-// Number of threads for thread pool
-const THREADS: u32 = 4;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process;
+use std::time::{Duration, Instant};
 
-fn main() {
-    // Creates the camera
-    let camera = Camera::new(
-        Vector3::new(-0.5, -0.5, -3.0),
-        Vector3::new(0.0, 1.0, 0.0),
-        90.0,
-        0.0,
-        5.0,
-    );
-
-    // Creates world objects
-    let sphere = Box::new(Sphere::new(
-        Vector3::new(-0.75, -0.125, -0.5),
-        Vector3::new(0.0, 0.0, 0.75),
-        0.75,
-        Some((0.5, Some(1.1))),
-    ));
-    let sphere2 = Box::new(Sphere::new(
-        Vector3::new(0.5, 0.0, 1.0),
-        Vector3::new(0.0, 1.0, 1.0),
-        1.0,
-        Some((0.5, None)),
-    ));
-    let sphere3 = Box::new(Sphere::new(
-        Vector3::new(1.0, 2.0, 2.0),
-        Vector3::new(1.0, 0.0, 0.0),
-        1.0,
-        Some((0.5, None)),
-    ));
-    let plane = Box::new(Plane::new(
-        Vector3::new(0.0, -1.0, 0.0),
-        Vector3::new(0.0, 1.0, 0.0),
-        Vector3::new(0.5, 0.5, 0.5),
-        Some((0.5, None)),
-    ));
-
-    // Brings objects into a single array
-    let mut world: Vec<Box<Intersectable + Sync + Send>> = Vec::new();
-    world.push(sphere);
-    world.push(sphere2);
-    world.push(sphere3);
-    world.push(plane);
-
-    // Creates a pixel array large enough for the output image
-    let mut data: Vec<Pixel> = vec![Pixel::new(0, 0, 0, 0); (WIDTH * HEIGHT) as usize];
-
-    // Starts timer
-    let trace_start = Instant::now();
+use raytracer::checkpoint::Checkpoint;
+use raytracer::renderer::Progress;
+use raytracer::tile::TileTime;
+use raytracer::{Film, OutputFormat, Renderer, Scene, SceneFile};
 
-    // Creates thread pool for ray tracing
-    let mut pool = Pool::new(THREADS);
-    pool.scoped(|scope| {
-        let thread_world = &world;
+use cli::{CliError, Options, USAGE};
 
-        // Keeps track of which pixels have been sent to the pool so far
-        let mut start = 0;
+mod cli;
 
-        // Divides the data array into chunks that will store our data
-        // We create a chunk for each thread allocated on the pool
-        for chunk in data.chunks_mut((WIDTH * HEIGHT / THREADS) as usize) {
-            let chunk_len = chunk.len();
+/// Seconds between checkpoints unless `--checkpoint-interval` is given
+const CHECKPOINT_INTERVAL: u64 = 60;
 
-            // Executes the trace
-            scope.execute(move || {
-                trace_chunk(chunk, start as u32, WIDTH, HEIGHT, &camera, thread_world);
-            });
-
-            start += chunk_len;
+fn main() {
+    // Parses the command line, exiting with status 2 on bad usage
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(CliError::Help) => {
+            println!("{}", USAGE);
+            return;
         }
-    });
-    // Thread pool is effectively joined here
+        Err(err) => {
+            eprintln!("error: {}", err);
+            eprintln!("Try `raytracer --help` for more information.");
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = run(&options) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
 
-    // Stops the timer
+/// Loads the scene, renders it, and writes the image
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    let SceneFile {
+        scene,
+        mut settings,
+        output,
+    } = SceneFile::load(&options.scene)?;
+    options.apply(&mut settings);
+    let output = options.output.clone().unwrap_or(output);
+    let format = match options.format {
+        Some(format) => format,
+        None => OutputFormat::from_path(&output).ok_or_else(|| {
+            format!(
+                "{}: unknown image format, use a .png, .exr, .hdr, or .pfm extension or --format",
+                output.display()
+            )
+        })?,
+    };
+
+    let output_transform = settings.output;
+
+    // Renders the image, timing the trace
+    let trace_start = Instant::now();
+    let renderer = Renderer::new(settings);
+    let (film, times) = if settings.progressive {
+        let film = render_progressive(&renderer, &scene, options, &|film| {
+            film.write(&output, format, &output_transform)
+                .map_err(|err| format!("{}: {}", output.display(), err))
+        })?;
+        (film, Vec::new())
+    } else {
+        renderer.render_tiles(&scene)
+    };
     let trace_duration = trace_start.elapsed().as_millis();
+    if let Some(path) = &options.tile_times {
+        write_tile_times(path, &times).map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
 
     // Writes the image
     let draw_start = Instant::now();
-    write_image(data);
+    film.write(&output, format, &output_transform)
+        .map_err(|err| format!("{}: {}", output.display(), err))?;
     let draw_duration = draw_start.elapsed().as_millis();
 
     println!("Trace: {} ms.", trace_duration);
+    if let Some(slowest) = times.iter().max_by_key(|time| time.duration()) {
+        let total: u128 = times.iter().map(|time| time.duration().as_micros()).sum();
+        println!(
+            "Tiles: {} tiles averaging {:.1} ms, slowest {} ms at ({}, {}).",
+            times.len(),
+            total as f64 / times.len() as f64 / 1000.0,
+            slowest.duration().as_millis(),
+            slowest.tile().x(),
+            slowest.tile().y()
+        );
+    }
     println!("Draw:  {} ms.", draw_duration);
+    Ok(())
 }
 
-/// Writes the pixel array to an image
-fn write_image(data: Vec<Pixel>) {
-    let file = File::create("out.png").unwrap();
-    let w = BufWriter::new(file);
-
-    let mut encoder = Encoder::new(w, WIDTH, HEIGHT);
-    encoder.set(ColorType::RGBA).set(BitDepth::Eight);
-    let mut writer = encoder.write_header().unwrap();
+/// Renders the scene in passes, starting from a checkpoint if one is being
+/// resumed, writing previews with `preview` and saving checkpoints as their
+/// intervals pass
+fn render_progressive(
+    renderer: &Renderer,
+    scene: &Scene,
+    options: &Options,
+    preview: &dyn Fn(&Film) -> Result<(), String>,
+) -> Result<Film, Box<dyn Error>> {
+    let settings = renderer.settings();
+    let checkpoint_interval = options
+        .checkpoint_interval
+        .unwrap_or(Duration::from_secs(CHECKPOINT_INTERVAL));
+    let mut last_preview = Instant::now();
+    let mut last_checkpoint = Instant::now();
+    let mut last_progress = Progress::default();
+    let checkpoint = match &options.resume {
+        Some(path) => {
+            let checkpoint = Checkpoint::load(path)?;
+            println!("Resuming: {}.", describe_progress(&checkpoint.progress()));
+            last_progress = checkpoint.progress();
+            Some(checkpoint)
+        }
+        None => None,
+    };
+    let save = |film: &Film, progress: &Progress, path: &Path| {
+        Checkpoint::new(settings, scene, film.clone(), *progress).save(path)
+    };
+
+    let mut on_pass = |film: &Film, progress: &Progress| {
+        last_progress = *progress;
+        if let Some(interval) = settings.preview_interval {
+            if last_preview.elapsed() >= interval {
+                match preview(film) {
+                    Ok(()) => println!("Preview: {}.", describe_progress(progress)),
+                    Err(err) => eprintln!("warning: {}", err),
+                }
+                last_preview = Instant::now();
+            }
+        }
+        if let Some(path) = &options.checkpoint {
+            if last_checkpoint.elapsed() >= checkpoint_interval {
+                match save(film, progress, path) {
+                    Ok(()) => println!("Checkpoint: {}.", describe_progress(progress)),
+                    Err(err) => eprintln!("warning: {}", err),
+                }
+                last_checkpoint = Instant::now();
+            }
+        }
+    };
+    let film = match checkpoint {
+        Some(checkpoint) => renderer.resume(scene, checkpoint, &mut on_pass)?,
+        None => renderer.render_progressive(scene, &mut on_pass),
+    };
+    println!("Passes: {}.", describe_progress(&last_progress));
+
+    // Saves the finished render too, so it can be resumed for more samples
+    if let Some(path) = &options.checkpoint {
+        save(&film, &last_progress, path)?;
+    }
+    Ok(film)
+}
 
-    let pixel_data = data.into_pixel_data();
+/// Describes how far a progressive render has come
+fn describe_progress(progress: &Progress) -> String {
+    let noise = match progress.noise() {
+        Some(noise) => format!("{:.4}", noise),
+        None => "unknown".to_string(),
+    };
+    format!(
+        "{} passes, {} samples per pixel, noise {}, {:.1} s",
+        progress.passes(),
+        progress.samples(),
+        noise,
+        progress.elapsed().as_secs_f32()
+    )
+}
 
-    writer.write_image_data(&pixel_data).unwrap();
+/// Writes how long each tile took to render as CSV, in the order the tiles
+/// finished
+fn write_tile_times(path: &Path, times: &[TileTime]) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, "x,y,width,height,thread,milliseconds")?;
+    for time in times {
+        let tile = time.tile();
+        writeln!(
+            w,
+            "{},{},{},{},{},{:.3}",
+            tile.x(),
+            tile.y(),
+            tile.width(),
+            tile.height(),
+            time.thread(),
+            time.duration().as_secs_f64() * 1000.0
+        )?;
+    }
+    w.flush()
 }
@@ -0,0 +1,831 @@
+use std::f32::consts::PI;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::microfacet::{self, fresnel_conductor, fresnel_dielectric, Frame, Ggx};
+use crate::rayhit::RayHit;
+use crate::sampling::{cosine_hemisphere, orthonormal_basis};
+use crate::texture::Texture;
+use crate::vector::Vector3;
+
+/// A direction chosen by sampling a material
+#[derive(Debug, Copy, Clone)]
+pub struct BsdfSample {
+    /// Normalized direction the light arrives from
+    pub direction: Vector3,
+    /// BSDF times the cosine term, divided by the pdf, which scales the
+    /// light arriving from the direction
+    pub weight: Vector3,
+    /// Probability density of choosing the direction, or 0 if it came from
+    /// a perfectly sharp reflection or refraction
+    pub pdf: f32,
+    /// Whether the direction came from a perfectly sharp reflection or
+    /// refraction
+    pub specular: bool,
+}
+
+/// A perfectly sharp direction light is reflected or refracted into, such
+/// as the reflection off a mirror
+#[derive(Debug, Copy, Clone)]
+pub struct SpecularLobe {
+    /// Normalized direction the light arrives from
+    pub direction: Vector3,
+    /// Fraction of the light arriving from the direction that is scattered
+    /// towards the viewer
+    pub weight: Vector3,
+}
+
+/// Material defines how light scatters off and is emitted from a surface
+///
+/// Every direction points away from the surface: `outgoing` towards the
+/// viewer and `incoming` towards where the light arrives from. The hit's
+/// normal is the surface's outward normal, so materials work out which side
+/// each direction is on themselves.
+pub trait Material: Debug + Send + Sync {
+    /// Evaluates the BSDF for light arriving along `incoming` and leaving
+    /// along `outgoing`, not including any perfectly sharp lobes
+    fn evaluate(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> Vector3;
+
+    /// Chooses a direction for light to arrive from, or returns None if the
+    /// surface absorbs all light
+    ///
+    /// `lobe` in 0..1 picks between the material's ways of scattering, such
+    /// as reflection and refraction, and `u` and `v` in 0..1 pick a direction
+    /// within it.
+    fn sample(
+        &self,
+        hit: &RayHit,
+        outgoing: Vector3,
+        lobe: f32,
+        u: f32,
+        v: f32,
+    ) -> Option<BsdfSample>;
+
+    /// Gets the probability density of `sample` choosing `incoming`, not
+    /// including any perfectly sharp lobes
+    fn pdf(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> f32;
+
+    /// Gets every perfectly sharp direction light is scattered from, so they
+    /// can be traced without sampling
+    fn specular_lobes(&self, _hit: &RayHit, _outgoing: Vector3) -> Vec<SpecularLobe> {
+        Vec::new()
+    }
+
+    /// Gets the light emitted by the surface along `outgoing`
+    fn emission(&self, _hit: &RayHit, _outgoing: Vector3) -> Vector3 {
+        Vector3::origin()
+    }
+
+    /// Gets the hit as the material shades it, which lets materials bend
+    /// the normal to add surface detail
+    ///
+    /// Integrators call this once on every hit before shading it.
+    fn shading_hit<'a>(&self, hit: &RayHit<'a>) -> RayHit<'a> {
+        *hit
+    }
+}
+
+/// Calculates a reflection vector given a source vector and normal vector
+pub fn reflect(i: Vector3, n: Vector3) -> Vector3 {
+    i - (n * (2.0 * n.dot(i)))
+}
+
+/// Calculates a refraction vector given a source vector, normal vector, and
+/// index of refraction
+pub fn refract(i: Vector3, n: Vector3, ior: f32) -> Vector3 {
+    let mut cosi = i.dot(n).clamp(-1.0, 1.0);
+    let mut etai = 1.0;
+    let mut etat = ior;
+    let mut normal = n;
+
+    if cosi < 0.0 {
+        cosi = -cosi;
+    } else {
+        std::mem::swap(&mut etai, &mut etat);
+        normal = -normal;
+    }
+    let eta = etai / etat;
+    let k = 1.0 - eta * eta * (1.0 - cosi * cosi);
+    let mut out = Vector3::origin();
+    if k >= 0.0 {
+        out = (i * eta) + (normal * (eta * cosi - k.sqrt()));
+    }
+    out
+}
+
+/// Calculates Fresnel reflective transmittance
+pub fn fresnel(i: Vector3, n: Vector3, ior: f32) -> f32 {
+    let mut cosi = i.dot(n).clamp(-1.0, 1.0);
+    let mut etai = 1.0;
+    let mut etat = ior;
+    let kr;
+    if cosi > 0.0 {
+        etai = ior;
+        etat = 1.0;
+    }
+    let sint = etai / etat * 0.0f32.max(1.0 - cosi * cosi).sqrt();
+    if sint >= 1.0 {
+        kr = 1.0;
+    } else {
+        let cost = 0.0f32.max(1.0 - sint * sint).sqrt();
+        cosi = cosi.abs();
+        let rs = ((etat * cosi) - (etai * cost)) / ((etat * cosi) + (etai * cost));
+        let rp = ((etai * cosi) - (etat * cost)) / ((etai * cosi) + (etat * cost));
+        kr = (rs * rs + rp * rp) / 2.0;
+    }
+    kr
+}
+
+/// Gets the surface normal flipped to the side `outgoing` is on
+pub(crate) fn facing_normal(hit: &RayHit, outgoing: Vector3) -> Vector3 {
+    if outgoing.dot(hit.normal()) < 0.0 {
+        -hit.normal()
+    } else {
+        hit.normal()
+    }
+}
+
+/// Checks whether two directions are on the same side of the surface
+pub(crate) fn same_side(hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> bool {
+    outgoing.dot(hit.normal()) * incoming.dot(hit.normal()) > 0.0
+}
+
+/// Gets the average of a color's channels
+pub(crate) fn average(color: Vector3) -> f32 {
+    (color.x + color.y + color.z) / 3.0
+}
+
+/// A perfectly diffuse surface that scatters light equally in every direction
+#[derive(Debug, Clone)]
+pub struct Lambertian {
+    color: Arc<dyn Texture>,
+}
+
+impl Lambertian {
+    /// Creates a new diffuse material with the given color or texture
+    pub fn new(color: impl Into<Arc<dyn Texture>>) -> Lambertian {
+        Lambertian {
+            color: color.into(),
+        }
+    }
+}
+
+impl Material for Lambertian {
+    fn evaluate(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> Vector3 {
+        if same_side(hit, outgoing, incoming) {
+            self.color.evaluate(hit) * (1.0 / PI)
+        } else {
+            Vector3::origin()
+        }
+    }
+
+    /// Chooses a cosine-weighted direction, which cancels the cosine term
+    /// and leaves just the color as the weight
+    fn sample(
+        &self,
+        hit: &RayHit,
+        outgoing: Vector3,
+        _lobe: f32,
+        u: f32,
+        v: f32,
+    ) -> Option<BsdfSample> {
+        let normal = facing_normal(hit, outgoing);
+        let direction = cosine_hemisphere(normal, u, v);
+        let cos_theta = direction.dot(normal);
+        if cos_theta <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            direction,
+            weight: self.color.evaluate(hit),
+            pdf: cos_theta / PI,
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> f32 {
+        if same_side(hit, outgoing, incoming) {
+            incoming.dot(hit.normal()).abs() / PI
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A diffuse surface with glossy Blinn-Phong highlights and an optional
+/// mirror coating
+///
+/// The coating reflects `reflectivity` of the light like a perfect mirror and
+/// the rest is scattered by the diffuse and glossy lobes.
+#[derive(Debug, Clone)]
+pub struct Phong {
+    diffuse: Arc<dyn Texture>,
+    specular: Arc<dyn Texture>,
+    exponent: f32,
+    reflectivity: f32,
+}
+
+impl Phong {
+    /// Creates a new Blinn-Phong material
+    ///
+    /// Higher exponents give smaller, sharper highlights.
+    pub fn new(
+        diffuse: impl Into<Arc<dyn Texture>>,
+        specular: impl Into<Arc<dyn Texture>>,
+        exponent: f32,
+        reflectivity: f32,
+    ) -> Phong {
+        Phong {
+            diffuse: diffuse.into(),
+            specular: specular.into(),
+            exponent,
+            reflectivity,
+        }
+    }
+
+    /// Gets the probability of sampling the glossy lobe instead of the
+    /// diffuse one
+    fn glossy_probability(&self, hit: &RayHit) -> f32 {
+        let diffuse = average(self.diffuse.evaluate(hit));
+        let specular = average(self.specular.evaluate(hit));
+        if diffuse + specular > 0.0 {
+            specular / (diffuse + specular)
+        } else {
+            0.0
+        }
+    }
+
+    /// Evaluates the diffuse and glossy lobes without the mirror coating
+    fn evaluate_lobes(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> Vector3 {
+        if !same_side(hit, outgoing, incoming) {
+            return Vector3::origin();
+        }
+        let normal = facing_normal(hit, outgoing);
+        let halfway = (outgoing + incoming).normalize();
+        let cos_halfway = normal.dot(halfway).max(0.0);
+
+        // Normalized so the highlight reflects about the same total light
+        // whatever its exponent
+        let glossy = (self.exponent + 8.0) / (8.0 * PI) * cos_halfway.powf(self.exponent);
+        self.diffuse.evaluate(hit) * (1.0 / PI) + self.specular.evaluate(hit) * glossy
+    }
+
+    /// Gets the probability density of the diffuse and glossy lobes
+    fn pdf_lobes(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> f32 {
+        if !same_side(hit, outgoing, incoming) {
+            return 0.0;
+        }
+        let normal = facing_normal(hit, outgoing);
+        let halfway = (outgoing + incoming).normalize();
+        let cos_halfway = normal.dot(halfway).max(0.0);
+        let glossy = self.glossy_probability(hit);
+
+        let diffuse_pdf = normal.dot(incoming).max(0.0) / PI;
+        let halfway_pdf = (self.exponent + 1.0) / (2.0 * PI) * cos_halfway.powf(self.exponent);
+        let glossy_pdf = halfway_pdf / (4.0 * outgoing.dot(halfway).abs()).max(f32::MIN_POSITIVE);
+        (1.0 - glossy) * diffuse_pdf + glossy * glossy_pdf
+    }
+}
+
+impl Material for Phong {
+    fn evaluate(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> Vector3 {
+        self.evaluate_lobes(hit, outgoing, incoming) * (1.0 - self.reflectivity)
+    }
+
+    /// Picks the mirror coating, the glossy lobe, or the diffuse lobe
+    fn sample(
+        &self,
+        hit: &RayHit,
+        outgoing: Vector3,
+        lobe: f32,
+        u: f32,
+        v: f32,
+    ) -> Option<BsdfSample> {
+        let normal = facing_normal(hit, outgoing);
+        if lobe < self.reflectivity {
+            return Some(BsdfSample {
+                direction: reflect(-outgoing, normal),
+                weight: Vector3::new_scalar(1.0),
+                pdf: 0.0,
+                specular: true,
+            });
+        }
+        let lobe = (lobe - self.reflectivity) / (1.0 - self.reflectivity);
+
+        let direction = if lobe < self.glossy_probability(hit) {
+            // Picks a halfway vector around the normal and reflects about it
+            let cos_theta = u.powf(1.0 / (self.exponent + 1.0));
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = 2.0 * PI * v;
+            let (tangent, bitangent) = orthonormal_basis(normal);
+            let halfway = tangent * (phi.cos() * sin_theta)
+                + bitangent * (phi.sin() * sin_theta)
+                + normal * cos_theta;
+            reflect(-outgoing, halfway)
+        } else {
+            cosine_hemisphere(normal, u, v)
+        };
+
+        let cos_theta = direction.dot(normal);
+        let pdf = self.pdf_lobes(hit, outgoing, direction);
+        if cos_theta <= 0.0 || pdf <= 0.0 {
+            return None;
+        }
+
+        // The coating's share of the light cancels with the chance of not
+        // picking it
+        Some(BsdfSample {
+            direction,
+            weight: self.evaluate_lobes(hit, outgoing, direction) * (cos_theta / pdf),
+            pdf: pdf * (1.0 - self.reflectivity),
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> f32 {
+        self.pdf_lobes(hit, outgoing, incoming) * (1.0 - self.reflectivity)
+    }
+
+    fn specular_lobes(&self, hit: &RayHit, outgoing: Vector3) -> Vec<SpecularLobe> {
+        if self.reflectivity <= 0.0 {
+            return Vec::new();
+        }
+        vec![SpecularLobe {
+            direction: reflect(-outgoing, facing_normal(hit, outgoing)),
+            weight: Vector3::new_scalar(self.reflectivity),
+        }]
+    }
+}
+
+/// A perfect mirror tinted by a color
+#[derive(Debug, Clone)]
+pub struct Mirror {
+    color: Arc<dyn Texture>,
+}
+
+impl Mirror {
+    /// Creates a new mirror that reflects the given fraction of each color
+    pub fn new(color: impl Into<Arc<dyn Texture>>) -> Mirror {
+        Mirror {
+            color: color.into(),
+        }
+    }
+}
+
+impl Material for Mirror {
+    fn evaluate(&self, _hit: &RayHit, _outgoing: Vector3, _incoming: Vector3) -> Vector3 {
+        Vector3::origin()
+    }
+
+    fn sample(
+        &self,
+        hit: &RayHit,
+        outgoing: Vector3,
+        _lobe: f32,
+        _u: f32,
+        _v: f32,
+    ) -> Option<BsdfSample> {
+        Some(BsdfSample {
+            direction: reflect(-outgoing, facing_normal(hit, outgoing)),
+            weight: self.color.evaluate(hit),
+            pdf: 0.0,
+            specular: true,
+        })
+    }
+
+    fn pdf(&self, _hit: &RayHit, _outgoing: Vector3, _incoming: Vector3) -> f32 {
+        0.0
+    }
+
+    fn specular_lobes(&self, hit: &RayHit, outgoing: Vector3) -> Vec<SpecularLobe> {
+        vec![SpecularLobe {
+            direction: reflect(-outgoing, facing_normal(hit, outgoing)),
+            weight: self.color.evaluate(hit),
+        }]
+    }
+}
+
+/// A smooth transparent surface such as glass or water that both reflects
+/// and refracts light
+#[derive(Debug, Clone)]
+pub struct Dielectric {
+    ior: f32,
+    color: Arc<dyn Texture>,
+}
+
+impl Dielectric {
+    /// Creates a new dielectric with the given index of refraction
+    ///
+    /// Refracted light is tinted by `color`, while reflections are left
+    /// untinted.
+    pub fn new(ior: f32, color: impl Into<Arc<dyn Texture>>) -> Dielectric {
+        Dielectric {
+            ior,
+            color: color.into(),
+        }
+    }
+}
+
+impl Material for Dielectric {
+    fn evaluate(&self, _hit: &RayHit, _outgoing: Vector3, _incoming: Vector3) -> Vector3 {
+        Vector3::origin()
+    }
+
+    /// Reflects or refracts with probability given by the Fresnel term
+    fn sample(
+        &self,
+        hit: &RayHit,
+        outgoing: Vector3,
+        lobe: f32,
+        _u: f32,
+        _v: f32,
+    ) -> Option<BsdfSample> {
+        let kr = fresnel(-outgoing, hit.normal(), self.ior);
+        let (direction, weight) = if lobe < kr {
+            (reflect(-outgoing, hit.normal()), Vector3::new_scalar(1.0))
+        } else {
+            (
+                refract(-outgoing, hit.normal(), self.ior).normalize(),
+                self.color.evaluate(hit),
+            )
+        };
+        Some(BsdfSample {
+            direction,
+            weight,
+            pdf: 0.0,
+            specular: true,
+        })
+    }
+
+    fn pdf(&self, _hit: &RayHit, _outgoing: Vector3, _incoming: Vector3) -> f32 {
+        0.0
+    }
+
+    fn specular_lobes(&self, hit: &RayHit, outgoing: Vector3) -> Vec<SpecularLobe> {
+        smooth_dielectric_lobes(hit, outgoing, self.ior, self.color.evaluate(hit))
+    }
+}
+
+/// Gets the sharp reflection and refraction off a smooth dielectric with
+/// the given index of refraction, tinting the refraction by `tint`
+pub(crate) fn smooth_dielectric_lobes(
+    hit: &RayHit,
+    outgoing: Vector3,
+    ior: f32,
+    tint: Vector3,
+) -> Vec<SpecularLobe> {
+    let kr = fresnel(-outgoing, hit.normal(), ior);
+    let mut lobes = vec![SpecularLobe {
+        direction: reflect(-outgoing, hit.normal()),
+        weight: Vector3::new_scalar(kr),
+    }];
+
+    // Checks if the surface has total internal reflection
+    if kr < 1.0 {
+        lobes.push(SpecularLobe {
+            direction: refract(-outgoing, hit.normal(), ior).normalize(),
+            weight: tint * (1.0 - kr),
+        });
+    }
+    lobes
+}
+
+/// A surface that emits light from its front side and absorbs everything
+/// that reaches it
+#[derive(Debug, Clone)]
+pub struct Emissive {
+    color: Arc<dyn Texture>,
+    intensity: f32,
+}
+
+impl Emissive {
+    /// Creates a new emitter shining with the given color and strength
+    pub fn new(color: impl Into<Arc<dyn Texture>>, intensity: f32) -> Emissive {
+        Emissive {
+            color: color.into(),
+            intensity,
+        }
+    }
+}
+
+impl Material for Emissive {
+    fn evaluate(&self, _hit: &RayHit, _outgoing: Vector3, _incoming: Vector3) -> Vector3 {
+        Vector3::origin()
+    }
+
+    fn sample(
+        &self,
+        _hit: &RayHit,
+        _outgoing: Vector3,
+        _lobe: f32,
+        _u: f32,
+        _v: f32,
+    ) -> Option<BsdfSample> {
+        None
+    }
+
+    fn pdf(&self, _hit: &RayHit, _outgoing: Vector3, _incoming: Vector3) -> f32 {
+        0.0
+    }
+
+    fn emission(&self, hit: &RayHit, outgoing: Vector3) -> Vector3 {
+        if outgoing.dot(hit.normal()) > 0.0 {
+            self.color.evaluate(hit) * self.intensity
+        } else {
+            Vector3::origin()
+        }
+    }
+}
+
+/// Common metals whose measured complex indices of refraction can be used
+/// for conductors
+///
+/// Each index is averaged over the red, green, and blue parts of the
+/// spectrum.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Metal {
+    Aluminium,
+    Copper,
+    Gold,
+    Iron,
+    Silver,
+}
+
+impl Metal {
+    /// Gets the metal with the given lowercase name
+    pub fn from_name(name: &str) -> Option<Metal> {
+        match name {
+            "aluminium" | "aluminum" => Some(Metal::Aluminium),
+            "copper" => Some(Metal::Copper),
+            "gold" => Some(Metal::Gold),
+            "iron" => Some(Metal::Iron),
+            "silver" => Some(Metal::Silver),
+            _ => None,
+        }
+    }
+
+    /// Gets the real part of the metal's index of refraction
+    pub fn eta(self) -> Vector3 {
+        match self {
+            Metal::Aluminium => Vector3::new(1.657, 0.880, 0.521),
+            Metal::Copper => Vector3::new(0.200, 0.924, 1.102),
+            Metal::Gold => Vector3::new(0.143, 0.374, 1.442),
+            Metal::Iron => Vector3::new(2.911, 2.950, 2.585),
+            Metal::Silver => Vector3::new(0.155, 0.117, 0.138),
+        }
+    }
+
+    /// Gets the imaginary part of the metal's index of refraction, which
+    /// describes how quickly light is absorbed inside it
+    pub fn k(self) -> Vector3 {
+        match self {
+            Metal::Aluminium => Vector3::new(9.224, 6.270, 4.837),
+            Metal::Copper => Vector3::new(3.912, 2.452, 2.142),
+            Metal::Gold => Vector3::new(3.983, 2.386, 1.603),
+            Metal::Iron => Vector3::new(3.089, 2.932, 2.767),
+            Metal::Silver => Vector3::new(4.828, 3.122, 2.147),
+        }
+    }
+}
+
+/// A metal whose reflections are spread out by microscopic roughness,
+/// modeled with the GGX microfacet distribution
+///
+/// A roughness of 0 gives a perfectly smooth metal.
+#[derive(Debug, Copy, Clone)]
+pub struct Conductor {
+    eta: Vector3,
+    k: Vector3,
+    roughness: f32,
+    distribution: Ggx,
+}
+
+impl Conductor {
+    /// Creates a new conductor with the complex index of refraction
+    /// `eta + ik` for each color channel and a roughness in 0..1
+    pub fn new(eta: Vector3, k: Vector3, roughness: f32) -> Conductor {
+        Conductor {
+            eta,
+            k,
+            roughness,
+            distribution: Ggx::new(roughness),
+        }
+    }
+
+    /// Creates a new conductor made of one of the preset metals
+    pub fn from_metal(metal: Metal, roughness: f32) -> Conductor {
+        Conductor::new(metal.eta(), metal.k(), roughness)
+    }
+
+    /// Evaluates the BSDF and its sampling density for local directions
+    fn evaluate_local(&self, outgoing: Vector3, incoming: Vector3) -> (Vector3, f32) {
+        if outgoing.z <= 0.0 || incoming.z <= 0.0 {
+            return (Vector3::origin(), 0.0);
+        }
+        let m = (outgoing + incoming).normalize();
+        let fresnel = fresnel_conductor(outgoing.dot(m), self.eta, self.k);
+        let d = self.distribution.d(m);
+        let g = self.distribution.g(outgoing, incoming);
+        let bsdf = fresnel * (d * g / (4.0 * outgoing.z * incoming.z));
+        let pdf = self.distribution.visible_pdf(outgoing, m) / (4.0 * outgoing.dot(m).abs());
+        (bsdf, pdf)
+    }
+}
+
+impl Material for Conductor {
+    fn evaluate(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> Vector3 {
+        if self.roughness <= 0.0 {
+            return Vector3::origin();
+        }
+        let frame = Frame::new(facing_normal(hit, outgoing));
+        self.evaluate_local(frame.to_local(outgoing), frame.to_local(incoming))
+            .0
+    }
+
+    /// Reflects about a microfacet normal picked from those visible from
+    /// `outgoing`
+    fn sample(
+        &self,
+        hit: &RayHit,
+        outgoing: Vector3,
+        _lobe: f32,
+        u: f32,
+        v: f32,
+    ) -> Option<BsdfSample> {
+        let normal = facing_normal(hit, outgoing);
+        if self.roughness <= 0.0 {
+            return Some(BsdfSample {
+                direction: reflect(-outgoing, normal),
+                weight: fresnel_conductor(outgoing.dot(normal), self.eta, self.k),
+                pdf: 0.0,
+                specular: true,
+            });
+        }
+
+        let frame = Frame::new(normal);
+        let local_outgoing = frame.to_local(outgoing);
+        let m = self.distribution.sample_visible(local_outgoing, u, v);
+        let local_incoming = reflect(-local_outgoing, m);
+        let (bsdf, pdf) = self.evaluate_local(local_outgoing, local_incoming);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            direction: frame.to_world(local_incoming),
+            weight: bsdf * (local_incoming.z / pdf),
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> f32 {
+        if self.roughness <= 0.0 {
+            return 0.0;
+        }
+        let frame = Frame::new(facing_normal(hit, outgoing));
+        self.evaluate_local(frame.to_local(outgoing), frame.to_local(incoming))
+            .1
+    }
+
+    /// Whitted tracing can only follow sharp reflections, so rough metals
+    /// reflect as if they were smooth
+    fn specular_lobes(&self, hit: &RayHit, outgoing: Vector3) -> Vec<SpecularLobe> {
+        let normal = facing_normal(hit, outgoing);
+        vec![SpecularLobe {
+            direction: reflect(-outgoing, normal),
+            weight: fresnel_conductor(outgoing.dot(normal), self.eta, self.k),
+        }]
+    }
+}
+
+/// A transparent surface such as frosted glass whose reflections and
+/// refractions are spread out by microscopic roughness, modeled with the GGX
+/// microfacet distribution (Walter et al., "Microfacet Models for Refraction
+/// through Rough Surfaces")
+#[derive(Debug, Clone)]
+pub struct RoughDielectric {
+    ior: f32,
+    color: Arc<dyn Texture>,
+    distribution: Ggx,
+}
+
+impl RoughDielectric {
+    /// Creates a new rough dielectric with the given index of refraction
+    /// and a roughness in 0..1
+    ///
+    /// Refracted light is tinted by `color`, while reflections are left
+    /// untinted.
+    pub fn new(ior: f32, color: impl Into<Arc<dyn Texture>>, roughness: f32) -> RoughDielectric {
+        RoughDielectric {
+            ior,
+            color: color.into(),
+            distribution: Ggx::new(roughness),
+        }
+    }
+
+    /// Evaluates the BSDF and its sampling density for directions in the
+    /// local frame of the outward normal, tinting refractions by `tint`
+    fn evaluate_local(
+        &self,
+        outgoing: Vector3,
+        incoming: Vector3,
+        tint: Vector3,
+    ) -> (Vector3, f32) {
+        let (cos_o, cos_i) = (outgoing.z, incoming.z);
+        if cos_o == 0.0 || cos_i == 0.0 {
+            return (Vector3::origin(), 0.0);
+        }
+
+        // Finds the microfacet normal that scatters between the directions
+        let reflected = cos_o * cos_i > 0.0;
+        let eta = match (reflected, cos_o > 0.0) {
+            (true, _) => 1.0,
+            (false, true) => self.ior,
+            (false, false) => 1.0 / self.ior,
+        };
+        let m = incoming * eta + outgoing;
+        if m.dot(m) == 0.0 {
+            return (Vector3::origin(), 0.0);
+        }
+        let m = m.normalize();
+        let m = if m.z < 0.0 { -m } else { m };
+
+        // Ignores microfacets that face away from either direction
+        if m.dot(incoming) * cos_i < 0.0 || m.dot(outgoing) * cos_o < 0.0 {
+            return (Vector3::origin(), 0.0);
+        }
+
+        let fresnel = fresnel_dielectric(outgoing.dot(m), self.ior);
+        let d = self.distribution.d(m);
+        let g = self.distribution.g(outgoing, incoming);
+        let visible = self.distribution.visible_pdf(outgoing, m);
+        if reflected {
+            let bsdf = fresnel * d * g / (4.0 * (cos_i * cos_o).abs());
+            let pdf = visible / (4.0 * outgoing.dot(m).abs()) * fresnel;
+            (Vector3::new_scalar(bsdf), pdf)
+        } else {
+            let denom = (incoming.dot(m) + outgoing.dot(m) / eta).powi(2);
+            let bsdf = (1.0 - fresnel)
+                * d
+                * g
+                * (incoming.dot(m) * outgoing.dot(m) / (cos_i * cos_o * denom)).abs();
+            let pdf = visible * incoming.dot(m).abs() / denom * (1.0 - fresnel);
+            (tint * bsdf, pdf)
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn evaluate(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> Vector3 {
+        let frame = Frame::new(hit.normal());
+        let tint = self.color.evaluate(hit);
+        self.evaluate_local(frame.to_local(outgoing), frame.to_local(incoming), tint)
+            .0
+    }
+
+    /// Picks a microfacet normal visible from `outgoing`, then reflects or
+    /// refracts through it with probability given by the Fresnel term
+    fn sample(
+        &self,
+        hit: &RayHit,
+        outgoing: Vector3,
+        lobe: f32,
+        u: f32,
+        v: f32,
+    ) -> Option<BsdfSample> {
+        let frame = Frame::new(hit.normal());
+        let local_outgoing = frame.to_local(outgoing);
+        let m = self.distribution.sample_visible(local_outgoing, u, v);
+        let fresnel = fresnel_dielectric(local_outgoing.dot(m), self.ior);
+        let local_incoming = if lobe < fresnel {
+            reflect(-local_outgoing, m)
+        } else {
+            microfacet::refract(local_outgoing, m, self.ior)?.0
+        };
+
+        let tint = self.color.evaluate(hit);
+        let (bsdf, pdf) = self.evaluate_local(local_outgoing, local_incoming, tint);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample {
+            direction: frame.to_world(local_incoming),
+            weight: bsdf * (local_incoming.z.abs() / pdf),
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> f32 {
+        let frame = Frame::new(hit.normal());
+        let tint = Vector3::new_scalar(1.0);
+        self.evaluate_local(frame.to_local(outgoing), frame.to_local(incoming), tint)
+            .1
+    }
+
+    /// Whitted tracing can only follow sharp directions, so rough
+    /// dielectrics reflect and refract as if they were smooth
+    fn specular_lobes(&self, hit: &RayHit, outgoing: Vector3) -> Vec<SpecularLobe> {
+        smooth_dielectric_lobes(hit, outgoing, self.ior, self.color.evaluate(hit))
+    }
+}
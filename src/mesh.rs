@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::intersectable::Intersectable;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::rayhit::RayHit;
+use crate::triangle::intersect_triangle;
+use crate::vector::Vector3;
+
+/// An indexed triangle mesh whose triangles share a single vertex buffer
+#[derive(Debug)]
+pub struct TriangleMesh {
+    positions: Vec<Vector3>,
+    normals: Option<Vec<Vector3>>,
+    uvs: Option<Vec<(f32, f32)>>,
+    indices: Vec<[u32; 3]>,
+    material: Arc<dyn Material>,
+    bounds: Aabb,
+    bvh: Bvh,
+}
+
+impl TriangleMesh {
+    /// Creates a new flat shaded mesh from a vertex buffer and a list of
+    /// counter-clockwise triangles indexing into it
+    pub fn new(
+        positions: Vec<Vector3>,
+        indices: Vec<[u32; 3]>,
+        material: Arc<dyn Material>,
+    ) -> TriangleMesh {
+        TriangleMesh::with_attributes(positions, None, None, indices, material)
+    }
+
+    /// Creates a new smooth shaded mesh with a normal for every vertex
+    ///
+    /// The normal buffer is indexed by the same triangles as the vertex buffer.
+    pub fn with_normals(
+        positions: Vec<Vector3>,
+        normals: Vec<Vector3>,
+        indices: Vec<[u32; 3]>,
+        material: Arc<dyn Material>,
+    ) -> TriangleMesh {
+        TriangleMesh::with_attributes(positions, Some(normals), None, indices, material)
+    }
+
+    /// Creates a new mesh with optional normals and texture coordinates for
+    /// every vertex, and builds the hierarchy over its triangles
+    ///
+    /// Without normals the mesh is flat shaded, and without texture
+    /// coordinates every triangle gets its own barycentric ones.
+    pub fn with_attributes(
+        positions: Vec<Vector3>,
+        normals: Option<Vec<Vector3>>,
+        uvs: Option<Vec<(f32, f32)>>,
+        indices: Vec<[u32; 3]>,
+        material: Arc<dyn Material>,
+    ) -> TriangleMesh {
+        if let Some(normals) = &normals {
+            assert_eq!(
+                positions.len(),
+                normals.len(),
+                "mesh needs exactly one normal per vertex"
+            );
+        }
+        if let Some(uvs) = &uvs {
+            assert_eq!(
+                positions.len(),
+                uvs.len(),
+                "mesh needs exactly one texture coordinate per vertex"
+            );
+        }
+
+        let triangle_bounds: Vec<Aabb> = indices
+            .iter()
+            .map(|[i0, i1, i2]| {
+                Aabb::empty()
+                    .grow(positions[*i0 as usize])
+                    .grow(positions[*i1 as usize])
+                    .grow(positions[*i2 as usize])
+            })
+            .collect();
+        let bounds = triangle_bounds
+            .iter()
+            .fold(Aabb::empty(), |bounds, triangle| bounds.union(*triangle));
+        let bvh = Bvh::new(&triangle_bounds);
+
+        TriangleMesh {
+            positions,
+            normals,
+            uvs,
+            indices,
+            material,
+            bounds,
+            bvh,
+        }
+    }
+
+    /// Gets the number of triangles in the mesh
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Checks whether the mesh has no triangles
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Gets the vertex buffer
+    pub fn positions(&self) -> &[Vector3] {
+        &self.positions
+    }
+
+    /// Gets the vertex normals, if the mesh is smooth shaded
+    pub fn normals(&self) -> Option<&[Vector3]> {
+        self.normals.as_deref()
+    }
+
+    /// Gets the vertex texture coordinates, if the mesh has them
+    pub fn uvs(&self) -> Option<&[(f32, f32)]> {
+        self.uvs.as_deref()
+    }
+
+    /// Gets the triangles as indices into the vertex buffer
+    pub fn indices(&self) -> &[[u32; 3]] {
+        &self.indices
+    }
+
+    /// Gets the material shared by every triangle
+    pub fn material(&self) -> &Arc<dyn Material> {
+        &self.material
+    }
+
+    /// Gets the three vertex positions of a triangle
+    fn vertices(&self, triangle: usize) -> (Vector3, Vector3, Vector3) {
+        let [i0, i1, i2] = self.indices[triangle];
+        (
+            self.positions[i0 as usize],
+            self.positions[i1 as usize],
+            self.positions[i2 as usize],
+        )
+    }
+
+    /// Calculates the shading normal of a triangle at the given barycentric
+    /// coordinates, interpolating vertex normals when the mesh has them
+    fn normal(&self, triangle: usize, u: f32, v: f32) -> Vector3 {
+        match &self.normals {
+            Some(normals) => {
+                let [i0, i1, i2] = self.indices[triangle];
+                let n0 = normals[i0 as usize];
+                let n1 = normals[i1 as usize];
+                let n2 = normals[i2 as usize];
+                (n0 * (1.0 - u - v) + n1 * u + n2 * v).normalize()
+            }
+            None => self.face_normal(triangle),
+        }
+    }
+
+    /// Calculates the normal of a triangle's plane, facing the side its
+    /// corners run counter-clockwise on
+    fn face_normal(&self, triangle: usize) -> Vector3 {
+        let (v0, v1, v2) = self.vertices(triangle);
+        (v1 - v0).cross(v2 - v0).normalize()
+    }
+
+    /// Calculates the texture coordinates of a triangle at the given
+    /// barycentric coordinates, interpolating vertex texture coordinates
+    /// when the mesh has them
+    fn uv(&self, triangle: usize, u: f32, v: f32) -> (f32, f32) {
+        match &self.uvs {
+            Some(uvs) => {
+                let [i0, i1, i2] = self.indices[triangle];
+                let (u0, v0) = uvs[i0 as usize];
+                let (u1, v1) = uvs[i1 as usize];
+                let (u2, v2) = uvs[i2 as usize];
+                let w = 1.0 - u - v;
+                (u0 * w + u1 * u + u2 * v, v0 * w + v1 * u + v2 * v)
+            }
+            None => (u, v),
+        }
+    }
+
+    /// Calculates the directions the texture coordinates u and v grow in
+    /// across a triangle, falling back to its edges when the mesh has no
+    /// texture coordinates or they are degenerate
+    fn tangents(&self, triangle: usize) -> (Vector3, Vector3) {
+        let (v0, v1, v2) = self.vertices(triangle);
+        let (e1, e2) = (v1 - v0, v2 - v0);
+        if let Some(uvs) = &self.uvs {
+            let [i0, i1, i2] = self.indices[triangle];
+            let (u0, w0) = uvs[i0 as usize];
+            let (u1, w1) = uvs[i1 as usize];
+            let (u2, w2) = uvs[i2 as usize];
+            let (du1, dv1) = (u1 - u0, w1 - w0);
+            let (du2, dv2) = (u2 - u0, w2 - w0);
+            let det = du1 * dv2 - du2 * dv1;
+            if det.abs() > 1e-12 {
+                let tangent = (e1 * dv2 - e2 * dv1) * (1.0 / det);
+                let bitangent = (e2 * du1 - e1 * du2) * (1.0 / det);
+                return (tangent, bitangent);
+            }
+        }
+        (e1, e2)
+    }
+}
+
+impl Intersectable for TriangleMesh {
+    /// Determines whether the given ray has intersected with any triangle
+    /// of the mesh and generates a RayHit for the closest one
+    fn intersect(&self, ray: Ray) -> Option<RayHit<'_>> {
+        let (triangle, t, u, v) = self.bvh.closest_hit(
+            ray,
+            |triangle| {
+                let (v0, v1, v2) = self.vertices(triangle);
+                intersect_triangle(ray, v0, v1, v2).map(|(t, u, v)| (triangle, t, u, v))
+            },
+            |(_, t, _, _)| *t,
+        )?;
+
+        // Calculates ray hit position and the interpolated normal and
+        // texture coordinates
+        let position = ray.origin() + ray.direction() * t;
+        let uv = self.uv(triangle, u, v);
+        let (tangent, bitangent) = self.tangents(triangle);
+        let face = self.face_normal(triangle);
+
+        // Smooth meshes shade with the interpolated normal but keep the
+        // face's, turned to the same side, as the geometric normal
+        let hit = match self.normals {
+            Some(_) => {
+                let normal = self.normal(triangle, u, v);
+                let face = if face.dot(normal) < 0.0 { -face } else { face };
+                RayHit::new(position, face, uv, t, &*self.material).with_normal(normal)
+            }
+            None => RayHit::new(position, face, uv, t, &*self.material),
+        };
+        Some(hit.with_tangents(tangent, bitangent))
+    }
+
+    /// Gets the box around all of the mesh's triangles
+    fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+}
@@ -0,0 +1,210 @@
+use std::f32::consts::PI;
+
+use crate::sampling::orthonormal_basis;
+use crate::vector::Vector3;
+
+/// Smallest alpha used by the distribution, which keeps nearly smooth
+/// surfaces from producing infinite densities
+const MIN_ALPHA: f32 = 1e-3;
+
+/// An orthonormal basis around a surface normal, used to move directions in
+/// and out of the local space where the normal is +z
+#[derive(Debug, Copy, Clone)]
+pub struct Frame {
+    tangent: Vector3,
+    bitangent: Vector3,
+    normal: Vector3,
+}
+
+impl Frame {
+    /// Creates a frame around the given normalized vector
+    pub fn new(normal: Vector3) -> Frame {
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        Frame {
+            tangent,
+            bitangent,
+            normal,
+        }
+    }
+
+    /// Converts a world space direction into the local frame
+    pub fn to_local(&self, v: Vector3) -> Vector3 {
+        Vector3::new(
+            v.dot(self.tangent),
+            v.dot(self.bitangent),
+            v.dot(self.normal),
+        )
+    }
+
+    /// Converts a local direction back into world space
+    pub fn to_world(&self, v: Vector3) -> Vector3 {
+        self.tangent * v.x + self.bitangent * v.y + self.normal * v.z
+    }
+}
+
+/// The isotropic GGX (Trowbridge-Reitz) distribution of microfacet normals
+///
+/// Every direction is given in a local frame where the surface normal is +z.
+#[derive(Debug, Copy, Clone)]
+pub struct Ggx {
+    alpha: f32,
+}
+
+impl Ggx {
+    /// Creates a distribution with the given perceptual roughness in 0..1,
+    /// which is squared to get the distribution's alpha
+    pub fn new(roughness: f32) -> Ggx {
+        Ggx {
+            alpha: (roughness * roughness).max(MIN_ALPHA),
+        }
+    }
+
+    /// Gets the density of microfacets facing along `m`
+    pub fn d(&self, m: Vector3) -> f32 {
+        if m.z <= 0.0 {
+            return 0.0;
+        }
+        let cos2 = m.z * m.z;
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        let alpha2 = self.alpha * self.alpha;
+        let e = 1.0 + tan2 / alpha2;
+        1.0 / (PI * alpha2 * cos2 * cos2 * e * e)
+    }
+
+    /// Smith's auxiliary function, measuring how much of the surface is
+    /// hidden by other microfacets as seen from `w`
+    fn lambda(&self, w: Vector3) -> f32 {
+        let cos2 = w.z * w.z;
+        if cos2 <= 0.0 {
+            return f32::INFINITY;
+        }
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// Gets the fraction of microfacets visible from `w`
+    pub fn g1(&self, w: Vector3) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Gets the fraction of microfacets visible from both directions, using
+    /// the height-correlated Smith masking-shadowing function
+    pub fn g(&self, wo: Vector3, wi: Vector3) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Gets the density of visible microfacet normals `m` as seen from `w`,
+    /// which is the density `sample_visible` picks them with
+    pub fn visible_pdf(&self, w: Vector3, m: Vector3) -> f32 {
+        if w.z == 0.0 {
+            return 0.0;
+        }
+        self.g1(w) / w.z.abs() * self.d(m) * w.dot(m).abs()
+    }
+
+    /// Picks a microfacet normal visible from `w` using `u` and `v` in 0..1
+    ///
+    /// Follows Heitz's "Sampling the GGX Distribution of Visible Normals",
+    /// which never picks normals facing away from `w`.
+    pub fn sample_visible(&self, w: Vector3, u: f32, v: f32) -> Vector3 {
+        // Stretches the view direction to a hemisphere configuration
+        let mut wh = Vector3::new(self.alpha * w.x, self.alpha * w.y, w.z).normalize();
+        if wh.z < 0.0 {
+            wh = -wh;
+        }
+        let t1 = if wh.z < 0.99999 {
+            Vector3::new(0.0, 0.0, 1.0).cross(wh).normalize()
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = wh.cross(t1);
+
+        // Picks a point on the projected disk, squashed towards the visible
+        // half of the hemisphere
+        let r = u.sqrt();
+        let phi = 2.0 * PI * v;
+        let x = r * phi.cos();
+        let mut y = r * phi.sin();
+        let h = (1.0 - x * x).max(0.0).sqrt();
+        let s = (1.0 + wh.z) / 2.0;
+        y = (1.0 - s) * h + s * y;
+        let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+
+        // Unstretches the normal back to the ellipsoid configuration
+        let nh = t1 * x + t2 * y + wh * z;
+        Vector3::new(self.alpha * nh.x, self.alpha * nh.y, nh.z.max(1e-6)).normalize()
+    }
+}
+
+/// Calculates the Fresnel reflectance of a dielectric boundary
+///
+/// `cos_i` is the cosine between the incident direction and the normal on
+/// the outside, and `eta` is the inside's index of refraction over the
+/// outside's. Negative cosines mean the light arrives from the inside.
+pub fn fresnel_dielectric(cos_i: f32, eta: f32) -> f32 {
+    let (cos_i, eta) = if cos_i < 0.0 {
+        (-cos_i, 1.0 / eta)
+    } else {
+        (cos_i, eta)
+    };
+    let cos_i = cos_i.min(1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_t = (1.0 - sin2_t).max(0.0).sqrt();
+    let parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    (parallel * parallel + perpendicular * perpendicular) / 2.0
+}
+
+/// Calculates the Fresnel reflectance of a conductor with the complex index
+/// of refraction `eta + ik` for each color channel
+pub fn fresnel_conductor(cos_i: f32, eta: Vector3, k: Vector3) -> Vector3 {
+    let channel = |eta: f32, k: f32| {
+        let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+        let sin2 = 1.0 - cos2;
+        let eta2 = eta * eta;
+        let k2 = k * k;
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_i.clamp(0.0, 1.0) * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        (rp + rs) / 2.0
+    };
+    Vector3::new(
+        channel(eta.x, k.x),
+        channel(eta.y, k.y),
+        channel(eta.z, k.z),
+    )
+}
+
+/// Refracts the direction `w`, which points away from the surface, through
+/// a boundary with normal `n` and relative index of refraction `eta`
+///
+/// Returns None on total internal reflection. Otherwise returns the
+/// refracted direction along with the relative index of refraction seen
+/// from `w`'s side.
+pub fn refract(w: Vector3, n: Vector3, eta: f32) -> Option<(Vector3, f32)> {
+    let mut cos_i = n.dot(w);
+    let (mut eta, mut n) = (eta, n);
+    if cos_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_i = -cos_i;
+        n = -n;
+    }
+    let sin2_i = (1.0 - cos_i * cos_i).max(0.0);
+    let sin2_t = sin2_i / (eta * eta);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some((-w * (1.0 / eta) + n * (cos_i / eta - cos_t), eta))
+}
@@ -0,0 +1,197 @@
+use crate::rng::Rng;
+use crate::vector::Vector3;
+
+/// Ken Perlin's improved gradient noise ("Improving Noise", 2002), a smooth
+/// pseudo-random function of 3D space, and his simplex noise over the same
+/// permutation table
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    permutation: Vec<u8>,
+}
+
+impl Perlin {
+    /// Creates noise whose pattern is chosen by `seed`
+    pub fn new(seed: u64) -> Perlin {
+        // Shuffles the table with Fisher-Yates, then repeats it so lookups
+        // never need to wrap
+        let mut rng = Rng::new(seed, 0);
+        let mut table: Vec<u8> = (0..=255).collect();
+        for i in (1..table.len()).rev() {
+            let j = rng.next_u32() as usize % (i + 1);
+            table.swap(i, j);
+        }
+        let permutation = table.iter().chain(table.iter()).copied().collect();
+        Perlin { permutation }
+    }
+
+    /// Hashes a lattice point into the permutation table
+    fn hash(&self, x: usize, y: usize, z: usize) -> u8 {
+        let p = &self.permutation;
+        p[p[p[x] as usize + y] as usize + z]
+    }
+
+    /// Evaluates the noise at a point, giving values in about -1..1 that
+    /// are 0 on every integer lattice point
+    pub fn noise(&self, point: Vector3) -> f32 {
+        let (fx, fy, fz) = (point.x.floor(), point.y.floor(), point.z.floor());
+        let (x, y, z) = (point.x - fx, point.y - fy, point.z - fz);
+        let (xi, yi, zi) = (
+            (fx as i64 & 255) as usize,
+            (fy as i64 & 255) as usize,
+            (fz as i64 & 255) as usize,
+        );
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        // Blends the gradients of the cell's eight corners
+        let corner = |dx: usize, dy: usize, dz: usize| {
+            let hash = self.hash(xi + dx, yi + dy, zi + dz);
+            gradient(hash, x - dx as f32, y - dy as f32, z - dz as f32)
+        };
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 0), corner(1, 0, 0)),
+                lerp(u, corner(0, 1, 0), corner(1, 1, 0)),
+            ),
+            lerp(
+                v,
+                lerp(u, corner(0, 0, 1), corner(1, 0, 1)),
+                lerp(u, corner(0, 1, 1), corner(1, 1, 1)),
+            ),
+        )
+    }
+
+    /// Evaluates Ken Perlin's simplex noise at a point, giving values in
+    /// about -1..1
+    ///
+    /// Rather than blending the eight corners of a cube, this sums the four
+    /// corners of the tetrahedron around the point, which hides the
+    /// lattice's axis-aligned artifacts.
+    pub fn simplex(&self, point: Vector3) -> f32 {
+        // Skews space so the tetrahedra become cubes, and finds the cell
+        let skew = (point.x + point.y + point.z) * SKEW;
+        let (fx, fy, fz) = (
+            (point.x + skew).floor(),
+            (point.y + skew).floor(),
+            (point.z + skew).floor(),
+        );
+        let unskew = (fx + fy + fz) * UNSKEW;
+        let (x, y, z) = (
+            point.x - fx + unskew,
+            point.y - fy + unskew,
+            point.z - fz + unskew,
+        );
+        let (xi, yi, zi) = (
+            (fx as i64 & 255) as usize,
+            (fy as i64 & 255) as usize,
+            (fz as i64 & 255) as usize,
+        );
+
+        // Walks from the cell's first corner to its last along the largest
+        // offsets first, which picks the tetrahedron the point is in
+        let (second, third) = if x >= y {
+            if y >= z {
+                ((1, 0, 0), (1, 1, 0))
+            } else if x >= z {
+                ((1, 0, 0), (1, 0, 1))
+            } else {
+                ((0, 0, 1), (1, 0, 1))
+            }
+        } else if y < z {
+            ((0, 0, 1), (0, 1, 1))
+        } else if x < z {
+            ((0, 1, 0), (0, 1, 1))
+        } else {
+            ((0, 1, 0), (1, 1, 0))
+        };
+
+        // Sums each corner's gradient, fading to nothing before it reaches
+        // the neighbouring tetrahedra
+        let corner = |(dx, dy, dz): (usize, usize, usize), step: f32| {
+            let offset = UNSKEW * step;
+            let (x, y, z) = (
+                x - dx as f32 + offset,
+                y - dy as f32 + offset,
+                z - dz as f32 + offset,
+            );
+            let falloff = 0.6 - x * x - y * y - z * z;
+            if falloff <= 0.0 {
+                return 0.0;
+            }
+            let hash = self.hash(xi + dx, yi + dy, zi + dz);
+            falloff.powi(4) * gradient(hash, x, y, z)
+        };
+        32.0 * (corner((0, 0, 0), 0.0)
+            + corner(second, 1.0)
+            + corner(third, 2.0)
+            + corner((1, 1, 1), 3.0))
+    }
+
+    /// Sums octaves of noise, each at twice the frequency and half the
+    /// amplitude of the last, giving fractal Brownian motion in about -1..1
+    pub fn fbm(&self, point: Vector3, octaves: u32) -> f32 {
+        self.octaves(point, octaves, |n| n)
+    }
+
+    /// Sums octaves of the absolute value of noise, giving the creased
+    /// pattern of turbulence in about 0..1
+    pub fn turbulence(&self, point: Vector3, octaves: u32) -> f32 {
+        self.octaves(point, octaves, f32::abs)
+    }
+
+    /// Sums octaves of shaped noise, normalized by the total amplitude
+    fn octaves(&self, point: Vector3, octaves: u32, shape: impl Fn(f32) -> f32) -> f32 {
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for _ in 0..octaves.max(1) {
+            sum += shape(self.noise(point * frequency)) * amplitude;
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        sum / total
+    }
+}
+
+impl Default for Perlin {
+    fn default() -> Perlin {
+        Perlin::new(0)
+    }
+}
+
+/// Scales the sum of a point's coordinates to skew simplex noise's
+/// tetrahedra into cubes
+const SKEW: f32 = 1.0 / 3.0;
+
+/// Scales the sum of a cell's coordinates to skew it back
+const UNSKEW: f32 = 1.0 / 6.0;
+
+/// Eases a lattice offset so the noise has continuous second derivatives
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// Linearly interpolates between two values
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// Dots an offset with one of twelve gradients along the cube's edges,
+/// picked by a hash
+fn gradient(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    let u = if h & 1 == 0 { u } else { -u };
+    let v = if h & 2 == 0 { v } else { -v };
+    u + v
+}
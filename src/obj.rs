@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::bump::{Bump, Bumped};
+use crate::material::{Dielectric, Emissive, Lambertian, Material, Phong};
+use crate::mesh::TriangleMesh;
+use crate::principled::{Principled, PrincipledParameters};
+use crate::texture::{ImageTexture, Texture, TextureError, Wrap};
+use crate::vector::Vector3;
+
+/// Describes why an OBJ or MTL file could not be loaded
+#[derive(Debug)]
+pub enum ObjError {
+    /// The file could not be read
+    Io(PathBuf, io::Error),
+    /// The file contained a malformed statement
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// A texture referenced by a material could not be loaded
+    Texture(TextureError),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            ObjError::Texture(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ObjError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ObjError::Io(_, err) => Some(err),
+            ObjError::Parse { .. } => None,
+            ObjError::Texture(err) => Some(err),
+        }
+    }
+}
+
+impl From<TextureError> for ObjError {
+    fn from(err: TextureError) -> ObjError {
+        ObjError::Texture(err)
+    }
+}
+
+/// Surface parameters read from a `newmtl` block of an MTL file
+#[derive(Debug, Clone)]
+pub struct ObjMaterial {
+    /// Diffuse color (`Kd`)
+    pub diffuse: Vector3,
+    /// Diffuse texture (`map_Kd`), resolved relative to the MTL file
+    pub diffuse_map: Option<PathBuf>,
+    /// Height map (`bump` or `map_Bump`), resolved relative to the MTL file
+    pub bump_map: Option<PathBuf>,
+    /// Height map strength (the `-bm` option of `bump`)
+    pub bump_multiplier: f32,
+    /// Tangent-space normal map (`norm`), resolved relative to the MTL file
+    pub normal_map: Option<PathBuf>,
+    /// Specular color (`Ks`)
+    pub specular: Vector3,
+    /// Specular exponent (`Ns`)
+    pub shininess: f32,
+    /// Emitted color (`Ke`)
+    pub emission: Vector3,
+    /// Optical density (`Ni`)
+    pub ior: f32,
+    /// Opacity (`d`, or one minus `Tr`)
+    pub dissolve: f32,
+    /// Illumination model (`illum`)
+    pub illum: u32,
+    /// PBR roughness (`Pr`)
+    pub roughness: Option<f32>,
+    /// PBR metallic (`Pm`)
+    pub metallic: Option<f32>,
+    /// PBR sheen (`Ps`)
+    pub sheen: Option<f32>,
+    /// PBR clearcoat thickness (`Pc`)
+    pub clearcoat: Option<f32>,
+    /// PBR clearcoat roughness (`Pcr`)
+    pub clearcoat_roughness: Option<f32>,
+}
+
+impl Default for ObjMaterial {
+    fn default() -> ObjMaterial {
+        ObjMaterial {
+            diffuse: Vector3::new_scalar(0.8),
+            diffuse_map: None,
+            bump_map: None,
+            bump_multiplier: 1.0,
+            normal_map: None,
+            specular: Vector3::origin(),
+            shininess: 10.0,
+            emission: Vector3::origin(),
+            ior: 1.0,
+            dissolve: 1.0,
+            illum: 2,
+            roughness: None,
+            metallic: None,
+            sheen: None,
+            clearcoat: None,
+            clearcoat_roughness: None,
+        }
+    }
+}
+
+impl ObjMaterial {
+    /// Maps the MTL illumination model onto one of the tracer's materials
+    ///
+    /// Emissive materials become emitters, partly transparent materials
+    /// become smooth principled materials transmitting one minus `d` of the
+    /// light through their diffuse color, and the refraction models (4, 6, 7,
+    /// 9) become clear dielectrics. The reflection models (3, 5, 8) get a
+    /// mirror coating as strong as the average specular color. The remaining
+    /// models are Blinn-Phong when they have a specular color and diffuse
+    /// otherwise.
+    ///
+    /// Materials using any of the PBR extension statements (`Pr`, `Pm`,
+    /// `Ps`, `Pc`, `Pcr`) become principled materials instead. A diffuse
+    /// texture replaces the diffuse color, and height and normal maps bend
+    /// the material's normal. Any texture that can't be loaded fails the
+    /// conversion.
+    pub fn to_material(&self) -> Result<Arc<dyn Material>, TextureError> {
+        let mut material = self.base_material()?;
+        if let Some(path) = &self.bump_map {
+            let bump = Bump::HeightMap(Arc::new(ImageTexture::load(path, Wrap::Repeat)?));
+            material = Arc::new(Bumped::new(material, bump, self.bump_multiplier));
+        }
+        if let Some(path) = &self.normal_map {
+            let bump = Bump::NormalMap(Arc::new(ImageTexture::load(path, Wrap::Repeat)?));
+            material = Arc::new(Bumped::new(material, bump, 1.0));
+        }
+        Ok(material)
+    }
+
+    /// Creates the material before any height or normal maps are applied
+    fn base_material(&self) -> Result<Arc<dyn Material>, TextureError> {
+        let average = |color: Vector3| (color.x + color.y + color.z) / 3.0;
+        let reflectivity = average(self.specular);
+        let diffuse: Arc<dyn Texture> = match &self.diffuse_map {
+            Some(path) => Arc::new(ImageTexture::load(path, Wrap::Repeat)?.decode_srgb()),
+            None => self.diffuse.into(),
+        };
+        Ok(match self.illum {
+            _ if average(self.emission) > 0.0 => Arc::new(Emissive::new(self.emission, 1.0)),
+            _ if self.is_pbr() => Arc::new(Principled::new(self.principled_parameters(diffuse))),
+            _ if self.dissolve < 1.0 => Arc::new(Principled::new(PrincipledParameters {
+                roughness: 0.0,
+                ..self.principled_parameters(diffuse)
+            })),
+            4 | 6 | 7 | 9 => Arc::new(Dielectric::new(
+                self.refractive_index(),
+                Vector3::new_scalar(1.0),
+            )),
+            3 | 5 | 8 if reflectivity > 0.0 => Arc::new(Phong::new(
+                diffuse,
+                self.specular,
+                self.shininess,
+                reflectivity.min(1.0),
+            )),
+            2 if reflectivity > 0.0 => {
+                Arc::new(Phong::new(diffuse, self.specular, self.shininess, 0.0))
+            }
+            _ => Arc::new(Lambertian::new(diffuse)),
+        })
+    }
+
+    /// Checks whether the material uses the PBR extension
+    fn is_pbr(&self) -> bool {
+        self.roughness.is_some()
+            || self.metallic.is_some()
+            || self.sheen.is_some()
+            || self.clearcoat.is_some()
+            || self.clearcoat_roughness.is_some()
+    }
+
+    /// Converts the PBR extension statements into principled parameters
+    ///
+    /// A specular color scales the dielectric reflections, and anything less
+    /// than fully opaque becomes transmission.
+    fn principled_parameters(&self, base_color: Arc<dyn Texture>) -> PrincipledParameters {
+        let defaults = PrincipledParameters::default();
+        let clamp = |value: f32| value.clamp(0.0, 1.0);
+        let specular = (self.specular.x + self.specular.y + self.specular.z) / 3.0;
+        PrincipledParameters {
+            base_color,
+            metallic: self.metallic.map_or(defaults.metallic, clamp),
+            roughness: self.roughness.map_or(defaults.roughness, clamp),
+            specular: if specular > 0.0 {
+                clamp(specular)
+            } else {
+                defaults.specular
+            },
+            sheen: self.sheen.map_or(defaults.sheen, clamp),
+            clearcoat: self.clearcoat.map_or(defaults.clearcoat, clamp),
+            clearcoat_roughness: self
+                .clearcoat_roughness
+                .map_or(defaults.clearcoat_roughness, clamp),
+            transmission: clamp(1.0 - self.dissolve),
+            ior: self.refractive_index(),
+            ..defaults
+        }
+    }
+
+    /// Gets the index of refraction, which is that of glass unless `Ni`
+    /// gives one above 1
+    ///
+    /// Exporters leave out `Ni` for opaque materials, or write 1, which
+    /// would make transparent surfaces bend no light and vanish.
+    fn refractive_index(&self) -> f32 {
+        if self.ior > 1.0 {
+            self.ior
+        } else {
+            PrincipledParameters::default().ior
+        }
+    }
+}
+
+/// Vertex attribute lists of an OBJ file, which faces index into
+#[derive(Default)]
+struct Attributes {
+    positions: Vec<Vector3>,
+    texcoords: Vec<(f32, f32)>,
+    normals: Vec<Vector3>,
+}
+
+/// Indices of a single face corner into the position, texture coordinate,
+/// and normal lists
+type FaceVertex = (usize, Option<usize>, Option<usize>);
+
+/// Collects the triangles of one group and material into a mesh
+struct MeshBuilder {
+    material: String,
+    positions: Vec<Vector3>,
+    normals: Vec<Option<Vector3>>,
+    uvs: Vec<Option<(f32, f32)>>,
+    indices: Vec<[u32; 3]>,
+    remap: HashMap<FaceVertex, u32>,
+}
+
+impl MeshBuilder {
+    fn new(material: &str) -> MeshBuilder {
+        MeshBuilder {
+            material: material.to_string(),
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+            remap: HashMap::new(),
+        }
+    }
+
+    /// Gets the mesh-local index of a face corner, adding a new vertex the
+    /// first time a position, texture coordinate, and normal combination is
+    /// seen
+    fn vertex(&mut self, corner: FaceVertex, attributes: &Attributes) -> u32 {
+        let (position, texcoord, normal) = corner;
+        let next = self.positions.len() as u32;
+        let index = *self.remap.entry(corner).or_insert(next);
+        if index == next {
+            self.positions.push(attributes.positions[position]);
+            self.uvs.push(texcoord.map(|t| attributes.texcoords[t]));
+            self.normals.push(normal.map(|n| attributes.normals[n]));
+        }
+        index
+    }
+
+    /// Builds the mesh, using smooth shading only when every vertex has a
+    /// normal, and texture coordinates only when every vertex has them
+    fn build(self, material: Arc<dyn Material>) -> TriangleMesh {
+        let normals = self.normals.into_iter().collect::<Option<Vec<Vector3>>>();
+        let uvs = self.uvs.into_iter().collect::<Option<Vec<(f32, f32)>>>();
+        TriangleMesh::with_attributes(self.positions, normals, uvs, self.indices, material)
+    }
+}
+
+/// Loads a Wavefront OBJ file and its MTL libraries as a list of meshes,
+/// one for every group and material combination in the file
+///
+/// Polygons are triangulated as fans, so they are expected to be convex.
+pub fn load_obj(path: &Path) -> Result<Vec<TriangleMesh>, ObjError> {
+    load_obj_with_dependencies(path).map(|(meshes, _)| meshes)
+}
+
+/// Loads a Wavefront OBJ file like [`load_obj`], along with the files it
+/// read besides the file itself: its MTL libraries and the textures of the
+/// materials its faces use, in the order they are named
+pub fn load_obj_with_dependencies(
+    path: &Path,
+) -> Result<(Vec<TriangleMesh>, Vec<PathBuf>), ObjError> {
+    let source = fs::read_to_string(path).map_err(|err| ObjError::Io(path.to_path_buf(), err))?;
+    let parse_error = |line: usize, message: String| ObjError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let mut attributes = Attributes::default();
+    let mut libraries: Vec<(PathBuf, HashMap<String, ObjMaterial>)> = Vec::new();
+
+    // Meshes are keyed by group and material name, in order of appearance
+    let mut builders: Vec<MeshBuilder> = Vec::new();
+    let mut lookup: HashMap<(String, String), usize> = HashMap::new();
+    let mut group = String::new();
+    let mut material = String::new();
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => attributes
+                .positions
+                .push(parse_vector(&args).map_err(|e| parse_error(number, e))?),
+            "vn" => attributes
+                .normals
+                .push(parse_vector(&args).map_err(|e| parse_error(number, e))?),
+            "vt" => {
+                let t = parse_floats(&args, 1).map_err(|e| parse_error(number, e))?;
+                attributes
+                    .texcoords
+                    .push((t[0], t.get(1).copied().unwrap_or(0.0)));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_error(
+                        number,
+                        "face needs at least three vertices".to_string(),
+                    ));
+                }
+                let mut corners = Vec::with_capacity(args.len());
+                for arg in &args {
+                    let corner = parse_face_vertex(
+                        arg,
+                        attributes.positions.len(),
+                        attributes.texcoords.len(),
+                        attributes.normals.len(),
+                    )
+                    .map_err(|e| parse_error(number, e))?;
+                    corners.push(corner);
+                }
+
+                // Finds or creates the mesh for the current group and material
+                let key = (group.clone(), material.clone());
+                let index = *lookup.entry(key).or_insert_with(|| {
+                    builders.push(MeshBuilder::new(&material));
+                    builders.len() - 1
+                });
+                let builder = &mut builders[index];
+
+                // Triangulates the polygon as a fan around its first corner
+                let first = builder.vertex(corners[0], &attributes);
+                let mut previous = builder.vertex(corners[1], &attributes);
+                for corner in &corners[2..] {
+                    let current = builder.vertex(*corner, &attributes);
+                    builder.indices.push([first, previous, current]);
+                    previous = current;
+                }
+            }
+            "g" | "o" => group = args.join(" "),
+            "usemtl" => material = args.join(" "),
+            "mtllib" => {
+                let directory = path.parent().unwrap_or_else(|| Path::new(""));
+                for library in &args {
+                    let library = directory.join(library);
+                    let materials = load_mtl(&library)?;
+                    libraries.push((library, materials));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Later libraries override materials of the same name in earlier ones
+    let mut materials: HashMap<&str, (usize, &ObjMaterial)> = HashMap::new();
+    for (index, (_, library)) in libraries.iter().enumerate() {
+        for (name, material) in library {
+            materials.insert(name, (index, material));
+        }
+    }
+    let builders: Vec<MeshBuilder> = builders
+        .into_iter()
+        .filter(|builder| !builder.indices.is_empty())
+        .collect();
+    let used = |name: &str, index: usize| {
+        materials
+            .get(name)
+            .is_some_and(|&(defined, _)| defined == index)
+            && builders.iter().any(|builder| builder.material == name)
+    };
+
+    // Only the materials faces use are converted, so only their textures
+    // are read. Materials are sorted by name, since libraries are read into
+    // a map.
+    let mut files = Vec::new();
+    for (index, (library, library_materials)) in libraries.iter().enumerate() {
+        files.push(library.clone());
+        let mut names: Vec<&String> = library_materials.keys().collect();
+        names.sort();
+        for name in names.into_iter().filter(|name| used(name, index)) {
+            let material = &library_materials[name];
+            files.extend(material.diffuse_map.clone());
+            files.extend(material.bump_map.clone());
+            files.extend(material.normal_map.clone());
+        }
+    }
+
+    // Meshes with the same material share it, and meshes with a missing
+    // material fall back to the MTL defaults
+    let mut shared: HashMap<&str, Arc<dyn Material>> = HashMap::new();
+    for (&name, &(_, material)) in &materials {
+        if builders.iter().any(|builder| builder.material == name) {
+            shared.insert(name, material.to_material()?);
+        }
+    }
+    let fallback = ObjMaterial::default().to_material()?;
+
+    let meshes = builders
+        .into_iter()
+        .map(|builder| {
+            let material = shared.get(builder.material.as_str()).unwrap_or(&fallback);
+            builder.build(Arc::clone(material))
+        })
+        .collect();
+    Ok((meshes, files))
+}
+
+/// Loads the materials of a Wavefront MTL file by name
+pub fn load_mtl(path: &Path) -> Result<HashMap<String, ObjMaterial>, ObjError> {
+    let source = fs::read_to_string(path).map_err(|err| ObjError::Io(path.to_path_buf(), err))?;
+    let parse_error = |line: usize, message: String| ObjError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, ObjMaterial)> = None;
+
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) if !keyword.starts_with('#') => keyword,
+            _ => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((args.join(" "), ObjMaterial::default()));
+            continue;
+        }
+
+        let material = match &mut current {
+            Some((_, material)) => material,
+            None => continue,
+        };
+        let result = match keyword {
+            "Kd" => parse_vector(&args).map(|v| material.diffuse = v),
+            "map_Kd" => {
+                texture_file(directory, &args).map(|file| material.diffuse_map = Some(file))
+            }
+            "bump" | "map_Bump" | "map_bump" => texture_file(directory, &args).and_then(|file| {
+                material.bump_map = Some(file);
+                match args.iter().position(|&arg| arg == "-bm") {
+                    Some(i) => parse_floats(args.get(i + 1..i + 2).unwrap_or(&[]), 1)
+                        .map(|v| material.bump_multiplier = v[0]),
+                    None => Ok(()),
+                }
+            }),
+            "norm" => texture_file(directory, &args).map(|file| material.normal_map = Some(file)),
+            "Ks" => parse_vector(&args).map(|v| material.specular = v),
+            "Ke" => parse_vector(&args).map(|v| material.emission = v),
+            "Ns" => parse_floats(&args, 1).map(|v| material.shininess = v[0]),
+            "Ni" => parse_floats(&args, 1).map(|v| material.ior = v[0]),
+            "d" => parse_floats(&args, 1).map(|v| material.dissolve = v[0]),
+            "Tr" => parse_floats(&args, 1).map(|v| material.dissolve = 1.0 - v[0]),
+            "Pr" => parse_floats(&args, 1).map(|v| material.roughness = Some(v[0])),
+            "Pm" => parse_floats(&args, 1).map(|v| material.metallic = Some(v[0])),
+            "Ps" => parse_floats(&args, 1).map(|v| material.sheen = Some(v[0])),
+            "Pc" => parse_floats(&args, 1).map(|v| material.clearcoat = Some(v[0])),
+            "Pcr" => parse_floats(&args, 1).map(|v| material.clearcoat_roughness = Some(v[0])),
+            "illum" => match args.first().map(|arg| arg.parse::<u32>()) {
+                Some(Ok(illum)) => {
+                    material.illum = illum;
+                    Ok(())
+                }
+                _ => Err("expected an illumination model number".to_string()),
+            },
+            _ => Ok(()),
+        };
+        result.map_err(|e| parse_error(number, e))?;
+    }
+
+    if let Some((name, material)) = current {
+        materials.insert(name, material);
+    }
+    Ok(materials)
+}
+
+/// Gets the file of a texture statement, resolved relative to the MTL file
+///
+/// Options such as `-s` come first, so the file is always last.
+fn texture_file(directory: &Path, args: &[&str]) -> Result<PathBuf, String> {
+    match args.last() {
+        Some(file) => Ok(directory.join(file)),
+        None => Err("expected a texture file".to_string()),
+    }
+}
+
+/// Parses at least `count` floats from a statement's arguments
+fn parse_floats(args: &[&str], count: usize) -> Result<Vec<f32>, String> {
+    if args.len() < count {
+        return Err(format!("expected {} numbers, found {}", count, args.len()));
+    }
+    args.iter()
+        .map(|arg| {
+            arg.parse::<f32>()
+                .map_err(|_| format!("invalid number `{}`", arg))
+        })
+        .collect()
+}
+
+/// Parses a vector from the first three arguments of a statement
+fn parse_vector(args: &[&str]) -> Result<Vector3, String> {
+    let v = parse_floats(args, 3)?;
+    Ok(Vector3::new(v[0], v[1], v[2]))
+}
+
+/// Parses a face corner of the form `v`, `v/vt`, `v//vn`, or `v/vt/vn`
+fn parse_face_vertex(
+    arg: &str,
+    positions: usize,
+    texcoords: usize,
+    normals: usize,
+) -> Result<FaceVertex, String> {
+    let mut parts = arg.split('/');
+    let position = resolve_index(parts.next().unwrap_or(""), positions)?;
+    let texcoord = match parts.next() {
+        Some(part) if !part.is_empty() => Some(resolve_index(part, texcoords)?),
+        _ => None,
+    };
+    let normal = match parts.next() {
+        Some(part) if !part.is_empty() => Some(resolve_index(part, normals)?),
+        _ => None,
+    };
+    Ok((position, texcoord, normal))
+}
+
+/// Converts a 1-based or negative (relative to the end) OBJ index into a
+/// 0-based index into a list of the given length
+fn resolve_index(part: &str, len: usize) -> Result<usize, String> {
+    let index: i64 = part
+        .parse()
+        .map_err(|_| format!("invalid index `{}`", part))?;
+    let resolved = if index < 0 {
+        len as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(format!("index {} is out of range", index));
+    }
+    Ok(resolved as usize)
+}
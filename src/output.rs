@@ -0,0 +1,252 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::film::Film;
+
+/// The file formats a render can be written in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// 8-bit PNG, clipping colors brighter than 1
+    #[default]
+    Png,
+    /// OpenEXR with a channel of 16-bit or 32-bit floats per color
+    Exr(ExrPrecision),
+    /// Radiance RGBE, sharing an 8-bit exponent between the three colors
+    Hdr,
+    /// Portable float map, with 32-bit floats per color
+    Pfm,
+}
+
+/// The size of the floats in an OpenEXR file
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ExrPrecision {
+    /// 16-bit floats, which keep about three significant digits
+    #[default]
+    Half,
+    /// 32-bit floats
+    Float,
+}
+
+impl OutputFormat {
+    /// Gets a format by its name on the command line
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "png" => Some(OutputFormat::Png),
+            "exr" => Some(OutputFormat::Exr(ExrPrecision::Half)),
+            "exr-float" => Some(OutputFormat::Exr(ExrPrecision::Float)),
+            "hdr" => Some(OutputFormat::Hdr),
+            "pfm" => Some(OutputFormat::Pfm),
+            _ => None,
+        }
+    }
+
+    /// Gets the format matching a file's extension, ignoring case
+    ///
+    /// OpenEXR files are written with half floats.
+    pub fn from_path(path: &Path) -> Option<OutputFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(OutputFormat::Png),
+            "exr" => Some(OutputFormat::Exr(ExrPrecision::Half)),
+            "hdr" => Some(OutputFormat::Hdr),
+            "pfm" => Some(OutputFormat::Pfm),
+            _ => None,
+        }
+    }
+}
+
+/// An image channel for an OpenEXR file: a name and one value per pixel,
+/// in rows from top to bottom
+pub struct ExrChannel<'a> {
+    pub name: &'a str,
+    pub values: &'a [f32],
+}
+
+/// Writes channels as an uncompressed scanline OpenEXR image
+///
+/// Every channel must hold `width * height` values. Channels are stored in
+/// alphabetical order, as the format requires.
+pub fn write_exr<W: Write>(
+    mut w: W,
+    width: u32,
+    height: u32,
+    channels: &[ExrChannel],
+    precision: ExrPrecision,
+) -> io::Result<()> {
+    let pixels = (width * height) as usize;
+    assert!(
+        channels
+            .iter()
+            .all(|channel| channel.values.len() == pixels),
+        "every channel needs one value per pixel"
+    );
+    let mut channels: Vec<&ExrChannel> = channels.iter().collect();
+    channels.sort_by(|a, b| a.name.cmp(b.name));
+    let (pixel_type, value_size) = match precision {
+        ExrPrecision::Half => (1i32, 2usize),
+        ExrPrecision::Float => (2i32, 4usize),
+    };
+
+    // Header attributes, each written as a name, type, size, and value
+    let mut header = Vec::new();
+    let mut attribute = |name: &str, kind: &str, value: &[u8]| {
+        header.extend_from_slice(name.as_bytes());
+        header.push(0);
+        header.extend_from_slice(kind.as_bytes());
+        header.push(0);
+        header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+        header.extend_from_slice(value);
+    };
+    let mut channel_list = Vec::new();
+    for channel in &channels {
+        channel_list.extend_from_slice(channel.name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&pixel_type.to_le_bytes());
+        // Perceptually linear flag and three reserved bytes
+        channel_list.extend_from_slice(&[0, 0, 0, 0]);
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channel_list.push(0);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect();
+    attribute("channels", "chlist", &channel_list);
+    attribute("compression", "compression", &[0]);
+    attribute("dataWindow", "box2i", &window);
+    attribute("displayWindow", "box2i", &window);
+    attribute("lineOrder", "lineOrder", &[0]);
+    attribute("pixelAspectRatio", "float", &1f32.to_le_bytes());
+    attribute("screenWindowCenter", "v2f", &[0; 8]);
+    attribute("screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+
+    // Magic number and version 2, for a single part scanline file
+    w.write_all(&[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0])?;
+    w.write_all(&header)?;
+
+    // Each uncompressed block holds one scanline, found through a table of
+    // offsets from the start of the file
+    let line_size = channels.len() * width as usize * value_size;
+    let first_line = 8 + header.len() + 8 * height as usize;
+    for y in 0..height as usize {
+        let offset = (first_line + y * (8 + line_size)) as u64;
+        w.write_all(&offset.to_le_bytes())?;
+    }
+    let mut line = Vec::with_capacity(line_size);
+    for y in 0..height as usize {
+        line.clear();
+        for channel in &channels {
+            let row = &channel.values[y * width as usize..(y + 1) * width as usize];
+            for &value in row {
+                match precision {
+                    ExrPrecision::Half => line.extend_from_slice(&to_half(value).to_le_bytes()),
+                    ExrPrecision::Float => line.extend_from_slice(&value.to_le_bytes()),
+                }
+            }
+        }
+        w.write_all(&(y as i32).to_le_bytes())?;
+        w.write_all(&(line_size as i32).to_le_bytes())?;
+        w.write_all(&line)?;
+    }
+    w.flush()
+}
+
+/// Writes the film as an uncompressed Radiance RGBE image
+///
+/// Negative colors are written as black.
+pub fn write_hdr<W: Write>(mut w: W, film: &Film) -> io::Result<()> {
+    write!(
+        w,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        film.height(),
+        film.width()
+    )?;
+    let mut row = Vec::with_capacity(film.width() as usize * 4);
+    for y in 0..film.height() {
+        row.clear();
+        for x in 0..film.width() {
+            let color = film.color(x, y);
+            row.extend_from_slice(&to_rgbe(color.x, color.y, color.z));
+        }
+        w.write_all(&row)?;
+    }
+    w.flush()
+}
+
+/// Writes the film as a color portable float map
+///
+/// Rows are stored from the bottom up in little-endian order, which the
+/// negative scale in the header announces.
+pub fn write_pfm<W: Write>(mut w: W, film: &Film) -> io::Result<()> {
+    write!(w, "PF\n{} {}\n-1.0\n", film.width(), film.height())?;
+    let mut row = Vec::with_capacity(film.width() as usize * 12);
+    for y in (0..film.height()).rev() {
+        row.clear();
+        for x in 0..film.width() {
+            let color = film.color(x, y);
+            for value in &[color.x, color.y, color.z] {
+                row.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        w.write_all(&row)?;
+    }
+    w.flush()
+}
+
+/// Rounds a float to the nearest 16-bit half float, saturating to infinity
+/// and flushing values too small for a half to zero
+fn to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    if exponent == 0xff {
+        // Infinity stays infinite, and NaN stays NaN
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // Subnormal halves, rounding to nearest even
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - exponent) as u32;
+        let half = mantissa >> shift;
+        let remainder = mantissa & ((1 << shift) - 1);
+        let midpoint = 1 << (shift - 1);
+        let round = remainder > midpoint || (remainder == midpoint && half & 1 == 1);
+        return sign | (half + round as u32) as u16;
+    }
+
+    // Normal halves, rounding to nearest even, where a carry out of the
+    // mantissa correctly bumps the exponent
+    let half = ((exponent as u32) << 10) | (mantissa >> 13);
+    let remainder = mantissa & 0x1fff;
+    let round = remainder > 0x1000 || (remainder == 0x1000 && half & 1 == 1);
+    sign | (half + round as u32) as u16
+}
+
+/// Packs a color into Radiance's shared exponent format
+fn to_rgbe(r: f32, g: f32, b: f32) -> [u8; 4] {
+    let (r, g, b) = (r.max(0.0), g.max(0.0), b.max(0.0));
+    let brightest = r.max(g).max(b);
+    if brightest < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+
+    // Splits the brightest channel into a mantissa in 0.5..1 and a power of
+    // two, so it maps onto 128..256
+    let exponent = brightest.log2().floor() as i32 + 1;
+    let exponent = exponent.clamp(-128, 127);
+    let scale = 256.0 / 2f32.powi(exponent);
+    let channel = |value: f32| (value * scale).min(255.0) as u8;
+    [channel(r), channel(g), channel(b), (exponent + 128) as u8]
+}
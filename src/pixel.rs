@@ -13,6 +13,11 @@ impl Pixel {
     pub fn new(r: u8, g: u8, b: u8, a: u8) -> Pixel {
         Pixel { r, g, b, a }
     }
+
+    /// Gets the red, green, blue, and alpha channels of the pixel
+    pub fn rgba(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
 }
 
 /// Defines a trait for converting some object into a Pixel Data array (Vec<u8>)
@@ -20,7 +25,7 @@ pub trait IntoPixelData {
     fn into_pixel_data(self) -> Vec<u8>;
 }
 
-impl IntoPixelData for Vec<Pixel> {
+impl IntoPixelData for &[Pixel] {
     /// Converts a Pixel array into an array of writable pixel data
     fn into_pixel_data(self) -> Vec<u8> {
         let mut out = Vec::new();
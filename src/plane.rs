@@ -1,6 +1,11 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
 use crate::intersectable::Intersectable;
+use crate::material::Material;
 use crate::ray::Ray;
-use crate::rayhit::{RayHit, ReflectionRefractionIndex};
+use crate::rayhit::RayHit;
+use crate::sampling::orthonormal_basis;
 use crate::vector::Vector3;
 
 /// A sphere with a position, color, and radius
@@ -8,23 +13,16 @@ use crate::vector::Vector3;
 pub struct Plane {
     origin: Vector3,
     normal: Vector3,
-    color: Vector3,
-    reflection_and_refraction: ReflectionRefractionIndex,
+    material: Arc<dyn Material>,
 }
 
 impl Plane {
     /// Creates a new sphere with given geometric data
-    pub fn new(
-        origin: Vector3,
-        normal: Vector3,
-        color: Vector3,
-        reflection_and_refraction: ReflectionRefractionIndex,
-    ) -> Plane {
+    pub fn new(origin: Vector3, normal: Vector3, material: Arc<dyn Material>) -> Plane {
         Plane {
             origin,
             normal,
-            color,
-            reflection_and_refraction,
+            material,
         }
     }
 }
@@ -32,8 +30,7 @@ impl Plane {
 impl Intersectable for Plane {
     /// Determines whether the given ray has intersected with the sphere
     /// and generates a RayHit
-    fn intersect(&self, ray: Ray) -> Option<RayHit> {
-        
+    fn intersect(&self, ray: Ray) -> Option<RayHit<'_>> {
         let denom = (-self.normal).dot(ray.direction());
         if denom > 0.001 {
             let ray_to_origin = self.origin - ray.origin();
@@ -43,13 +40,13 @@ impl Intersectable for Plane {
                 let position = ray.origin() + ray.direction() * t;
                 let normal = self.normal.normalize();
 
-                Some(RayHit::new(
-                    position,
-                    normal,
-                    t,
-                    self.color,
-                    self.reflection_and_refraction,
-                ))
+                // Tiles texture coordinates across the plane once per unit
+                let (tangent, bitangent) = orthonormal_basis(normal);
+                let offset = position - self.origin;
+                let uv = (offset.dot(tangent), offset.dot(bitangent));
+
+                let hit = RayHit::new(position, normal, uv, t, &*self.material);
+                Some(hit.with_tangents(tangent, bitangent))
             } else {
                 None
             }
@@ -57,4 +54,9 @@ impl Intersectable for Plane {
             None
         }
     }
+
+    /// Planes are infinite, so they are never bounded
+    fn bounds(&self) -> Aabb {
+        Aabb::infinite()
+    }
 }
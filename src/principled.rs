@@ -0,0 +1,335 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::material::{
+    average, facing_normal, reflect, same_side, smooth_dielectric_lobes, BsdfSample, Material,
+    RoughDielectric, SpecularLobe,
+};
+use crate::microfacet::{Frame, Ggx};
+use crate::rayhit::RayHit;
+use crate::sampling::cosine_hemisphere;
+use crate::texture::Texture;
+use crate::vector::Vector3;
+
+/// Reflectance of the clearcoat layer at normal incidence, matching a
+/// varnish with an index of refraction of 1.5
+const CLEARCOAT_REFLECTANCE: f32 = 0.04;
+
+/// Artist-friendly parameters of a principled material
+///
+/// Every parameter except the base color and index of refraction is in 0..1.
+#[derive(Debug, Clone)]
+pub struct PrincipledParameters {
+    /// Color of the diffuse lobe, metallic reflections, and refracted light
+    pub base_color: Arc<dyn Texture>,
+    /// Blend between a dielectric such as plastic and a metal
+    pub metallic: f32,
+    /// Roughness of reflections and refractions
+    pub roughness: f32,
+    /// Strength of dielectric reflections, where 0.5 reflects 4% of the
+    /// light at normal incidence
+    pub specular: f32,
+    /// How much dielectric reflections are tinted towards the base color
+    pub specular_tint: f32,
+    /// Strength of the soft sheen at grazing angles, mostly used for cloth
+    pub sheen: f32,
+    /// How much the sheen is tinted towards the base color
+    pub sheen_tint: f32,
+    /// Strength of a clear varnish layer on top of the surface
+    pub clearcoat: f32,
+    /// Roughness of the clear varnish layer
+    pub clearcoat_roughness: f32,
+    /// Blend between an opaque surface and a transparent one like glass
+    pub transmission: f32,
+    /// Index of refraction used by transmission
+    pub ior: f32,
+}
+
+impl Default for PrincipledParameters {
+    fn default() -> PrincipledParameters {
+        PrincipledParameters {
+            base_color: Vector3::new_scalar(0.8).into(),
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            transmission: 0.0,
+            ior: 1.5,
+        }
+    }
+}
+
+/// A single material covering plastics, metals, cloth, varnish, and glass
+/// through a handful of intuitive parameters, following the Disney
+/// principled BSDF (Burley, "Physically Based Shading at Disney" and
+/// "Extending the Disney BRDF to a BSDF with Integrated Subsurface
+/// Scattering")
+///
+/// The material mixes a retroreflective diffuse lobe with sheen, a GGX
+/// specular lobe, a GGX clearcoat lobe, and a rough dielectric for
+/// transmission.
+#[derive(Debug, Clone)]
+pub struct Principled {
+    parameters: PrincipledParameters,
+    specular: Ggx,
+    clearcoat: Ggx,
+    transmission: RoughDielectric,
+    diffuse_weight: f32,
+    specular_weight: f32,
+    clearcoat_weight: f32,
+    transmission_weight: f32,
+}
+
+impl Principled {
+    /// Creates a new principled material
+    pub fn new(parameters: PrincipledParameters) -> Principled {
+        let p = &parameters;
+        Principled {
+            specular: Ggx::new(p.roughness),
+            clearcoat: Ggx::new(p.clearcoat_roughness),
+            transmission: RoughDielectric::new(p.ior, Arc::clone(&p.base_color), p.roughness),
+            diffuse_weight: (1.0 - p.metallic) * (1.0 - p.transmission),
+            specular_weight: 1.0 - (1.0 - p.metallic) * p.transmission,
+            clearcoat_weight: 0.25 * p.clearcoat,
+            transmission_weight: (1.0 - p.metallic) * p.transmission,
+            parameters,
+        }
+    }
+
+    /// Gets the colors of the lobes at a hit from the base color there
+    fn colors(&self, hit: &RayHit) -> Colors {
+        let p = &self.parameters;
+        let base = p.base_color.evaluate(hit);
+
+        // Tints are the base color's hue and saturation at full brightness
+        let luminance = 0.3 * base.x + 0.6 * base.y + 0.1 * base.z;
+        let tint = if luminance > 0.0 {
+            base * (1.0 / luminance)
+        } else {
+            Vector3::new_scalar(1.0)
+        };
+        let dielectric_specular =
+            lerp(Vector3::new_scalar(1.0), tint, p.specular_tint) * (0.08 * p.specular);
+
+        Colors {
+            base,
+            specular: lerp(dielectric_specular, base, p.metallic),
+            sheen: lerp(Vector3::new_scalar(1.0), tint, p.sheen_tint) * p.sheen,
+        }
+    }
+
+    /// Gets the chance of sampling the diffuse, specular, clearcoat, and
+    /// transmission lobes, weighted by how much light each reflects along
+    /// `outgoing`
+    fn lobe_probabilities(&self, colors: &Colors, cos_o: f32) -> [f32; 4] {
+        let weights = [
+            self.diffuse_weight * average(colors.base),
+            self.specular_weight * average(schlick(colors.specular, cos_o)),
+            self.clearcoat_weight * schlick_weight(cos_o).max(CLEARCOAT_REFLECTANCE),
+            self.transmission_weight,
+        ];
+        let total: f32 = weights.iter().sum();
+        if total <= 0.0 {
+            return [1.0, 0.0, 0.0, 0.0];
+        }
+        [
+            weights[0] / total,
+            weights[1] / total,
+            weights[2] / total,
+            weights[3] / total,
+        ]
+    }
+
+    /// Evaluates the reflective lobes for local directions above the surface
+    fn evaluate_reflection(
+        &self,
+        colors: &Colors,
+        outgoing: Vector3,
+        incoming: Vector3,
+    ) -> Vector3 {
+        let (cos_o, cos_i) = (outgoing.z, incoming.z);
+        if cos_o <= 0.0 || cos_i <= 0.0 {
+            return Vector3::origin();
+        }
+        let m = (outgoing + incoming).normalize();
+        let cos_d = incoming.dot(m);
+
+        // Diffuse with retroreflection at grazing angles on rough surfaces,
+        // plus sheen
+        let fd90 = 0.5 + 2.0 * self.parameters.roughness * cos_d * cos_d;
+        let retro = (1.0 + (fd90 - 1.0) * schlick_weight(cos_i))
+            * (1.0 + (fd90 - 1.0) * schlick_weight(cos_o));
+        let diffuse = colors.base * (retro / PI) + colors.sheen * schlick_weight(cos_d);
+
+        // Microfacet specular and clearcoat layers
+        let specular = schlick(colors.specular, cos_d)
+            * (self.specular.d(m) * self.specular.g(outgoing, incoming) / (4.0 * cos_o * cos_i));
+        let clearcoat = (CLEARCOAT_REFLECTANCE
+            + (1.0 - CLEARCOAT_REFLECTANCE) * schlick_weight(cos_d))
+            * self.clearcoat.d(m)
+            * self.clearcoat.g(outgoing, incoming)
+            / (4.0 * cos_o * cos_i);
+
+        diffuse * self.diffuse_weight
+            + specular * self.specular_weight
+            + Vector3::new_scalar(clearcoat * self.clearcoat_weight)
+    }
+
+    /// Gets the density of sampling the reflective lobes, weighted by their
+    /// probabilities, for local directions above the surface
+    fn pdf_reflection(
+        &self,
+        outgoing: Vector3,
+        incoming: Vector3,
+        probabilities: &[f32; 4],
+    ) -> f32 {
+        if outgoing.z <= 0.0 || incoming.z <= 0.0 {
+            return 0.0;
+        }
+        let m = (outgoing + incoming).normalize();
+        let jacobian = 1.0 / (4.0 * outgoing.dot(m).abs());
+        probabilities[0] * incoming.z / PI
+            + probabilities[1] * self.specular.visible_pdf(outgoing, m) * jacobian
+            + probabilities[2] * self.clearcoat.visible_pdf(outgoing, m) * jacobian
+    }
+}
+
+impl Material for Principled {
+    fn evaluate(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> Vector3 {
+        let mut bsdf = Vector3::origin();
+        if same_side(hit, outgoing, incoming) {
+            let frame = Frame::new(facing_normal(hit, outgoing));
+            let colors = self.colors(hit);
+            bsdf = self.evaluate_reflection(
+                &colors,
+                frame.to_local(outgoing),
+                frame.to_local(incoming),
+            );
+        }
+        if self.transmission_weight > 0.0 {
+            bsdf = bsdf
+                + self.transmission.evaluate(hit, outgoing, incoming) * self.transmission_weight;
+        }
+        bsdf
+    }
+
+    /// Picks a lobe in proportion to how much light it reflects, samples a
+    /// direction from it, and weights the direction by the combined density
+    /// of every lobe
+    fn sample(
+        &self,
+        hit: &RayHit,
+        outgoing: Vector3,
+        lobe: f32,
+        u: f32,
+        v: f32,
+    ) -> Option<BsdfSample> {
+        let normal = facing_normal(hit, outgoing);
+        let frame = Frame::new(normal);
+        let local_outgoing = frame.to_local(outgoing);
+        let probabilities = self.lobe_probabilities(&self.colors(hit), local_outgoing.z);
+
+        let specular_end = probabilities[0] + probabilities[1];
+        let clearcoat_end = specular_end + probabilities[2];
+        let direction = if lobe < probabilities[0] {
+            cosine_hemisphere(normal, u, v)
+        } else if lobe < specular_end {
+            let m = self.specular.sample_visible(local_outgoing, u, v);
+            frame.to_world(reflect(-local_outgoing, m))
+        } else if lobe < clearcoat_end {
+            let m = self.clearcoat.sample_visible(local_outgoing, u, v);
+            frame.to_world(reflect(-local_outgoing, m))
+        } else {
+            let lobe = (lobe - clearcoat_end) / probabilities[3];
+            self.transmission
+                .sample(hit, outgoing, lobe, u, v)?
+                .direction
+        };
+
+        let pdf = self.pdf(hit, outgoing, direction);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let bsdf = self.evaluate(hit, outgoing, direction);
+        Some(BsdfSample {
+            direction,
+            weight: bsdf * (direction.dot(normal).abs() / pdf),
+            pdf,
+            specular: false,
+        })
+    }
+
+    fn pdf(&self, hit: &RayHit, outgoing: Vector3, incoming: Vector3) -> f32 {
+        let frame = Frame::new(facing_normal(hit, outgoing));
+        let local_outgoing = frame.to_local(outgoing);
+        let probabilities = self.lobe_probabilities(&self.colors(hit), local_outgoing.z);
+
+        let mut pdf = 0.0;
+        if same_side(hit, outgoing, incoming) {
+            pdf += self.pdf_reflection(local_outgoing, frame.to_local(incoming), &probabilities);
+        }
+        if probabilities[3] > 0.0 {
+            pdf += probabilities[3] * self.transmission.pdf(hit, outgoing, incoming);
+        }
+        pdf
+    }
+
+    /// Whitted tracing can only follow sharp directions, so the specular,
+    /// clearcoat, and transmission lobes are traced as if they were smooth
+    fn specular_lobes(&self, hit: &RayHit, outgoing: Vector3) -> Vec<SpecularLobe> {
+        let normal = facing_normal(hit, outgoing);
+        let cos_o = outgoing.dot(normal);
+        let colors = self.colors(hit);
+        let clearcoat =
+            CLEARCOAT_REFLECTANCE + (1.0 - CLEARCOAT_REFLECTANCE) * schlick_weight(cos_o);
+        let reflectance = schlick(colors.specular, cos_o) * self.specular_weight
+            + Vector3::new_scalar(clearcoat * self.clearcoat_weight);
+
+        let mut lobes = vec![SpecularLobe {
+            direction: reflect(-outgoing, normal),
+            weight: reflectance,
+        }];
+        if self.transmission_weight > 0.0 {
+            let ior = self.parameters.ior;
+            for lobe in smooth_dielectric_lobes(hit, outgoing, ior, colors.base) {
+                lobes.push(SpecularLobe {
+                    direction: lobe.direction,
+                    weight: lobe.weight * self.transmission_weight,
+                });
+            }
+        }
+        lobes
+    }
+}
+
+/// Colors of a principled material's lobes at a single hit
+struct Colors {
+    base: Vector3,
+    specular: Vector3,
+    sheen: Vector3,
+}
+
+/// Linearly interpolates between two colors
+fn lerp(a: Vector3, b: Vector3, t: f32) -> Vector3 {
+    a * (1.0 - t) + b * t
+}
+
+/// Gets Schlick's weight for how much more light is reflected at the given
+/// angle than at normal incidence
+fn schlick_weight(cos_theta: f32) -> f32 {
+    (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+
+/// Calculates Schlick's approximation of Fresnel reflectance from the
+/// reflectance at normal incidence
+fn schlick(normal_reflectance: Vector3, cos_theta: f32) -> Vector3 {
+    lerp(
+        normal_reflectance,
+        Vector3::new_scalar(1.0),
+        schlick_weight(cos_theta),
+    )
+}
@@ -0,0 +1,269 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::noise::Perlin;
+use crate::rayhit::RayHit;
+use crate::texture::Texture;
+use crate::vector::Vector3;
+
+/// Describes where on a surface a procedural texture is evaluated
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Mapping {
+    /// Evaluates the pattern through space at the hit's position, as if the
+    /// object was carved out of a solid block
+    #[default]
+    Position,
+    /// Evaluates the pattern on the plane at the hit's texture coordinates
+    Uv,
+}
+
+impl Mapping {
+    /// Gets a mapping by its name in a scene file
+    pub fn from_name(name: &str) -> Option<Mapping> {
+        match name {
+            "position" => Some(Mapping::Position),
+            "uv" => Some(Mapping::Uv),
+            _ => None,
+        }
+    }
+
+    /// Gets the point a pattern is evaluated at for a hit
+    pub fn point(self, hit: &RayHit) -> Vector3 {
+        match self {
+            Mapping::Position => hit.position(),
+            Mapping::Uv => {
+                let (u, v) = hit.uv();
+                Vector3::new(u, v, 0.0)
+            }
+        }
+    }
+}
+
+/// Maps values in 0..1 onto colors by blending between colored stops
+#[derive(Debug, Clone)]
+pub struct ColorRamp {
+    stops: Vec<(f32, Vector3)>,
+}
+
+impl ColorRamp {
+    /// Creates a ramp through the given positions and colors
+    ///
+    /// Values before the first stop or after the last take the color of the
+    /// nearest stop.
+    pub fn new(mut stops: Vec<(f32, Vector3)>) -> ColorRamp {
+        assert!(!stops.is_empty(), "color ramp needs at least one stop");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        ColorRamp { stops }
+    }
+
+    /// Creates a ramp that blends evenly from one color to another
+    pub fn between(from: Vector3, to: Vector3) -> ColorRamp {
+        ColorRamp::new(vec![(0.0, from), (1.0, to)])
+    }
+
+    /// Gets the color of the ramp at a value
+    pub fn color(&self, t: f32) -> Vector3 {
+        let first = self.stops[0];
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let ((t0, c0), (t1, c1)) = (pair[0], pair[1]);
+            if t <= t1 {
+                let blend = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1.0 };
+                return c0 * (1.0 - blend) + c1 * blend;
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+}
+
+impl Default for ColorRamp {
+    fn default() -> ColorRamp {
+        ColorRamp::between(Vector3::origin(), Vector3::new_scalar(1.0))
+    }
+}
+
+/// A checkerboard alternating between two textures in unit cells
+///
+/// Mapped by position it fills space with cubes, and mapped by texture
+/// coordinates it covers surfaces with squares.
+#[derive(Debug, Clone)]
+pub struct Checker {
+    mapping: Mapping,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl Checker {
+    /// Creates a new checkerboard, with `even` at the origin's cell
+    pub fn new(
+        mapping: Mapping,
+        even: impl Into<Arc<dyn Texture>>,
+        odd: impl Into<Arc<dyn Texture>>,
+    ) -> Checker {
+        Checker {
+            mapping,
+            even: even.into(),
+            odd: odd.into(),
+        }
+    }
+}
+
+impl Texture for Checker {
+    fn evaluate(&self, hit: &RayHit) -> Vector3 {
+        let p = self.mapping.point(hit);
+        let cell = p.x.floor() as i64 + p.y.floor() as i64 + p.z.floor() as i64;
+        if cell.rem_euclid(2) == 0 {
+            self.even.evaluate(hit)
+        } else {
+            self.odd.evaluate(hit)
+        }
+    }
+}
+
+/// The kinds of noise a noise texture can show
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NoiseKind {
+    /// A single octave of smooth Perlin noise
+    Perlin,
+    /// Fractal Brownian motion, which adds finer detail to Perlin noise
+    #[default]
+    Fbm,
+    /// Octaves of the absolute value of noise, giving billowing creases
+    Turbulence,
+    /// A single octave of simplex noise, which is smooth like Perlin noise
+    /// without its grid-aligned streaks
+    Simplex,
+}
+
+impl NoiseKind {
+    /// Gets a kind of noise by its name in a scene file
+    pub fn from_name(name: &str) -> Option<NoiseKind> {
+        match name {
+            "perlin" => Some(NoiseKind::Perlin),
+            "fbm" => Some(NoiseKind::Fbm),
+            "turbulence" => Some(NoiseKind::Turbulence),
+            "simplex" => Some(NoiseKind::Simplex),
+            _ => None,
+        }
+    }
+}
+
+/// Noise colored through a ramp
+#[derive(Debug, Clone)]
+pub struct Noise {
+    mapping: Mapping,
+    kind: NoiseKind,
+    octaves: u32,
+    ramp: ColorRamp,
+    perlin: Perlin,
+}
+
+impl Noise {
+    /// Creates a new noise texture summing the given number of octaves
+    pub fn new(mapping: Mapping, kind: NoiseKind, octaves: u32, ramp: ColorRamp) -> Noise {
+        Noise {
+            mapping,
+            kind,
+            octaves,
+            ramp,
+            perlin: Perlin::default(),
+        }
+    }
+}
+
+impl Texture for Noise {
+    fn evaluate(&self, hit: &RayHit) -> Vector3 {
+        let p = self.mapping.point(hit);
+        let t = match self.kind {
+            NoiseKind::Perlin => 0.5 + 0.5 * self.perlin.noise(p),
+            NoiseKind::Fbm => 0.5 + 0.5 * self.perlin.fbm(p, self.octaves),
+            NoiseKind::Turbulence => self.perlin.turbulence(p, self.octaves),
+            NoiseKind::Simplex => 0.5 + 0.5 * self.perlin.simplex(p),
+        };
+        self.ramp.color(t)
+    }
+}
+
+/// Marble veins, made by bending stripes along x with turbulence
+#[derive(Debug, Clone)]
+pub struct Marble {
+    mapping: Mapping,
+    frequency: f32,
+    turbulence: f32,
+    octaves: u32,
+    ramp: ColorRamp,
+    perlin: Perlin,
+}
+
+impl Marble {
+    /// Creates a new marble texture with `frequency` veins per unit, bent
+    /// by `turbulence`
+    pub fn new(
+        mapping: Mapping,
+        frequency: f32,
+        turbulence: f32,
+        octaves: u32,
+        ramp: ColorRamp,
+    ) -> Marble {
+        Marble {
+            mapping,
+            frequency,
+            turbulence,
+            octaves,
+            ramp,
+            perlin: Perlin::default(),
+        }
+    }
+}
+
+impl Texture for Marble {
+    fn evaluate(&self, hit: &RayHit) -> Vector3 {
+        let p = self.mapping.point(hit);
+        let phase = 2.0 * PI * self.frequency * p.x
+            + self.turbulence * self.perlin.turbulence(p, self.octaves);
+        self.ramp.color(0.5 + 0.5 * phase.sin())
+    }
+}
+
+/// Wood grain, made of growth rings around the y axis wobbled by noise
+#[derive(Debug, Clone)]
+pub struct Wood {
+    mapping: Mapping,
+    rings: f32,
+    turbulence: f32,
+    octaves: u32,
+    ramp: ColorRamp,
+    perlin: Perlin,
+}
+
+impl Wood {
+    /// Creates a new wood texture with `rings` growth rings per unit,
+    /// wobbled by `turbulence`
+    pub fn new(
+        mapping: Mapping,
+        rings: f32,
+        turbulence: f32,
+        octaves: u32,
+        ramp: ColorRamp,
+    ) -> Wood {
+        Wood {
+            mapping,
+            rings,
+            turbulence,
+            octaves,
+            ramp,
+            perlin: Perlin::default(),
+        }
+    }
+}
+
+impl Texture for Wood {
+    fn evaluate(&self, hit: &RayHit) -> Vector3 {
+        let p = self.mapping.point(hit);
+        let radius = (p.x * p.x + p.z * p.z).sqrt();
+        let ring = radius * self.rings + self.turbulence * self.perlin.fbm(p, self.octaves);
+        self.ramp.color(ring - ring.floor())
+    }
+}
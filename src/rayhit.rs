@@ -1,34 +1,42 @@
+use crate::material::Material;
+use crate::sampling::orthonormal_basis;
 use crate::vector::Vector3;
 
-/// Reflection Refraction Index allows storing reflection/refraction data
-/// with three options: Diffuse, Reflective, and Reflective/Refractive.
-pub type ReflectionRefractionIndex = Option<(f32, Option<f32>)>;
-
 /// Describes a ray intersection of the surface of an intersectable
-#[derive(Debug)]
-pub struct RayHit {
+#[derive(Debug, Copy, Clone)]
+pub struct RayHit<'a> {
     position: Vector3,
     normal: Vector3,
+    geometric_normal: Vector3,
+    tangent: Vector3,
+    bitangent: Vector3,
+    uv: (f32, f32),
     distance: f32,
-    color: Vector3,
-    reflect_and_refract: ReflectionRefractionIndex,
+    material: &'a dyn Material,
 }
 
-impl RayHit {
+impl<'a> RayHit<'a> {
     /// Creates a new RayHit with the given hit data
+    ///
+    /// The tangent frame is an arbitrary one around the normal until it is
+    /// set with `with_tangents`.
     pub fn new(
         position: Vector3,
         normal: Vector3,
+        uv: (f32, f32),
         distance: f32,
-        color: Vector3,
-        reflect_and_refract: ReflectionRefractionIndex,
-    ) -> RayHit {
+        material: &'a dyn Material,
+    ) -> RayHit<'a> {
+        let (tangent, bitangent) = orthonormal_basis(normal);
         RayHit {
             position,
             normal,
+            geometric_normal: normal,
+            tangent,
+            bitangent,
+            uv,
             distance,
-            color,
-            reflect_and_refract,
+            material,
         }
     }
 
@@ -37,23 +45,83 @@ impl RayHit {
         self.position
     }
 
-    /// Gets the normal of the intersection on the surface
+    /// Gets the outward normal of the intersection on the surface
     pub fn normal(&self) -> Vector3 {
         self.normal
     }
 
+    /// Gets the outward normal of the surface's actual geometry, which is
+    /// the normal before materials bend it
+    pub fn geometric_normal(&self) -> Vector3 {
+        self.geometric_normal
+    }
+
+    /// Gets a copy of the hit with its tangent frame aligned to the
+    /// directions the texture coordinates u and v increase in
+    ///
+    /// The directions are projected onto the surface and made orthonormal,
+    /// keeping the tangent's direction exactly. Directions that vanish on
+    /// the surface leave the hit's frame as it was.
+    pub fn with_tangents(self, tangent: Vector3, bitangent: Vector3) -> RayHit<'a> {
+        let tangent = tangent - self.normal * self.normal.dot(tangent);
+        if tangent.len() < 1e-6 {
+            return self;
+        }
+        let tangent = tangent.normalize();
+
+        // Keeps the bitangent on the same side as the given one, so mirrored
+        // texture coordinates give mirrored frames
+        let side = self.normal.cross(tangent);
+        let bitangent = if side.dot(bitangent) < 0.0 {
+            -side
+        } else {
+            side
+        };
+        RayHit {
+            tangent,
+            bitangent,
+            ..self
+        }
+    }
+
+    /// Gets a copy of the hit with a new shading normal, bending the
+    /// tangent frame to stay perpendicular to it
+    ///
+    /// The geometric normal is kept as it was.
+    pub fn with_normal(self, normal: Vector3) -> RayHit<'a> {
+        let (tangent, bitangent) = orthonormal_basis(normal);
+        let bent = RayHit {
+            normal,
+            tangent,
+            bitangent,
+            ..self
+        };
+        bent.with_tangents(self.tangent, self.bitangent)
+    }
+
+    /// Gets the unit tangent, along which the texture coordinate u grows
+    pub fn tangent(&self) -> Vector3 {
+        self.tangent
+    }
+
+    /// Gets the unit bitangent, perpendicular to the normal and tangent on
+    /// the side the texture coordinate v grows
+    pub fn bitangent(&self) -> Vector3 {
+        self.bitangent
+    }
+
+    /// Gets the texture coordinates of the intersection on the surface
+    pub fn uv(&self) -> (f32, f32) {
+        self.uv
+    }
+
     /// Gets the distance between the ray origin and intersection point
     pub fn distance(&self) -> f32 {
         self.distance
     }
 
-    /// Gets the color of the point that was hit
-    pub fn color(&self) -> Vector3 {
-        self.color
-    }
-
-    /// Gets the optional reflection and refraction index
-    pub fn reflection_and_refraction_index(&self) -> ReflectionRefractionIndex {
-        self.reflect_and_refract
+    /// Gets the material of the surface that was hit
+    pub fn material(&self) -> &'a dyn Material {
+        self.material
     }
 }
@@ -0,0 +1,231 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use scoped_threadpool::Pool;
+
+use crate::checkpoint::{Checkpoint, CheckpointError};
+use crate::film::Film;
+use crate::filter::FilterSampler;
+use crate::image::Image;
+use crate::scene::Scene;
+use crate::settings::RenderSettings;
+use crate::tile::{Tile, TileTime};
+use crate::trace::{strata, trace_tile};
+
+/// Renders scenes into images using a pool of threads
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    settings: RenderSettings,
+    sampler: FilterSampler,
+}
+
+impl Renderer {
+    /// Creates a renderer with the given settings, rendering on at least
+    /// one thread
+    pub fn new(mut settings: RenderSettings) -> Renderer {
+        settings.threads = settings.threads.max(1);
+        Renderer {
+            settings,
+            sampler: FilterSampler::new(settings.filter),
+        }
+    }
+
+    /// Gets the settings used for rendering
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    /// Renders the scene into a new image
+    pub fn render(&self, scene: &Scene) -> Image {
+        self.render_film(scene).to_image(&self.settings.output)
+    }
+
+    /// Renders the scene into a floating point film
+    pub fn render_film(&self, scene: &Scene) -> Film {
+        self.render_tiles(scene).0
+    }
+
+    /// Renders the scene into a floating point film, timing every tile
+    ///
+    /// Tiles are returned in the order they finished.
+    pub fn render_tiles(&self, scene: &Scene) -> (Film, Vec<TileTime>) {
+        let mut film = Film::new(self.settings.width, self.settings.height);
+        let times = self.render_pass(scene, &mut film, 0, self.settings.samples);
+        (film, times)
+    }
+
+    /// Renders the scene progressively, adding passes of samples to the
+    /// film until the settings' sample count, time limit, or noise
+    /// threshold is reached
+    ///
+    /// `on_pass` is called with the film and progress after every pass,
+    /// such as to write previews.
+    pub fn render_progressive<F>(&self, scene: &Scene, on_pass: F) -> Film
+    where
+        F: FnMut(&Film, &Progress),
+    {
+        let film = Film::new(self.settings.width, self.settings.height);
+        self.add_passes(scene, film, Progress::default(), on_pass)
+    }
+
+    /// Resumes a progressive render from a checkpoint, adding passes until
+    /// the settings' limits are reached, or returns an error if the
+    /// checkpoint was saved from a different scene or with settings that
+    /// sample differently
+    ///
+    /// The time spent before the checkpoint counts towards the time limit.
+    /// With the same scene, the film matches an uninterrupted render bit for
+    /// bit.
+    pub fn resume<F>(
+        &self,
+        scene: &Scene,
+        checkpoint: Checkpoint,
+        on_pass: F,
+    ) -> Result<Film, CheckpointError>
+    where
+        F: FnMut(&Film, &Progress),
+    {
+        if !checkpoint.matches(&self.settings, scene) {
+            return Err(CheckpointError::Mismatch);
+        }
+        let (film, progress) = checkpoint.into_parts();
+        Ok(self.add_passes(scene, film, progress, on_pass))
+    }
+
+    /// Adds passes to a film until the settings' limits are reached
+    fn add_passes<F>(
+        &self,
+        scene: &Scene,
+        mut film: Film,
+        mut progress: Progress,
+        mut on_pass: F,
+    ) -> Film
+    where
+        F: FnMut(&Film, &Progress),
+    {
+        let settings = &self.settings;
+        let pass_samples = strata(settings.pass_samples).pow(2);
+        let start = Instant::now();
+        let earlier = progress.elapsed;
+
+        while !progress.is_finished(settings) {
+            self.render_pass(scene, &mut film, progress.passes, settings.pass_samples);
+            progress = Progress {
+                passes: progress.passes + 1,
+                samples: progress.samples + pass_samples,
+                elapsed: earlier + start.elapsed(),
+                noise: film.noise(),
+            };
+            on_pass(&film, &progress);
+        }
+        film
+    }
+
+    /// Renders one pass of `samples` samples through every pixel, adding
+    /// them to the film, and times every tile
+    ///
+    /// Each pass draws different random numbers, so passes with different
+    /// numbers add up to a less noisy image. Tiles are returned in the order
+    /// they finished.
+    pub fn render_pass(
+        &self,
+        scene: &Scene,
+        film: &mut Film,
+        pass: u32,
+        samples: u32,
+    ) -> Vec<TileTime> {
+        let (settings, sampler) = (&self.settings, &self.sampler);
+        let tiles = Tile::grid(settings.width, settings.height, settings.tile_size);
+        let mut times = Vec::with_capacity(tiles.len());
+
+        // Every thread pulls the next tile off a shared queue until it runs
+        // dry, so threads that drew cheap tiles such as sky keep working
+        // while others are busy with glass
+        let next = AtomicUsize::new(0);
+        let mut pool = Pool::new(settings.threads);
+        pool.scoped(|scope| {
+            let (sender, receiver) = mpsc::channel();
+            for thread in 0..settings.threads {
+                let sender = sender.clone();
+                let (tiles, next) = (&tiles, &next);
+                scope.execute(move || {
+                    while let Some(&tile) = tiles.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let start = Instant::now();
+                        let pixels = trace_tile(tile, pass, samples, settings, sampler, scene);
+                        let time = TileTime::new(tile, thread, start.elapsed());
+
+                        // Stops early if tiles are no longer being collected
+                        if sender.send((time, pixels)).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            // Adds tiles to the film as they finish
+            for (time, pixels) in receiver {
+                film.add_tile(time.tile(), &pixels);
+                times.push(time);
+            }
+        });
+        // Thread pool is effectively joined here
+
+        times
+    }
+}
+
+/// How far a progressive render has come
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Progress {
+    passes: u32,
+    samples: u32,
+    elapsed: Duration,
+    noise: Option<f32>,
+}
+
+impl Progress {
+    /// Creates a record of a progressive render's progress
+    pub fn new(passes: u32, samples: u32, elapsed: Duration, noise: Option<f32>) -> Progress {
+        Progress {
+            passes,
+            samples,
+            elapsed,
+            noise,
+        }
+    }
+
+    /// Gets the number of passes rendered so far
+    pub fn passes(&self) -> u32 {
+        self.passes
+    }
+
+    /// Gets the number of samples taken through each pixel so far
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Gets the time spent rendering so far
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Gets the film's estimated noise, or None until every pixel has at
+    /// least 2 samples
+    pub fn noise(&self) -> Option<f32> {
+        self.noise
+    }
+
+    /// Checks whether the render has reached any of its limits
+    pub fn is_finished(&self, settings: &RenderSettings) -> bool {
+        let time_up = settings
+            .time_limit
+            .is_some_and(|limit| self.elapsed >= limit);
+        let quiet = match (settings.noise_threshold, self.noise) {
+            (Some(threshold), Some(noise)) => noise <= threshold,
+            _ => false,
+        };
+        self.samples >= settings.samples || time_up || quiet
+    }
+}
@@ -0,0 +1,64 @@
+use std::f32::consts::PI;
+
+use crate::vector::Vector3;
+
+/// Maps a point in the unit square onto the unit disk, keeping strata
+/// adjacent (Shirley and Chiu's concentric mapping)
+pub fn concentric_disk(u: f32, v: f32) -> (f32, f32) {
+    let a = 2.0 * u - 1.0;
+    let b = 2.0 * v - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, (PI / 4.0) * (b / a))
+    } else {
+        (b, (PI / 2.0) - (PI / 4.0) * (a / b))
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
+/// Maps a point in the unit square uniformly onto a regular polygon with
+/// `sides` corners on the unit circle, one of them straight up
+///
+/// The polygon is split into a fan of triangles around its center, and `u`
+/// picks the triangle before being reused within it.
+pub fn regular_polygon(sides: u32, u: f32, v: f32) -> (f32, f32) {
+    let sides = sides.max(3);
+    let scaled = u * sides as f32;
+    let index = (scaled as u32).min(sides - 1);
+    let u = scaled - index as f32;
+
+    let corner = |k: u32| {
+        let angle = PI / 2.0 + 2.0 * PI * k as f32 / sides as f32;
+        (angle.cos(), angle.sin())
+    };
+    let (a, b) = (corner(index), corner(index + 1));
+    let s = u.sqrt();
+    let (wa, wb) = (s * (1.0 - v), s * v);
+    (a.0 * wa + b.0 * wb, a.1 * wa + b.1 * wb)
+}
+
+/// Builds two unit vectors that form an orthonormal basis with the given
+/// normalized vector
+pub fn orthonormal_basis(n: Vector3) -> (Vector3, Vector3) {
+    // Duff et al., "Building an Orthonormal Basis, Revisited"
+    let sign = 1.0f32.copysign(n.z);
+    let a = -1.0 / (sign + n.z);
+    let b = n.x * n.y * a;
+    (
+        Vector3::new(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x),
+        Vector3::new(b, sign + n.y * n.y * a, -n.y),
+    )
+}
+
+/// Maps a point in the unit square onto the hemisphere around the normalized
+/// vector `n`, with density proportional to the cosine of the angle to `n`
+///
+/// Directions are projected up from the concentric disk (Malley's method).
+pub fn cosine_hemisphere(n: Vector3, u: f32, v: f32) -> Vector3 {
+    let (x, y) = concentric_disk(u, v);
+    let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+    let (tangent, bitangent) = orthonormal_basis(n);
+    (tangent * x + bitangent * y + n * z).normalize()
+}
@@ -0,0 +1,1674 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::bump::{Bump, Bumped};
+use crate::camera::{Camera, Projection};
+use crate::checkpoint::Fnv;
+use crate::disk::Disk;
+use crate::filter::Filter;
+use crate::gltf::{gltf_dependencies, load_gltf, GltfError};
+use crate::instance::Instance;
+use crate::intersectable::Intersectable;
+use crate::light::{
+    DirectionalLight, DiskLight, Light, PointLight, RectangleLight, SphereLight, SpotLight,
+};
+use crate::material::{
+    Conductor, Dielectric, Emissive, Lambertian, Material, Metal, Mirror, Phong, RoughDielectric,
+};
+use crate::mesh::TriangleMesh;
+use crate::obj::{load_obj_with_dependencies, ObjError};
+use crate::plane::Plane;
+use crate::principled::{Principled, PrincipledParameters};
+use crate::procedural::{Checker, ColorRamp, Mapping, Marble, Noise, NoiseKind, Wood};
+use crate::settings::{Integrator, RenderSettings};
+use crate::sphere::Sphere;
+use crate::texture::{ImageTexture, Scaled, Texture, TextureError, Wrap};
+use crate::tonemap::{OutputTransform, ToneMap};
+use crate::transform::{Matrix4, Transform};
+use crate::triangle::Triangle;
+use crate::vector::Vector3;
+use crate::world::World;
+
+/// Everything needed to shade rays: the camera, objects, and lighting
+pub struct Scene {
+    camera: Camera,
+    world: World,
+    lights: Vec<Box<dyn Light>>,
+    ambient: f32,
+    background: Vector3,
+    fingerprint: u64,
+}
+
+impl Scene {
+    /// Creates a new scene
+    pub fn new(
+        camera: Camera,
+        world: World,
+        lights: Vec<Box<dyn Light>>,
+        ambient: f32,
+        background: Vector3,
+    ) -> Scene {
+        Scene {
+            camera,
+            world,
+            lights,
+            ambient,
+            background,
+            fingerprint: 0,
+        }
+    }
+
+    /// Sets the fingerprint identifying the scene's contents, which
+    /// checkpoints compare before resuming a render
+    pub fn with_fingerprint(mut self, fingerprint: u64) -> Scene {
+        self.fingerprint = fingerprint;
+        self
+    }
+
+    /// Gets the camera the scene is viewed through
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Gets the objects in the scene
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Gets the lights illuminating the scene
+    pub fn lights(&self) -> &[Box<dyn Light>] {
+        &self.lights
+    }
+
+    /// Gets the strength of the ambient light
+    pub fn ambient(&self) -> f32 {
+        self.ambient
+    }
+
+    /// Gets the color of rays that escape the scene
+    pub fn background(&self) -> Vector3 {
+        self.background
+    }
+
+    /// Gets the fingerprint identifying the scene's contents
+    ///
+    /// Scenes loaded from files hash the bytes of the scene file and every
+    /// mesh, material library, buffer, and image it loads. Scenes built in
+    /// code have a fingerprint of 0 unless they are given one.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+}
+
+/// A scene loaded from a scene description file, along with the render
+/// settings and output path it asks for
+pub struct SceneFile {
+    pub scene: Scene,
+    pub settings: RenderSettings,
+    pub output: PathBuf,
+}
+
+/// Describes why a scene description file could not be loaded
+#[derive(Debug)]
+pub enum SceneError {
+    /// The file could not be read
+    Io(PathBuf, io::Error),
+    /// The file is malformed or describes an invalid scene
+    Parse {
+        path: PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A mesh referenced by the scene could not be loaded
+    Obj(ObjError),
+    /// A glTF file referenced by the scene could not be loaded
+    Gltf(GltfError),
+    /// A texture referenced by the scene could not be loaded
+    Texture(TextureError),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            SceneError::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{}:{}: {}", path.display(), line, column, message),
+            SceneError::Obj(err) => write!(f, "{}", err),
+            SceneError::Gltf(err) => write!(f, "{}", err),
+            SceneError::Texture(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io(_, err) => Some(err),
+            SceneError::Parse { .. } => None,
+            SceneError::Obj(err) => Some(err),
+            SceneError::Gltf(err) => Some(err),
+            SceneError::Texture(err) => Some(err),
+        }
+    }
+}
+
+impl From<ObjError> for SceneError {
+    fn from(err: ObjError) -> SceneError {
+        SceneError::Obj(err)
+    }
+}
+
+impl From<GltfError> for SceneError {
+    fn from(err: GltfError) -> SceneError {
+        SceneError::Gltf(err)
+    }
+}
+
+impl From<TextureError> for SceneError {
+    fn from(err: TextureError) -> SceneError {
+        SceneError::Texture(err)
+    }
+}
+
+/// Top level of a scene description file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    #[serde(default = "default_output")]
+    output: String,
+    #[serde(default)]
+    render: RenderDescription,
+    camera: Spanned<CameraDescription>,
+    #[serde(default)]
+    lights: Vec<Spanned<LightDescription>>,
+    #[serde(default = "default_ambient")]
+    ambient: f32,
+    #[serde(default = "default_background")]
+    background: [f32; 3],
+    #[serde(default)]
+    textures: HashMap<String, Spanned<TextureDescription>>,
+    #[serde(default)]
+    materials: HashMap<String, Spanned<SurfaceDescription>>,
+    #[serde(default)]
+    objects: Vec<Spanned<PlacedObjectDescription>>,
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RenderDescription {
+    width: Spanned<u32>,
+    height: Spanned<u32>,
+    threads: Spanned<u32>,
+    tile_size: Spanned<u32>,
+    bounces: u32,
+    samples: Spanned<u32>,
+    progressive: bool,
+    pass_samples: Spanned<u32>,
+    time_limit: Option<Spanned<f32>>,
+    noise_threshold: Option<Spanned<f32>>,
+    preview_interval: Option<Spanned<f32>>,
+    seed: u64,
+    filter: Option<Spanned<FilterDescription>>,
+    integrator: Option<Spanned<String>>,
+    exposure: f32,
+    tone_map: Option<Spanned<String>>,
+    white_point: Option<Spanned<f32>>,
+    srgb: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FilterDescription {
+    #[serde(rename = "type")]
+    kind: String,
+    radius: Option<f32>,
+    alpha: Option<f32>,
+    b: Option<f32>,
+    c: Option<f32>,
+}
+
+impl FilterDescription {
+    /// Creates the named filter, overriding its default parameters with any
+    /// that were given, or reports the key at fault
+    fn to_filter(&self) -> Result<Filter, (&'static str, String)> {
+        let mut filter = Filter::from_name(&self.kind)
+            .ok_or_else(|| ("type", format!("unknown filter `{}`", self.kind)))?;
+        match &mut filter {
+            Filter::Box { radius } | Filter::Tent { radius } => {
+                if self.alpha.is_some() || self.b.is_some() || self.c.is_some() {
+                    return Err(("type", format!("{} filters only take a radius", self.kind)));
+                }
+                *radius = self.radius.unwrap_or(*radius);
+            }
+            Filter::Gaussian { radius, alpha } => {
+                if self.b.is_some() || self.c.is_some() {
+                    let message = "gaussian filters only take a radius and alpha";
+                    return Err(("type", message.to_string()));
+                }
+                *radius = self.radius.unwrap_or(*radius);
+                *alpha = self.alpha.unwrap_or(*alpha);
+                if *alpha <= 0.0 {
+                    let message = "gaussian filter alpha must be greater than 0";
+                    return Err(("alpha", message.to_string()));
+                }
+            }
+            Filter::Mitchell { radius, b, c } => {
+                if self.alpha.is_some() {
+                    let message = "mitchell filters only take a radius, b, and c";
+                    return Err(("type", message.to_string()));
+                }
+                *radius = self.radius.unwrap_or(*radius);
+                *b = self.b.unwrap_or(*b);
+                *c = self.c.unwrap_or(*c);
+            }
+        }
+        if filter.radius() <= 0.0 {
+            return Err(("radius", "filter radius must be greater than 0".to_string()));
+        }
+
+        // Samples are scaled by the filter's integral, so it must be usable
+        let integral = filter.integral();
+        if !integral.is_finite() || integral <= 0.0 {
+            return Err(match filter {
+                Filter::Mitchell { b, c, .. } => (
+                    if self.b.is_some() { "b" } else { "c" },
+                    format!(
+                        "mitchell filter b = {} and c = {} give weights summing to {}, \
+                         but they must sum to more than 0",
+                        b, c, integral
+                    ),
+                ),
+                _ => (
+                    if self.alpha.is_some() {
+                        "alpha"
+                    } else {
+                        "radius"
+                    },
+                    format!(
+                        "{} filter weights sum to {}, but they must sum to more than 0",
+                        self.kind, integral
+                    ),
+                ),
+            });
+        }
+        Ok(filter)
+    }
+}
+
+impl Default for RenderDescription {
+    fn default() -> RenderDescription {
+        let settings = RenderSettings::default();
+        RenderDescription {
+            width: Spanned::new(0..0, settings.width),
+            height: Spanned::new(0..0, settings.height),
+            threads: Spanned::new(0..0, settings.threads),
+            tile_size: Spanned::new(0..0, settings.tile_size),
+            bounces: settings.bounces,
+            samples: Spanned::new(0..0, settings.samples),
+            progressive: settings.progressive,
+            pass_samples: Spanned::new(0..0, settings.pass_samples),
+            time_limit: None,
+            noise_threshold: None,
+            preview_interval: None,
+            seed: settings.seed,
+            filter: None,
+            integrator: None,
+            exposure: settings.output.exposure,
+            tone_map: None,
+            white_point: None,
+            srgb: settings.output.srgb,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    position: [f32; 3],
+    #[serde(default = "default_up")]
+    up: [f32; 3],
+    fov: Option<Spanned<f32>>,
+    projection: Option<String>,
+    view_width: Option<f32>,
+    target: Option<[f32; 3]>,
+    pitch: Option<f32>,
+    yaw: Option<f32>,
+    #[serde(default)]
+    roll: f32,
+    #[serde(default)]
+    aperture: f32,
+    focus_distance: Option<f32>,
+    #[serde(default)]
+    blades: u32,
+}
+
+impl CameraDescription {
+    /// Creates the described camera, reporting problems at `span` or at
+    /// the field of view
+    fn to_camera(
+        &self,
+        span: Range<usize>,
+        error: &dyn Fn(Range<usize>, String) -> SceneError,
+    ) -> Result<Camera, SceneError> {
+        let message = |message: &str| error(span.clone(), message.to_string());
+
+        // Picks the projection, which decides what field of view it takes
+        let projection = match (self.projection.as_deref(), self.view_width) {
+            (Some("orthographic"), Some(width)) if width > 0.0 => {
+                Projection::Orthographic { width }
+            }
+            (Some("orthographic"), Some(_)) => {
+                return Err(message("view_width must be greater than 0"))
+            }
+            (Some("orthographic"), None) => {
+                return Err(message("orthographic cameras need a view_width"))
+            }
+            (_, Some(_)) => return Err(message("view_width is only used by orthographic cameras")),
+            (None, None) | (Some("perspective"), None) => Projection::Perspective,
+            (Some("fisheye"), None) => Projection::Fisheye,
+            (Some("equirectangular"), None) => Projection::Equirectangular,
+            (Some(name), None) => {
+                return Err(error(span, format!("unknown projection `{}`", name)))
+            }
+        };
+        let max_fov = match projection {
+            Projection::Perspective => Some(180.0),
+            Projection::Fisheye => Some(360.0),
+            Projection::Orthographic { .. } | Projection::Equirectangular => None,
+        };
+        let fov = match (&self.fov, max_fov) {
+            (Some(fov), Some(max)) if *fov.get_ref() <= 0.0 || *fov.get_ref() >= max => {
+                return Err(error(
+                    fov.span(),
+                    format!("fov must be between 0 and {} degrees", max),
+                ));
+            }
+            (Some(fov), _) => *fov.get_ref(),
+            (None, Some(_)) => return Err(message("camera needs a fov")),
+            (None, None) => 0.0,
+        };
+
+        // Aims the camera either at a target or by pitch and yaw
+        let position = vector(self.position);
+        let camera = match self.target {
+            Some(_) if self.pitch.is_some() || self.yaw.is_some() => {
+                return Err(message(
+                    "camera takes either a target or a pitch and yaw, not both",
+                ));
+            }
+            Some(target) if (vector(target) - position).len() == 0.0 => {
+                return Err(message("camera target must differ from its position"));
+            }
+            Some(target) => Camera::look_at(position, vector(target), vector(self.up), fov),
+            None => Camera::new(
+                position,
+                vector(self.up),
+                fov,
+                self.pitch.unwrap_or(0.0),
+                self.yaw.unwrap_or(0.0),
+            ),
+        };
+
+        // Adds a lens for depth of field, focused on the target by default
+        if self.aperture < 0.0 {
+            return Err(message("aperture must not be negative"));
+        }
+        if self.blades > 0 && self.blades < 3 {
+            return Err(message(
+                "aperture needs at least 3 blades, or 0 for a round one",
+            ));
+        }
+        let focus_distance = match (self.focus_distance, self.target) {
+            (Some(distance), _) => distance,
+            (None, Some(target)) => (vector(target) - position).len(),
+            (None, None) if self.aperture > 0.0 => {
+                return Err(message(
+                    "a camera with an aperture needs a focus_distance or a target",
+                ));
+            }
+            (None, None) => 1.0,
+        };
+        if focus_distance <= 0.0 {
+            return Err(message("focus_distance must be greater than 0"));
+        }
+
+        Ok(camera
+            .with_roll(self.roll)
+            .with_projection(projection)
+            .with_lens(self.aperture, focus_distance)
+            .with_blades(self.blades))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum LightDescription {
+    Directional {
+        direction: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    Point {
+        position: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+        angle: f32,
+        #[serde(default)]
+        falloff: f32,
+    },
+    Rectangle {
+        position: [f32; 3],
+        u: [f32; 3],
+        v: [f32; 3],
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+        #[serde(default = "default_light_samples")]
+        samples: u32,
+    },
+    Disk {
+        position: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+        #[serde(default = "default_light_samples")]
+        samples: u32,
+    },
+    Sphere {
+        position: [f32; 3],
+        radius: f32,
+        #[serde(default = "default_light_color")]
+        color: [f32; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+        #[serde(default = "default_light_samples")]
+        samples: u32,
+    },
+}
+
+/// A material color, given either directly or by the name of a texture
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorDescription {
+    Constant([f32; 3]),
+    Texture(String),
+}
+
+impl ColorDescription {
+    /// Gets the color as a texture, looking named textures up in `textures`
+    fn to_texture(
+        &self,
+        textures: &HashMap<&str, Arc<dyn Texture>>,
+    ) -> Result<Arc<dyn Texture>, String> {
+        match self {
+            ColorDescription::Constant(color) => Ok(vector(*color).into()),
+            ColorDescription::Texture(name) => match textures.get(name.as_str()) {
+                Some(texture) => Ok(Arc::clone(texture)),
+                None => Err(format!("unknown texture `{}`", name)),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum TextureDescription {
+    Image {
+        path: String,
+        wrap: Option<String>,
+        srgb: Option<bool>,
+        scale: Option<ScaleDescription>,
+    },
+    Checker {
+        #[serde(default = "default_white")]
+        even: ColorDescription,
+        #[serde(default = "default_black")]
+        odd: ColorDescription,
+        mapping: Option<String>,
+        scale: Option<ScaleDescription>,
+    },
+    Noise {
+        noise: Option<String>,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        mapping: Option<String>,
+        scale: Option<ScaleDescription>,
+        ramp: Option<Vec<RampStopDescription>>,
+    },
+    Marble {
+        #[serde(default = "default_frequency")]
+        frequency: f32,
+        #[serde(default = "default_marble_turbulence")]
+        turbulence: f32,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        mapping: Option<String>,
+        scale: Option<ScaleDescription>,
+        ramp: Option<Vec<RampStopDescription>>,
+    },
+    Wood {
+        #[serde(default = "default_rings")]
+        rings: f32,
+        #[serde(default = "default_wood_turbulence")]
+        turbulence: f32,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        mapping: Option<String>,
+        scale: Option<ScaleDescription>,
+        ramp: Option<Vec<RampStopDescription>>,
+    },
+}
+
+impl TextureDescription {
+    /// Gets how much the texture's pattern is scaled by, if at all
+    fn scale(&self) -> Option<&ScaleDescription> {
+        match self {
+            TextureDescription::Image { scale, .. }
+            | TextureDescription::Checker { scale, .. }
+            | TextureDescription::Noise { scale, .. }
+            | TextureDescription::Marble { scale, .. }
+            | TextureDescription::Wood { scale, .. } => scale.as_ref(),
+        }
+    }
+}
+
+/// The scale of a texture, either the same along every axis or per axis
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScaleDescription {
+    Uniform(f32),
+    PerAxis([f32; 3]),
+}
+
+impl ScaleDescription {
+    /// Gets the scale along each axis
+    fn to_vector(&self) -> Vector3 {
+        match self {
+            ScaleDescription::Uniform(scale) => Vector3::new_scalar(*scale),
+            ScaleDescription::PerAxis(scale) => vector(*scale),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RampStopDescription {
+    position: f32,
+    color: [f32; 3],
+}
+
+/// Creates the color ramp described by a list of stops, or the given
+/// default if there isn't one
+fn ramp(stops: &Option<Vec<RampStopDescription>>, default: ColorRamp) -> Result<ColorRamp, String> {
+    match stops {
+        Some(stops) if stops.is_empty() => Err("color ramps need at least one stop".to_string()),
+        Some(stops) => Ok(ColorRamp::new(
+            stops
+                .iter()
+                .map(|stop| (stop.position, vector(stop.color)))
+                .collect(),
+        )),
+        None => Ok(default),
+    }
+}
+
+/// Gets a procedural texture's mapping by name, defaulting to position
+fn mapping(name: &Option<String>) -> Result<Mapping, String> {
+    match name {
+        Some(name) => {
+            Mapping::from_name(name).ok_or_else(|| format!("unknown texture mapping `{}`", name))
+        }
+        None => Ok(Mapping::default()),
+    }
+}
+
+/// Creates the textures of a scene file on demand, so textures can be made
+/// out of other textures declared in any order
+struct TextureLoader<'a> {
+    descriptions: &'a HashMap<String, Spanned<TextureDescription>>,
+    directory: &'a Path,
+    textures: HashMap<&'a str, Arc<dyn Texture>>,
+    pending: Vec<&'a str>,
+    files: Vec<PathBuf>,
+}
+
+impl<'a> TextureLoader<'a> {
+    /// Gets the named texture, creating it and every texture it refers to
+    /// the first time it is needed
+    fn load(
+        &mut self,
+        name: &'a str,
+        error: &dyn Fn(Range<usize>, String) -> SceneError,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        if let Some(texture) = self.textures.get(name) {
+            return Ok(Arc::clone(texture));
+        }
+        let (name, description) = self
+            .descriptions
+            .get_key_value(name)
+            .expect("texture references are checked before loading");
+        let span = description.span();
+        if self.pending.contains(&name.as_str()) {
+            return Err(error(
+                span,
+                format!("texture `{}` refers back to itself", name),
+            ));
+        }
+        self.pending.push(name);
+
+        let message = |message: String| error(span.clone(), message);
+        let texture: Arc<dyn Texture> = match description.get_ref() {
+            TextureDescription::Image {
+                path, wrap, srgb, ..
+            } => {
+                let wrap = match wrap {
+                    Some(wrap) => Wrap::from_name(wrap)
+                        .ok_or_else(|| message(format!("unknown wrap mode `{}`", wrap)))?,
+                    None => Wrap::default(),
+                };
+                let path = self.directory.join(path);
+                let texture = ImageTexture::load(&path, wrap)?;
+                self.files.push(path);
+                if srgb.unwrap_or(true) {
+                    Arc::new(texture.decode_srgb())
+                } else {
+                    Arc::new(texture)
+                }
+            }
+            TextureDescription::Checker {
+                even,
+                odd,
+                mapping: name,
+                ..
+            } => Arc::new(Checker::new(
+                mapping(name).map_err(message)?,
+                self.color(even, span.clone(), error)?,
+                self.color(odd, span.clone(), error)?,
+            )),
+            TextureDescription::Noise {
+                noise,
+                octaves,
+                mapping: name,
+                ramp: stops,
+                ..
+            } => {
+                let kind = match noise {
+                    Some(noise) => NoiseKind::from_name(noise)
+                        .ok_or_else(|| message(format!("unknown noise `{}`", noise)))?,
+                    None => NoiseKind::default(),
+                };
+                Arc::new(Noise::new(
+                    mapping(name).map_err(message)?,
+                    kind,
+                    *octaves,
+                    ramp(stops, ColorRamp::default()).map_err(message)?,
+                ))
+            }
+            TextureDescription::Marble {
+                frequency,
+                turbulence,
+                octaves,
+                mapping: name,
+                ramp: stops,
+                ..
+            } => Arc::new(Marble::new(
+                mapping(name).map_err(message)?,
+                *frequency,
+                *turbulence,
+                *octaves,
+                ramp(stops, default_marble_ramp()).map_err(message)?,
+            )),
+            TextureDescription::Wood {
+                rings,
+                turbulence,
+                octaves,
+                mapping: name,
+                ramp: stops,
+                ..
+            } => Arc::new(Wood::new(
+                mapping(name).map_err(message)?,
+                *rings,
+                *turbulence,
+                *octaves,
+                ramp(stops, default_wood_ramp()).map_err(message)?,
+            )),
+        };
+        let texture: Arc<dyn Texture> = match description.get_ref().scale() {
+            Some(scale) => Arc::new(Scaled::new(texture, scale.to_vector())),
+            None => texture,
+        };
+
+        self.pending.pop();
+        self.textures.insert(name, Arc::clone(&texture));
+        Ok(texture)
+    }
+
+    /// Gets a color used by a texture, loading the texture it names if it
+    /// isn't a plain color
+    fn color(
+        &mut self,
+        color: &'a ColorDescription,
+        span: Range<usize>,
+        error: &dyn Fn(Range<usize>, String) -> SceneError,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        match color {
+            ColorDescription::Constant(color) => Ok(vector(*color).into()),
+            ColorDescription::Texture(name) if self.descriptions.contains_key(name) => {
+                self.load(name, error)
+            }
+            ColorDescription::Texture(name) => {
+                Err(error(span, format!("unknown texture `{}`", name)))
+            }
+        }
+    }
+}
+
+/// A material along with the textures that bend its normal
+#[derive(Deserialize)]
+struct SurfaceDescription {
+    #[serde(flatten)]
+    material: MaterialDescription,
+    normal_map: Option<String>,
+    #[serde(default = "default_strength")]
+    normal_strength: f32,
+    bump_map: Option<String>,
+    #[serde(default = "default_strength")]
+    bump_strength: f32,
+}
+
+impl SurfaceDescription {
+    /// Creates the described material, wrapping it in a height map and then
+    /// a normal map if it has them
+    fn to_material(
+        &self,
+        textures: &HashMap<&str, Arc<dyn Texture>>,
+    ) -> Result<Arc<dyn Material>, String> {
+        let map = |name: &str| match textures.get(name) {
+            Some(texture) => Ok(Arc::clone(texture)),
+            None => Err(format!("unknown texture `{}`", name)),
+        };
+        let mut material = self.material.to_material(textures)?;
+        if let Some(name) = &self.bump_map {
+            let bump = Bump::HeightMap(map(name)?);
+            material = Arc::new(Bumped::new(material, bump, self.bump_strength));
+        }
+        if let Some(name) = &self.normal_map {
+            let bump = Bump::NormalMap(map(name)?);
+            material = Arc::new(Bumped::new(material, bump, self.normal_strength));
+        }
+        Ok(material)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum MaterialDescription {
+    Lambertian {
+        color: ColorDescription,
+    },
+    Phong {
+        color: ColorDescription,
+        #[serde(default = "default_specular")]
+        specular: ColorDescription,
+        #[serde(default = "default_exponent")]
+        exponent: f32,
+        #[serde(default)]
+        reflectivity: f32,
+    },
+    Mirror {
+        #[serde(default = "default_white")]
+        color: ColorDescription,
+    },
+    Dielectric {
+        ior: f32,
+        #[serde(default = "default_white")]
+        color: ColorDescription,
+        #[serde(default)]
+        roughness: f32,
+    },
+    Conductor {
+        metal: Option<String>,
+        eta: Option<[f32; 3]>,
+        k: Option<[f32; 3]>,
+        #[serde(default)]
+        roughness: f32,
+    },
+    Emissive {
+        #[serde(default = "default_white")]
+        color: ColorDescription,
+        #[serde(default = "default_intensity")]
+        intensity: f32,
+    },
+    Principled {
+        base_color: Option<ColorDescription>,
+        metallic: Option<f32>,
+        roughness: Option<f32>,
+        specular: Option<f32>,
+        specular_tint: Option<f32>,
+        sheen: Option<f32>,
+        sheen_tint: Option<f32>,
+        clearcoat: Option<f32>,
+        clearcoat_roughness: Option<f32>,
+        transmission: Option<f32>,
+        ior: Option<f32>,
+    },
+}
+
+impl MaterialDescription {
+    /// Creates the described material, looking up any textures its colors
+    /// name in `textures`
+    fn to_material(
+        &self,
+        textures: &HashMap<&str, Arc<dyn Texture>>,
+    ) -> Result<Arc<dyn Material>, String> {
+        let texture = |color: &ColorDescription| color.to_texture(textures);
+        Ok(match self {
+            MaterialDescription::Lambertian { color } => Arc::new(Lambertian::new(texture(color)?)),
+            MaterialDescription::Phong {
+                color,
+                specular,
+                exponent,
+                reflectivity,
+            } => {
+                if !(0.0..=1.0).contains(reflectivity) {
+                    return Err("reflectivity must be between 0 and 1".to_string());
+                }
+                if *exponent < 0.0 {
+                    return Err("exponent must not be negative".to_string());
+                }
+                Arc::new(Phong::new(
+                    texture(color)?,
+                    texture(specular)?,
+                    *exponent,
+                    *reflectivity,
+                ))
+            }
+            MaterialDescription::Mirror { color } => Arc::new(Mirror::new(texture(color)?)),
+            MaterialDescription::Dielectric {
+                ior,
+                color,
+                roughness,
+            } => {
+                if *ior <= 0.0 {
+                    return Err("ior must be greater than 0".to_string());
+                }
+                check_roughness(*roughness)?;
+                if *roughness > 0.0 {
+                    Arc::new(RoughDielectric::new(*ior, texture(color)?, *roughness))
+                } else {
+                    Arc::new(Dielectric::new(*ior, texture(color)?))
+                }
+            }
+            MaterialDescription::Conductor {
+                metal,
+                eta,
+                k,
+                roughness,
+            } => {
+                check_roughness(*roughness)?;
+                match (metal, eta, k) {
+                    (Some(name), None, None) => match Metal::from_name(name) {
+                        Some(metal) => Arc::new(Conductor::from_metal(metal, *roughness)),
+                        None => return Err(format!("unknown metal `{}`", name)),
+                    },
+                    (None, Some(eta), Some(k)) => {
+                        Arc::new(Conductor::new(vector(*eta), vector(*k), *roughness))
+                    }
+                    _ => return Err("conductors need either a metal or both eta and k".to_string()),
+                }
+            }
+            MaterialDescription::Emissive { color, intensity } => {
+                Arc::new(Emissive::new(texture(color)?, *intensity))
+            }
+            MaterialDescription::Principled {
+                base_color,
+                metallic,
+                roughness,
+                specular,
+                specular_tint,
+                sheen,
+                sheen_tint,
+                clearcoat,
+                clearcoat_roughness,
+                transmission,
+                ior,
+            } => {
+                let defaults = PrincipledParameters::default();
+                let parameters = PrincipledParameters {
+                    base_color: match base_color {
+                        Some(color) => texture(color)?,
+                        None => Arc::clone(&defaults.base_color),
+                    },
+                    metallic: metallic.unwrap_or(defaults.metallic),
+                    roughness: roughness.unwrap_or(defaults.roughness),
+                    specular: specular.unwrap_or(defaults.specular),
+                    specular_tint: specular_tint.unwrap_or(defaults.specular_tint),
+                    sheen: sheen.unwrap_or(defaults.sheen),
+                    sheen_tint: sheen_tint.unwrap_or(defaults.sheen_tint),
+                    clearcoat: clearcoat.unwrap_or(defaults.clearcoat),
+                    clearcoat_roughness: clearcoat_roughness
+                        .unwrap_or(defaults.clearcoat_roughness),
+                    transmission: transmission.unwrap_or(defaults.transmission),
+                    ior: ior.unwrap_or(defaults.ior),
+                };
+                let p = &parameters;
+                for &(name, value) in &[
+                    ("metallic", p.metallic),
+                    ("roughness", p.roughness),
+                    ("specular", p.specular),
+                    ("specular_tint", p.specular_tint),
+                    ("sheen", p.sheen),
+                    ("sheen_tint", p.sheen_tint),
+                    ("clearcoat", p.clearcoat),
+                    ("clearcoat_roughness", p.clearcoat_roughness),
+                    ("transmission", p.transmission),
+                ] {
+                    if !(0.0..=1.0).contains(&value) {
+                        return Err(format!("{} must be between 0 and 1", name));
+                    }
+                }
+                if p.ior <= 0.0 {
+                    return Err("ior must be greater than 0".to_string());
+                }
+                Arc::new(Principled::new(parameters))
+            }
+        })
+    }
+}
+
+/// An object along with the transforms that place it, applied in order
+#[derive(Deserialize)]
+struct PlacedObjectDescription {
+    #[serde(flatten)]
+    object: ObjectDescription,
+    #[serde(default)]
+    transform: Vec<TransformDescription>,
+}
+
+impl PlacedObjectDescription {
+    /// Creates the combined transform of the object, or returns None if it
+    /// has none
+    fn to_transform(&self) -> Result<Option<Transform>, String> {
+        if self.transform.is_empty() {
+            return Ok(None);
+        }
+        let mut combined = Transform::identity();
+        for transform in &self.transform {
+            combined = combined.then(transform.to_transform()?);
+        }
+        Ok(Some(combined))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+enum TransformDescription {
+    Translate([f32; 3]),
+    Scale(ScaleDescription),
+    /// Rotations in degrees about the x, y, then z axes
+    Rotate([f32; 3]),
+    /// A matrix given by its rows
+    Matrix([[f32; 4]; 4]),
+}
+
+impl TransformDescription {
+    /// Creates the described transform
+    fn to_transform(&self) -> Result<Transform, String> {
+        match self {
+            TransformDescription::Translate(offset) => Ok(Transform::translate(vector(*offset))),
+            TransformDescription::Scale(scale) => Transform::scale(scale.to_vector())
+                .ok_or_else(|| "scale must not be 0 along any axis".to_string()),
+            TransformDescription::Rotate([x, y, z]) => {
+                Ok(Transform::rotate(Vector3::new(1.0, 0.0, 0.0), *x)
+                    .then(Transform::rotate(Vector3::new(0.0, 1.0, 0.0), *y))
+                    .then(Transform::rotate(Vector3::new(0.0, 0.0, 1.0), *z)))
+            }
+            TransformDescription::Matrix(rows) => Transform::new(Matrix4::new(*rows))
+                .ok_or_else(|| "transform matrix can't be inverted".to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum ObjectDescription {
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    Plane {
+        origin: [f32; 3],
+        normal: [f32; 3],
+        material: String,
+    },
+    Triangle {
+        vertices: [[f32; 3]; 3],
+        material: String,
+    },
+    Disk {
+        center: [f32; 3],
+        normal: [f32; 3],
+        radius: f32,
+        material: String,
+    },
+    Mesh {
+        path: String,
+    },
+}
+
+fn default_output() -> String {
+    "out.png".to_string()
+}
+
+fn default_background() -> [f32; 3] {
+    [0.529, 0.808, 0.98]
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_light_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+fn default_white() -> ColorDescription {
+    ColorDescription::Constant([1.0, 1.0, 1.0])
+}
+
+fn default_black() -> ColorDescription {
+    ColorDescription::Constant([0.0, 0.0, 0.0])
+}
+
+fn default_specular() -> ColorDescription {
+    ColorDescription::Constant([0.5, 0.5, 0.5])
+}
+
+fn default_exponent() -> f32 {
+    64.0
+}
+
+fn default_strength() -> f32 {
+    1.0
+}
+
+fn default_octaves() -> u32 {
+    4
+}
+
+fn default_frequency() -> f32 {
+    1.0
+}
+
+fn default_marble_turbulence() -> f32 {
+    5.0
+}
+
+fn default_rings() -> f32 {
+    8.0
+}
+
+fn default_wood_turbulence() -> f32 {
+    0.5
+}
+
+/// Dark veins through white stone
+fn default_marble_ramp() -> ColorRamp {
+    ColorRamp::new(vec![
+        (0.0, Vector3::new(0.15, 0.15, 0.2)),
+        (0.4, Vector3::new(0.7, 0.7, 0.72)),
+        (1.0, Vector3::new(0.95, 0.94, 0.9)),
+    ])
+}
+
+/// Light early wood fading into darker late wood
+fn default_wood_ramp() -> ColorRamp {
+    ColorRamp::new(vec![
+        (0.0, Vector3::new(0.75, 0.52, 0.3)),
+        (0.7, Vector3::new(0.6, 0.38, 0.2)),
+        (1.0, Vector3::new(0.35, 0.2, 0.1)),
+    ])
+}
+
+fn default_light_samples() -> u32 {
+    16
+}
+
+fn default_ambient() -> f32 {
+    0.1
+}
+
+/// Checks that a material's roughness is in 0..1
+fn check_roughness(roughness: f32) -> Result<(), String> {
+    if (0.0..=1.0).contains(&roughness) {
+        Ok(())
+    } else {
+        Err("roughness must be between 0 and 1".to_string())
+    }
+}
+
+/// Converts a coordinate array from the file into a vector
+fn vector(v: [f32; 3]) -> Vector3 {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+/// A mesh loaded from a file, with the transform the file places it with
+type PlacedMesh = (Arc<TriangleMesh>, Option<Transform>);
+
+/// Loads the meshes of an OBJ file, or of a glTF file (`.gltf` or `.glb`)
+/// along with the transform placing each one, adding every file it reads to
+/// `files`
+fn load_meshes(path: &Path, files: &mut Vec<PathBuf>) -> Result<Vec<PlacedMesh>, SceneError> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    files.push(path.to_path_buf());
+    match extension.as_deref() {
+        Some("gltf") | Some("glb") => {
+            files.extend(gltf_dependencies(path)?);
+            Ok(load_gltf(path)?
+                .into_iter()
+                .map(|gltf| {
+                    let transform = Some(gltf.transform).filter(|&t| t != Transform::identity());
+                    (gltf.mesh, transform)
+                })
+                .collect())
+        }
+        _ => {
+            let (meshes, dependencies) = load_obj_with_dependencies(path)?;
+            files.extend(dependencies);
+            Ok(meshes
+                .into_iter()
+                .map(|mesh| (Arc::new(mesh), None))
+                .collect())
+        }
+    }
+}
+
+impl SceneFile {
+    /// Loads a TOML scene description file
+    ///
+    /// Mesh and texture paths are resolved relative to the scene file, while the output
+    /// path is left relative to the working directory.
+    ///
+    /// ```no_run
+    /// use std::path::Path;
+    ///
+    /// use raytracer::{Renderer, SceneFile};
+    ///
+    /// let file = SceneFile::load(Path::new("scenes/default.toml")).unwrap();
+    /// let image = Renderer::new(file.settings).render(&file.scene);
+    /// image.write_png(&file.output).unwrap();
+    /// ```
+    pub fn load(path: &Path) -> Result<SceneFile, SceneError> {
+        let source =
+            fs::read_to_string(path).map_err(|err| SceneError::Io(path.to_path_buf(), err))?;
+        let error = |span: Range<usize>, message: String| {
+            let (line, column) = line_and_column(&source, span.start);
+            SceneError::Parse {
+                path: path.to_path_buf(),
+                line,
+                column,
+                message,
+            }
+        };
+
+        // Unknown keys are reported against their table, or just its header,
+        // so they are found within it to point at the key itself
+        let description: SceneDescription = toml::from_str(&source).map_err(|err| {
+            let span = err.span().unwrap_or(0..0);
+            let span = match unknown_key(err.message()) {
+                Some(key) => key_span(&source, span, key),
+                None => span,
+            };
+            error(span, err.message().to_string())
+        })?;
+
+        // Validates the render settings
+        let render = &description.render;
+        for (name, value) in &[
+            ("width", &render.width),
+            ("height", &render.height),
+            ("threads", &render.threads),
+            ("tile_size", &render.tile_size),
+            ("samples", &render.samples),
+            ("pass_samples", &render.pass_samples),
+        ] {
+            if *value.get_ref() == 0 {
+                return Err(error(value.span(), format!("{} must be at least 1", name)));
+            }
+        }
+        for (name, value) in &[
+            ("time_limit", &render.time_limit),
+            ("noise_threshold", &render.noise_threshold),
+            ("preview_interval", &render.preview_interval),
+        ] {
+            if let Some(value) = value {
+                if !(value.get_ref().is_finite() && *value.get_ref() > 0.0) {
+                    return Err(error(
+                        value.span(),
+                        format!("{} must be greater than 0", name),
+                    ));
+                }
+            }
+        }
+        // Times too long to represent are as good as forever
+        let seconds = |value: &Option<Spanned<f32>>| {
+            value
+                .as_ref()
+                .map(|value| Duration::try_from_secs_f32(*value.get_ref()).unwrap_or(Duration::MAX))
+        };
+        let settings = RenderSettings {
+            width: *render.width.get_ref(),
+            height: *render.height.get_ref(),
+            threads: *render.threads.get_ref(),
+            tile_size: *render.tile_size.get_ref(),
+            bounces: render.bounces,
+            samples: *render.samples.get_ref(),
+            // Limits on time or noise only make sense for progressive renders
+            progressive: render.progressive
+                || render.time_limit.is_some()
+                || render.noise_threshold.is_some(),
+            pass_samples: *render.pass_samples.get_ref(),
+            time_limit: seconds(&render.time_limit),
+            noise_threshold: render
+                .noise_threshold
+                .as_ref()
+                .map(|value| *value.get_ref()),
+            preview_interval: seconds(&render.preview_interval),
+            seed: render.seed,
+            filter: match &render.filter {
+                Some(filter) => filter.get_ref().to_filter().map_err(|(key, message)| {
+                    error(key_span(&source, filter.span(), key), message)
+                })?,
+                None => Filter::default(),
+            },
+            integrator: match &render.integrator {
+                Some(name) => Integrator::from_name(name.get_ref()).ok_or_else(|| {
+                    error(
+                        name.span(),
+                        format!("unknown integrator `{}`", name.get_ref()),
+                    )
+                })?,
+                None => Integrator::default(),
+            },
+            output: OutputTransform {
+                exposure: render.exposure,
+                tone_map: match &render.tone_map {
+                    Some(name) => ToneMap::from_name(name.get_ref()).ok_or_else(|| {
+                        error(
+                            name.span(),
+                            format!("unknown tone map `{}`", name.get_ref()),
+                        )
+                    })?,
+                    None => ToneMap::default(),
+                },
+                white_point: match &render.white_point {
+                    Some(white) if *white.get_ref() <= 0.0 => {
+                        return Err(error(
+                            white.span(),
+                            "white point must be greater than 0".to_string(),
+                        ))
+                    }
+                    white => white.as_ref().map(|white| *white.get_ref()),
+                },
+                srgb: render.srgb,
+            },
+        };
+
+        // Creates the camera
+        let camera = description
+            .camera
+            .get_ref()
+            .to_camera(description.camera.span(), &error)?;
+
+        // Creates the textures once so materials can share them
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut loader = TextureLoader {
+            descriptions: &description.textures,
+            directory,
+            textures: HashMap::new(),
+            pending: Vec::new(),
+            files: Vec::new(),
+        };
+        for name in description.textures.keys() {
+            loader.load(name, &error)?;
+        }
+        let textures = loader.textures;
+        let mut files = loader.files;
+
+        // Creates the materials once so objects can share them
+        let mut materials: HashMap<&str, Arc<dyn Material>> = HashMap::new();
+        for (name, material) in &description.materials {
+            let created = material
+                .get_ref()
+                .to_material(&textures)
+                .map_err(|message| error(material.span(), message))?;
+            materials.insert(name, created);
+        }
+
+        // Looks up a material by name, reporting the `material` key of the
+        // object referencing it if it doesn't exist
+        let material = |name: &str, span: Range<usize>| -> Result<Arc<dyn Material>, SceneError> {
+            match materials.get(name) {
+                Some(material) => Ok(Arc::clone(material)),
+                None => Err(error(
+                    key_span(&source, span, "material"),
+                    format!("unknown material `{}`", name),
+                )),
+            }
+        };
+
+        // Checks a size is positive, reporting its key if it isn't
+        let positive = |span: Range<usize>, key: &str, value: f32| {
+            if value.is_finite() && value > 0.0 {
+                Ok(value)
+            } else {
+                let message = format!("{} must be greater than 0", key);
+                Err(error(key_span(&source, span, key), message))
+            }
+        };
+        let at_least_one = |span: Range<usize>, key: &str, value: u32| {
+            if value >= 1 {
+                Ok(value)
+            } else {
+                let message = format!("{} must be at least 1", key);
+                Err(error(key_span(&source, span, key), message))
+            }
+        };
+
+        // Creates the world objects, loading each mesh file once so every
+        // object placing it shares the same triangles. glTF files place
+        // their meshes with their own transforms, which are applied before
+        // the object's.
+        let mut meshes: HashMap<PathBuf, Vec<PlacedMesh>> = HashMap::new();
+        let mut objects: Vec<Box<dyn Intersectable + Sync + Send>> = Vec::new();
+        for object in &description.objects {
+            let shapes: Vec<(Arc<dyn Intersectable + Sync + Send>, Option<Transform>)> =
+                match &object.get_ref().object {
+                    ObjectDescription::Sphere {
+                        center,
+                        radius,
+                        material: name,
+                    } => vec![(
+                        Arc::new(Sphere::new(
+                            vector(*center),
+                            positive(object.span(), "radius", *radius)?,
+                            material(name, object.span())?,
+                        )),
+                        None,
+                    )],
+                    ObjectDescription::Plane {
+                        origin,
+                        normal,
+                        material: name,
+                    } => vec![(
+                        Arc::new(Plane::new(
+                            vector(*origin),
+                            vector(*normal),
+                            material(name, object.span())?,
+                        )),
+                        None,
+                    )],
+                    ObjectDescription::Triangle {
+                        vertices,
+                        material: name,
+                    } => vec![(
+                        Arc::new(Triangle::new(
+                            vector(vertices[0]),
+                            vector(vertices[1]),
+                            vector(vertices[2]),
+                            material(name, object.span())?,
+                        )),
+                        None,
+                    )],
+                    ObjectDescription::Disk {
+                        center,
+                        normal,
+                        radius,
+                        material: name,
+                    } => vec![(
+                        Arc::new(Disk::new(
+                            vector(*center),
+                            vector(*normal),
+                            positive(object.span(), "radius", *radius)?,
+                            material(name, object.span())?,
+                        )),
+                        None,
+                    )],
+                    ObjectDescription::Mesh { path } => {
+                        let path = directory.join(path);
+                        if !meshes.contains_key(&path) {
+                            meshes.insert(path.clone(), load_meshes(&path, &mut files)?);
+                        }
+                        meshes[&path]
+                            .iter()
+                            .map(|(mesh, transform)| {
+                                let mesh = Arc::clone(mesh) as Arc<dyn Intersectable + Sync + Send>;
+                                (mesh, *transform)
+                            })
+                            .collect()
+                    }
+                };
+
+            let transform = object
+                .get_ref()
+                .to_transform()
+                .map_err(|message| error(object.span(), message))?;
+            for (shape, placement) in shapes {
+                let transform = match (placement, transform) {
+                    (Some(placement), Some(transform)) => Some(placement.then(transform)),
+                    (placement, transform) => placement.or(transform),
+                };
+                match transform {
+                    Some(transform) => objects.push(Box::new(Instance::new(shape, transform))),
+                    None => objects.push(Box::new(shape)),
+                }
+            }
+        }
+
+        // Creates the lights
+        let mut lights: Vec<Box<dyn Light>> = Vec::new();
+        for light in &description.lights {
+            match *light.get_ref() {
+                LightDescription::Directional {
+                    direction,
+                    color,
+                    intensity,
+                } => lights.push(Box::new(DirectionalLight::new(
+                    vector(direction),
+                    vector(color),
+                    intensity,
+                ))),
+                LightDescription::Point {
+                    position,
+                    color,
+                    intensity,
+                } => lights.push(Box::new(PointLight::new(
+                    vector(position),
+                    vector(color),
+                    intensity,
+                ))),
+                LightDescription::Spot {
+                    position,
+                    direction,
+                    color,
+                    intensity,
+                    angle,
+                    falloff,
+                } => {
+                    if angle <= 0.0 || angle >= 180.0 {
+                        return Err(error(
+                            key_span(&source, light.span(), "angle"),
+                            "spot light angle must be between 0 and 180 degrees".to_string(),
+                        ));
+                    }
+                    lights.push(Box::new(SpotLight::new(
+                        vector(position),
+                        vector(direction),
+                        vector(color),
+                        intensity,
+                        angle,
+                        falloff,
+                    )));
+                }
+                LightDescription::Rectangle {
+                    position,
+                    u,
+                    v,
+                    color,
+                    intensity,
+                    samples,
+                } => lights.push(Box::new(RectangleLight::new(
+                    vector(position),
+                    vector(u),
+                    vector(v),
+                    vector(color),
+                    intensity,
+                    at_least_one(light.span(), "samples", samples)?,
+                ))),
+                LightDescription::Disk {
+                    position,
+                    normal,
+                    radius,
+                    color,
+                    intensity,
+                    samples,
+                } => lights.push(Box::new(DiskLight::new(
+                    vector(position),
+                    vector(normal),
+                    positive(light.span(), "radius", radius)?,
+                    vector(color),
+                    intensity,
+                    at_least_one(light.span(), "samples", samples)?,
+                ))),
+                LightDescription::Sphere {
+                    position,
+                    radius,
+                    color,
+                    intensity,
+                    samples,
+                } => lights.push(Box::new(SphereLight::new(
+                    vector(position),
+                    positive(light.span(), "radius", radius)?,
+                    vector(color),
+                    intensity,
+                    at_least_one(light.span(), "samples", samples)?,
+                ))),
+            }
+        }
+
+        let scene = Scene::new(
+            camera,
+            World::new(objects),
+            lights,
+            description.ambient,
+            vector(description.background),
+        )
+        .with_fingerprint(fingerprint(&source, files)?);
+
+        Ok(SceneFile {
+            scene,
+            settings,
+            output: PathBuf::from(description.output),
+        })
+    }
+}
+
+/// Hashes the source of a scene file and the bytes of every file it loaded,
+/// in order of their paths since textures are loaded in no particular order
+fn fingerprint(source: &str, mut files: Vec<PathBuf>) -> Result<u64, SceneError> {
+    files.sort();
+    files.dedup();
+    let mut hash = Fnv::new();
+    hash.write(source.as_bytes());
+    for file in &files {
+        let bytes = fs::read(file).map_err(|err| SceneError::Io(file.clone(), err))?;
+        hash.write(&(bytes.len() as u64).to_le_bytes());
+        hash.write(&bytes);
+    }
+    Ok(hash.finish())
+}
+
+/// Gets the key an unknown field error is about
+fn unknown_key(message: &str) -> Option<&str> {
+    let rest = message.strip_prefix("unknown field `")?;
+    rest.find('`').map(|end| &rest[..end])
+}
+
+/// Finds where a key is set within a table, or returns the table's span if
+/// it can't be found
+///
+/// Tables under a `[header]` are only spanned by their header, so they are
+/// searched up to the next header.
+fn key_span(source: &str, table: Range<usize>, key: &str) -> Range<usize> {
+    let text = match source.get(table.clone()) {
+        Some(text) if text.starts_with('[') => {
+            let body = &source[table.end..];
+            let end = body
+                .match_indices('\n')
+                .map(|(i, _)| i + 1)
+                .find(|&i| body[i..].trim_start().starts_with('['))
+                .unwrap_or(body.len());
+            &source[table.start..table.end + end]
+        }
+        Some(text) => text,
+        None => return table,
+    };
+    for (offset, _) in text.match_indices(key) {
+        let before = text[..offset].chars().next_back();
+        let after = text[offset + key.len()..].trim_start_matches([' ', '\t']);
+        let starts_key = before.is_none_or(|c| c.is_whitespace() || c == '{' || c == ',');
+        if starts_key && after.starts_with('=') {
+            let start = table.start + offset;
+            return start..start + key.len();
+        }
+    }
+    table
+}
+
+/// Converts a byte offset into a 1-based line and column
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
@@ -0,0 +1,91 @@
+use std::thread;
+use std::time::Duration;
+
+use crate::filter::Filter;
+use crate::tonemap::OutputTransform;
+
+/// Algorithm used to estimate the light arriving along each camera ray
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Integrator {
+    /// Follows only mirror and glass bounces, shading surfaces with direct
+    /// Phong lighting plus a constant ambient term
+    #[default]
+    Whitted,
+    /// Monte Carlo path tracing, which also gathers indirect light bounced
+    /// between diffuse surfaces
+    Path,
+}
+
+impl Integrator {
+    /// Gets the integrator with the given name
+    pub fn from_name(name: &str) -> Option<Integrator> {
+        match name {
+            "whitted" => Some(Integrator::Whitted),
+            "path" => Some(Integrator::Path),
+            _ => None,
+        }
+    }
+}
+
+/// Options controlling how a scene is rendered
+#[derive(Debug, Copy, Clone)]
+pub struct RenderSettings {
+    /// Output image width in pixels
+    pub width: u32,
+    /// Output image height in pixels
+    pub height: u32,
+    /// Number of threads in the thread pool, where 0 is treated as 1
+    pub threads: u32,
+    /// Width and height of the square tiles threads take turns rendering
+    pub tile_size: u32,
+    /// Maximum number of bounces per ray
+    pub bounces: u32,
+    /// Number of rays traced through each pixel, rounded up to a square
+    /// number so they can be stratified, or the most rays a progressive
+    /// render traces through each pixel
+    pub samples: u32,
+    /// Whether to render in passes, adding samples to the film until the
+    /// sample count, time limit, or noise threshold is reached
+    pub progressive: bool,
+    /// Number of samples each progressive pass adds to every pixel, rounded
+    /// up to a square number
+    pub pass_samples: u32,
+    /// Time after which a progressive render stops starting new passes
+    pub time_limit: Option<Duration>,
+    /// Relative error, as estimated by `Film::noise` for the noisiest
+    /// pixel, at or below which a progressive render stops
+    pub noise_threshold: Option<f32>,
+    /// Time between writing the image so far during a progressive render
+    pub preview_interval: Option<Duration>,
+    /// Filter used to weight each pixel's samples
+    pub filter: Filter,
+    /// Seed for the random numbers used while sampling
+    pub seed: u64,
+    /// Algorithm used to trace each sample
+    pub integrator: Integrator,
+    /// Exposure, tone mapping, and encoding applied when writing 8-bit
+    /// images
+    pub output: OutputTransform,
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            width: 1024,
+            height: 1024,
+            threads: thread::available_parallelism().map_or(4, |n| n.get() as u32),
+            tile_size: 32,
+            bounces: 8,
+            samples: 1,
+            progressive: false,
+            pass_samples: 1,
+            time_limit: None,
+            noise_threshold: None,
+            preview_interval: None,
+            filter: Filter::default(),
+            seed: 0,
+            integrator: Integrator::default(),
+            output: OutputTransform::default(),
+        }
+    }
+}
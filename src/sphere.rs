@@ -1,30 +1,28 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
 use crate::intersectable::Intersectable;
+use crate::material::Material;
 use crate::ray::Ray;
-use crate::rayhit::{RayHit, ReflectionRefractionIndex};
+use crate::rayhit::RayHit;
 use crate::vector::Vector3;
 
-/// A sphere with a position, color, and radius
+/// A sphere with a position, radius, and material
 #[derive(Debug)]
 pub struct Sphere {
     position: Vector3,
-    color: Vector3,
     radius: f32,
-    reflection_and_refraction: ReflectionRefractionIndex,
+    material: Arc<dyn Material>,
 }
 
 impl Sphere {
     /// Creates a new sphere with given geometric data
-    pub fn new(
-        position: Vector3,
-        color: Vector3,
-        radius: f32,
-        reflection_and_refraction: ReflectionRefractionIndex,
-    ) -> Sphere {
+    pub fn new(position: Vector3, radius: f32, material: Arc<dyn Material>) -> Sphere {
         Sphere {
             position,
-            color,
             radius,
-            reflection_and_refraction,
+            material,
         }
     }
 }
@@ -32,7 +30,7 @@ impl Sphere {
 impl Intersectable for Sphere {
     /// Determines whether the given ray has intersected with the sphere
     /// and generates a RayHit
-    fn intersect(&self, ray: Ray) -> Option<RayHit> {
+    fn intersect(&self, ray: Ray) -> Option<RayHit<'_>> {
         let mut t0;
         let mut t1;
 
@@ -40,7 +38,7 @@ impl Intersectable for Sphere {
         let ray_to_sphere = ray.origin() - self.position;
         let a = ray.direction().dot(ray.direction());
         let b = ray.direction().dot(ray_to_sphere) * 2.0;
-        let c = ray_to_sphere.dot(ray_to_sphere) - self.radius;
+        let c = ray_to_sphere.dot(ray_to_sphere) - self.radius * self.radius;
 
         // Solves the quadratic formula
         let discr = b * b - 4.0 * a * c;
@@ -64,9 +62,7 @@ impl Intersectable for Sphere {
 
         // Obtains the closer hit
         if t0 > t1 {
-            let temp = t0;
-            t0 = t1;
-            t1 = temp;
+            std::mem::swap(&mut t0, &mut t1);
         }
 
         // Ensures that the sphere is in front of the ray's origin
@@ -81,12 +77,22 @@ impl Intersectable for Sphere {
         let position = ray.origin() + ray.direction() * t0;
         let normal = (position - self.position).normalize();
 
-        Some(RayHit::new(
-            position,
-            normal,
-            t0,
-            self.color,
-            self.reflection_and_refraction,
-        ))
+        // Maps longitude onto u and latitude onto v, with v increasing
+        // towards the top of the sphere
+        let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * PI);
+        let v = 0.5 + normal.y.clamp(-1.0, 1.0).asin() / PI;
+
+        // Longitude grows east around the y axis, and latitude grows up
+        let tangent = Vector3::new(-normal.z, 0.0, normal.x);
+        let bitangent = Vector3::new(0.0, 1.0, 0.0);
+
+        let hit = RayHit::new(position, normal, (u, v), t0, &*self.material);
+        Some(hit.with_tangents(tangent, bitangent))
+    }
+
+    /// Gets the box around the sphere
+    fn bounds(&self) -> Aabb {
+        let extent = Vector3::new_scalar(self.radius);
+        Aabb::new(self.position - extent, self.position + extent)
     }
 }
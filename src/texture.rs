@@ -0,0 +1,276 @@
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use png::{ColorType, Decoder, DecodingError};
+
+use crate::rayhit::RayHit;
+use crate::tonemap::srgb_decode;
+use crate::vector::Vector3;
+
+/// Texture defines a color that varies over a surface
+pub trait Texture: Debug + Send + Sync {
+    /// Gets the color of the texture at a hit
+    fn evaluate(&self, hit: &RayHit) -> Vector3;
+}
+
+/// A plain color is a texture that is the same everywhere
+impl Texture for Vector3 {
+    fn evaluate(&self, _hit: &RayHit) -> Vector3 {
+        *self
+    }
+}
+
+impl From<Vector3> for Arc<dyn Texture> {
+    fn from(color: Vector3) -> Arc<dyn Texture> {
+        Arc::new(color)
+    }
+}
+
+/// Describes how texture coordinates outside of 0..1 are mapped back onto
+/// an image
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Wrap {
+    /// Tiles the image
+    #[default]
+    Repeat,
+    /// Stretches the image's edge texels outwards
+    Clamp,
+    /// Tiles the image, flipping every other copy so edges line up
+    Mirror,
+}
+
+impl Wrap {
+    /// Gets a wrap mode by its name in a scene file
+    pub fn from_name(name: &str) -> Option<Wrap> {
+        match name {
+            "repeat" => Some(Wrap::Repeat),
+            "clamp" => Some(Wrap::Clamp),
+            "mirror" => Some(Wrap::Mirror),
+            _ => None,
+        }
+    }
+
+    /// Maps a texel index into 0..len
+    fn apply(self, index: i64, len: i64) -> usize {
+        let wrapped = match self {
+            Wrap::Repeat => index.rem_euclid(len),
+            Wrap::Clamp => index.clamp(0, len - 1),
+            Wrap::Mirror => {
+                let index = index.rem_euclid(2 * len);
+                if index >= len {
+                    2 * len - 1 - index
+                } else {
+                    index
+                }
+            }
+        };
+        wrapped as usize
+    }
+}
+
+/// Describes why an image texture could not be loaded
+#[derive(Debug)]
+pub enum TextureError {
+    /// The file could not be read
+    Io(PathBuf, io::Error),
+    /// The file is not a valid PNG image
+    Decode(PathBuf, DecodingError),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            TextureError::Decode(path, err) => write!(f, "{}: {}", path.display(), err),
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureError::Io(_, err) => Some(err),
+            TextureError::Decode(_, err) => Some(err),
+        }
+    }
+}
+
+/// A texture that looks colors up in an image by the hit's texture
+/// coordinates, blending the four nearest texels
+///
+/// Texture coordinates run from (0, 0) at the bottom left of the image to
+/// (1, 1) at the top right. Texels are used as they are stored unless they
+/// are decoded from sRGB with `decode_srgb`, so data such as normal maps
+/// and height maps stays linear.
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    width: u32,
+    height: u32,
+    texels: Vec<Vector3>,
+    wrap: Wrap,
+}
+
+impl ImageTexture {
+    /// Creates a new texture from rows of texels, top to bottom
+    pub fn new(width: u32, height: u32, texels: Vec<Vector3>, wrap: Wrap) -> ImageTexture {
+        assert!(width > 0 && height > 0, "texture must not be empty");
+        assert_eq!(
+            texels.len(),
+            (width * height) as usize,
+            "texture needs exactly one texel per pixel"
+        );
+        ImageTexture {
+            width,
+            height,
+            texels,
+            wrap,
+        }
+    }
+
+    /// Loads a texture from a PNG file
+    ///
+    /// Every color type and bit depth is accepted. Gray images are spread
+    /// over all three channels, and alpha is ignored.
+    pub fn load(path: &Path, wrap: Wrap) -> Result<ImageTexture, TextureError> {
+        let file = File::open(path).map_err(|err| TextureError::Io(path.to_path_buf(), err))?;
+        ImageTexture::read(file, path, wrap)
+    }
+
+    /// Reads a texture from PNG data, such as an image embedded in another
+    /// file, reporting errors against `path`
+    pub fn read<R: Read>(data: R, path: &Path, wrap: Wrap) -> Result<ImageTexture, TextureError> {
+        let decode_error = |err| TextureError::Decode(path.to_path_buf(), err);
+
+        // The decoder expands palettes and low bit depths to 8 bits per channel
+        let (info, mut reader) = Decoder::new(data).read_info().map_err(decode_error)?;
+        let mut data = vec![0; info.buffer_size()];
+        reader.next_frame(&mut data).map_err(decode_error)?;
+
+        let channels = match info.color_type {
+            ColorType::Grayscale => 1,
+            ColorType::GrayscaleAlpha => 2,
+            ColorType::RGB => 3,
+            ColorType::RGBA => 4,
+            ColorType::Indexed => unreachable!("palettes are expanded by the decoder"),
+        };
+        let mut texels = Vec::with_capacity((info.width * info.height) as usize);
+        for row in data.chunks(info.line_size) {
+            for texel in row[..info.width as usize * channels].chunks(channels) {
+                let channel = |i: usize| f32::from(texel[i]) / 255.0;
+                texels.push(if channels < 3 {
+                    Vector3::new_scalar(channel(0))
+                } else {
+                    Vector3::new(channel(0), channel(1), channel(2))
+                });
+            }
+        }
+
+        Ok(ImageTexture::new(info.width, info.height, texels, wrap))
+    }
+
+    /// Converts texels encoded with the sRGB curve, as color images usually
+    /// are, into the linear colors the renderer works in
+    pub fn decode_srgb(mut self) -> ImageTexture {
+        for texel in &mut self.texels {
+            *texel = Vector3::new(
+                srgb_decode(texel.x),
+                srgb_decode(texel.y),
+                srgb_decode(texel.z),
+            );
+        }
+        self
+    }
+
+    /// Multiplies every texel by a color
+    pub fn tint(mut self, color: Vector3) -> ImageTexture {
+        for texel in &mut self.texels {
+            *texel = Vector3::new(texel.x * color.x, texel.y * color.y, texel.z * color.z);
+        }
+        self
+    }
+
+    /// Changes how coordinates outside the image are wrapped
+    pub fn with_wrap(mut self, wrap: Wrap) -> ImageTexture {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Gets the width of the image in texels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Gets the height of the image in texels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Gets the texel at the given column and row, wrapping both
+    fn texel(&self, x: i64, y: i64) -> Vector3 {
+        let x = self.wrap.apply(x, i64::from(self.width));
+        let y = self.wrap.apply(y, i64::from(self.height));
+        self.texels[y * self.width as usize + x]
+    }
+
+    /// Gets the bilinearly filtered color at the given texture coordinates
+    pub fn sample(&self, u: f32, v: f32) -> Vector3 {
+        // Texel centers sit half a texel in from their edges, and rows are
+        // stored from the top down
+        let x = u * self.width as f32 - 0.5;
+        let y = (1.0 - v) * self.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = self.texel(x0, y0) * (1.0 - fx) + self.texel(x0 + 1, y0) * fx;
+        let bottom = self.texel(x0, y0 + 1) * (1.0 - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+impl Texture for ImageTexture {
+    fn evaluate(&self, hit: &RayHit) -> Vector3 {
+        let (u, v) = hit.uv();
+        self.sample(u, v)
+    }
+}
+
+/// A texture evaluated at scaled positions and texture coordinates
+///
+/// Scales above 1 repeat the texture's pattern more often, shrinking it,
+/// while scales below 1 stretch it out. Texture coordinates are scaled by
+/// the x and y components.
+#[derive(Debug, Clone)]
+pub struct Scaled {
+    texture: Arc<dyn Texture>,
+    scale: Vector3,
+}
+
+impl Scaled {
+    /// Creates a new scaled copy of a texture
+    pub fn new(texture: impl Into<Arc<dyn Texture>>, scale: Vector3) -> Scaled {
+        Scaled {
+            texture: texture.into(),
+            scale,
+        }
+    }
+}
+
+impl Texture for Scaled {
+    fn evaluate(&self, hit: &RayHit) -> Vector3 {
+        let (u, v) = hit.uv();
+        let scaled = RayHit::new(
+            hit.position() * self.scale,
+            hit.normal(),
+            (u * self.scale.x, v * self.scale.y),
+            hit.distance(),
+            hit.material(),
+        )
+        .with_tangents(hit.tangent(), hit.bitangent());
+        self.texture.evaluate(&scaled)
+    }
+}
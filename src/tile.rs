@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+/// A rectangle of pixels rendered as one unit of work
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Tile {
+    /// Creates a tile whose top left pixel is at the given column and row
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Tile {
+        Tile {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Splits a `width` by `height` image into tiles of `size` pixels
+    /// square, in rows from the top left
+    ///
+    /// Tiles along the right and bottom edges are cut short to fit the
+    /// image rather than leaving slivers of their own.
+    pub fn grid(width: u32, height: u32, size: u32) -> Vec<Tile> {
+        let size = size.max(1);
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(size as usize) {
+            for x in (0..width).step_by(size as usize) {
+                tiles.push(Tile::new(x, y, size.min(width - x), size.min(height - y)));
+            }
+        }
+        tiles
+    }
+
+    /// Gets the column of the tile's left edge
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    /// Gets the row of the tile's top edge
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// Gets the width of the tile in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Gets the height of the tile in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Gets the number of pixels in the tile
+    pub fn len(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+
+    /// Checks whether the tile covers no pixels
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the column and row of every pixel in the tile, in rows
+    /// from the top left
+    pub fn pixels(&self) -> impl Iterator<Item = (u32, u32)> {
+        let tile = *self;
+        (tile.y..tile.y + tile.height)
+            .flat_map(move |y| (tile.x..tile.x + tile.width).map(move |x| (x, y)))
+    }
+}
+
+/// How long a tile took to render, and which thread rendered it
+#[derive(Debug, Copy, Clone)]
+pub struct TileTime {
+    tile: Tile,
+    thread: u32,
+    duration: Duration,
+}
+
+impl TileTime {
+    /// Creates a record of a rendered tile
+    pub fn new(tile: Tile, thread: u32, duration: Duration) -> TileTime {
+        TileTime {
+            tile,
+            thread,
+            duration,
+        }
+    }
+
+    /// Gets the tile that was rendered
+    pub fn tile(&self) -> Tile {
+        self.tile
+    }
+
+    /// Gets the index of the thread that rendered the tile
+    pub fn thread(&self) -> u32 {
+        self.thread
+    }
+
+    /// Gets how long the tile took to render
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
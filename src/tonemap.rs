@@ -0,0 +1,211 @@
+use crate::vector::Vector3;
+
+/// Curve compressing the film's unbounded colors into the 0..1 range of an
+/// 8-bit image
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ToneMap {
+    /// Clips each channel at 1, blowing out anything brighter
+    #[default]
+    Clip,
+    /// Reinhard's curve on luminance, which approaches white but never
+    /// reaches it
+    Reinhard,
+    /// Reinhard's curve stretched so the white point maps to exactly 1
+    ExtendedReinhard,
+    /// John Hable's filmic curve from Uncharted 2, with a toe that deepens
+    /// shadows and a long shoulder for highlights
+    Hable,
+    /// Stephen Hill's fit of the ACES reference and output transforms
+    Aces,
+    /// Troy Sobotka's AgX, which desaturates bright colors towards white
+    /// instead of skewing their hue
+    Agx,
+}
+
+impl ToneMap {
+    /// Gets the tone mapper with the given name
+    pub fn from_name(name: &str) -> Option<ToneMap> {
+        match name {
+            "clip" => Some(ToneMap::Clip),
+            "reinhard" => Some(ToneMap::Reinhard),
+            "extended_reinhard" => Some(ToneMap::ExtendedReinhard),
+            "hable" => Some(ToneMap::Hable),
+            "aces" => Some(ToneMap::Aces),
+            "agx" => Some(ToneMap::Agx),
+            _ => None,
+        }
+    }
+}
+
+/// Turns the film's linear colors into display colors for 8-bit images
+///
+/// Colors are scaled by the exposure, compressed by the tone mapper, and
+/// then encoded with the sRGB transfer curve that displays expect.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OutputTransform {
+    /// Brightness adjustment in stops, doubling the light for each one
+    pub exposure: f32,
+    /// Curve mapping bright colors into 0..1
+    pub tone_map: ToneMap,
+    /// Luminance mapped to white by the extended Reinhard curve, before
+    /// exposure, or None to use the brightest pixel
+    pub white_point: Option<f32>,
+    /// Whether to encode colors with the sRGB curve, rather than writing
+    /// them linearly
+    pub srgb: bool,
+}
+
+impl OutputTransform {
+    /// Maps a linear color to a display color in 0..1
+    ///
+    /// `white` is the exposed luminance that the extended Reinhard curve
+    /// maps to white.
+    pub fn apply(&self, color: Vector3, white: f32) -> Vector3 {
+        let color = (color * self.scale()).max(Vector3::origin());
+        let mapped = match self.tone_map {
+            ToneMap::Clip => color,
+            ToneMap::Reinhard => scale_luminance(color, |l| l / (1.0 + l)),
+            ToneMap::ExtendedReinhard => {
+                let white = white.max(1e-6);
+                scale_luminance(color, |l| l * (1.0 + l / (white * white)) / (1.0 + l))
+            }
+            ToneMap::Hable => hable(color),
+            ToneMap::Aces => aces(color),
+            ToneMap::Agx => agx(color),
+        };
+        let mapped = mapped.max(Vector3::origin()).min(Vector3::new_scalar(1.0));
+        if self.srgb {
+            Vector3::new(
+                srgb_encode(mapped.x),
+                srgb_encode(mapped.y),
+                srgb_encode(mapped.z),
+            )
+        } else {
+            mapped
+        }
+    }
+
+    /// Gets the factor colors are multiplied by for the exposure
+    pub fn scale(&self) -> f32 {
+        self.exposure.exp2()
+    }
+}
+
+impl Default for OutputTransform {
+    fn default() -> OutputTransform {
+        OutputTransform {
+            exposure: 0.0,
+            tone_map: ToneMap::default(),
+            white_point: None,
+            srgb: true,
+        }
+    }
+}
+
+/// Calculates the luminance of a linear color with Rec. 709 primaries
+pub fn luminance(color: Vector3) -> f32 {
+    color.dot(Vector3::new(0.2126, 0.7152, 0.0722))
+}
+
+/// Encodes a linear value in 0..1 with the sRGB transfer curve
+pub fn srgb_encode(value: f32) -> f32 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Decodes a value in 0..1 encoded with the sRGB transfer curve
+pub fn srgb_decode(value: f32) -> f32 {
+    if value <= 0.040_45 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Scales a color so its luminance follows a curve, keeping its hue
+fn scale_luminance(color: Vector3, curve: impl Fn(f32) -> f32) -> Vector3 {
+    let luminance = luminance(color);
+    if luminance <= 0.0 {
+        return color;
+    }
+    color * (curve(luminance) / luminance)
+}
+
+/// Multiplies a color by a matrix given by its rows
+fn transform(rows: &[[f32; 3]; 3], color: Vector3) -> Vector3 {
+    let row = |r: &[f32; 3]| r[0] * color.x + r[1] * color.y + r[2] * color.z;
+    Vector3::new(row(&rows[0]), row(&rows[1]), row(&rows[2]))
+}
+
+/// Applies a curve to each channel of a color
+fn per_channel(color: Vector3, curve: impl Fn(f32) -> f32) -> Vector3 {
+    Vector3::new(curve(color.x), curve(color.y), curve(color.z))
+}
+
+fn hable(color: Vector3) -> Vector3 {
+    // Shoulder strength, linear strength, linear angle, toe strength, toe
+    // numerator, and toe denominator
+    const A: f32 = 0.15;
+    const B: f32 = 0.50;
+    const C: f32 = 0.10;
+    const D: f32 = 0.20;
+    const E: f32 = 0.02;
+    const F: f32 = 0.30;
+    const WHITE: f32 = 11.2;
+    const EXPOSURE_BIAS: f32 = 2.0;
+
+    let curve = |x: f32| ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+    per_channel(color, |x| curve(x * EXPOSURE_BIAS) / curve(WHITE))
+}
+
+fn aces(color: Vector3) -> Vector3 {
+    // sRGB into the ACES working space, with the reference transform's
+    // exposure folded in, and back again
+    const INPUT: [[f32; 3]; 3] = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    const OUTPUT: [[f32; 3]; 3] = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+
+    let fitted = per_channel(transform(&INPUT, color), |v| {
+        (v * (v + 0.024_578_6) - 0.000_090_537) / (v * (0.983_729 * v + 0.432_951) + 0.238_081)
+    });
+    transform(&OUTPUT, fitted)
+}
+
+fn agx(color: Vector3) -> Vector3 {
+    // Insets the primaries so bright saturated colors bleed into the other
+    // channels, and outsets them again afterwards
+    const INSET: [[f32; 3]; 3] = [
+        [0.842_479, 0.078_434, 0.079_224],
+        [0.042_328, 0.878_469, 0.079_166],
+        [0.042_376, 0.078_434, 0.879_143],
+    ];
+    const OUTSET: [[f32; 3]; 3] = [
+        [1.196_879, -0.098_021, -0.099_030],
+        [-0.052_897, 1.151_903, -0.098_961],
+        [-0.052_972, -0.098_043, 1.151_074],
+    ];
+    const MIN_EV: f32 = -12.473_93;
+    const MAX_EV: f32 = 4.026_069;
+
+    // Encodes the range of stops as 0..1 and applies the sigmoid contrast
+    // curve, which gives display encoded values
+    let inset = transform(&INSET, color);
+    let encoded = per_channel(inset, |v| {
+        let x = (v.max(1e-10).log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x
+            - 0.00232
+    });
+    per_channel(transform(&OUTSET, encoded), |v| v.max(0.0).powf(2.2))
+}
@@ -1,258 +1,275 @@
-use crate::camera::Camera;
-use crate::intersectable::Intersectable;
-use crate::pixel::Pixel;
+use std::f32::consts::PI;
+
+use crate::film::FilmPixel;
+use crate::filter::FilterSampler;
 use crate::ray::Ray;
 use crate::rayhit::RayHit;
+use crate::rng::Rng;
+use crate::scene::Scene;
+use crate::settings::{Integrator, RenderSettings};
+use crate::tile::Tile;
 use crate::vector::Vector3;
 
-/// Number of maximum bounces per ray
-const BOUNCES: u32 = 8;
-
-/// Calculates a reflection vector given a source vector and normal vector
-fn reflect(i: Vector3, n: Vector3) -> Vector3 {
-    i - (n * (2.0 * n.dot(i)))
-}
+/// Number of bounces a path makes before Russian roulette may end it
+const ROULETTE_DEPTH: u32 = 3;
 
-/// Calculates a refraction vector given a source vector, normal vector, and
-/// index of refraction
-fn refract(i: Vector3, n: Vector3, ior: f32) -> Vector3 {
-    let mut cosi = clamp(i.dot(n), -1.0, 1.0);
-    let mut etai = 1.0;
-    let mut etat = ior;
-    let mut normal = n;
+/// Highest probability of a path surviving Russian roulette, so paths
+/// through bright surfaces still end eventually
+const MAX_SURVIVAL: f32 = 0.95;
 
-    if cosi < 0.0 {
-        cosi = -cosi;
+/// Offsets a point slightly off the surface on the side a new ray leaves
+/// towards, so the ray doesn't hit the surface it starts on
+///
+/// The geometric normal is used, since a bent shading normal can point
+/// into the surface.
+fn offset_origin(hit: &RayHit, direction: Vector3) -> Vector3 {
+    let hit_bias = hit.geometric_normal() * 0.001;
+    if direction.dot(hit.geometric_normal()) > 0.0 {
+        hit.position() + hit_bias
     } else {
-        let temp = etai;
-        etai = etat;
-        etat = temp;
-        normal = -normal;
+        hit.position() - hit_bias
     }
-    let eta = etai / etat;
-    let k = 1.0 - eta * eta * (1.0 - cosi * cosi);
-    let mut out = Vector3::origin();
-    if k >= 0.0 {
-        out = (i * eta) + (normal * (eta * cosi - k.sqrt()));
-    }
-    out
-}
-
-/// Clamps a float between two given floats
-fn clamp(input: f32, min: f32, max: f32) -> f32 {
-    input.max(min).min(max)
 }
 
-/// Calculates Fresnel reflective transmittance
-fn fresnel(i: Vector3, n: Vector3, ior: f32) -> f32 {
-    let mut cosi = clamp(i.dot(n), -1.0, 1.0);
-    let mut etai = 1.0;
-    let mut etat = ior;
-    let kr;
-    if cosi > 0.0 {
-        etai = ior;
-        etat = 1.0;
-    }
-    let sint = etai / etat * 0.0f32.max(1.0 - cosi * cosi).sqrt();
-    if sint >= 1.0 {
-        kr = 1.0;
+/// Gets the surface normal flipped to face back along the ray
+fn facing_normal(hit: &RayHit, ray: Ray) -> Vector3 {
+    if ray.direction().dot(hit.normal()) < 0.0 {
+        hit.normal()
     } else {
-        let cost = 0.0f32.max(1.0 - sint * sint).sqrt();
-        cosi = cosi.abs();
-        let rs = ((etat * cosi) - (etai * cost)) / ((etat * cosi) + (etai * cost));
-        let rp = ((etai * cosi) - (etat * cost)) / ((etai * cosi) + (etat * cost));
-        kr = (rs * rs + rp * rp) / 2.0;
+        -hit.normal()
     }
-    kr
 }
 
-/// Traces a ray through the world
-fn trace(depth: u32, ray: Ray, world: &Vec<Box<Intersectable + Sync + Send>>) -> Vector3 {
-    // Ambient light strength
-    let ambient_strength = 0.1;
-
-    // Light direction vector and color
-    let light_dir = (-Vector3::new(-1.0, -1.0, 1.0)).normalize();
-    let light_color = Vector3::new_scalar(1.0);
-
-    // Specular strength of objects
-    let specular_strength = 0.5;
-
-    let mut closest_raycast: Option<RayHit> = None;
-    for object in world {
-        let raycast = object.intersect(ray);
-
-        // Determines the new closest intersection
-        closest_raycast = match raycast {
-            Some(hit) => match &closest_raycast {
-                Some(closest_hit) => {
-                    if hit.distance() < closest_hit.distance() {
-                        Some(hit)
-                    } else {
-                        closest_raycast
-                    }
-                }
-                None => Some(hit),
-            },
-            None => closest_raycast,
-        };
-    }
+/// Traces a ray through the scene, bouncing at most `bounces` times
+///
+/// Light strengths follow the convention that a white diffuse surface facing
+/// a light head-on reflects the light's full strength, so light samples are
+/// scaled by π to cancel the 1/π of a Lambertian BSDF.
+fn trace(depth: u32, bounces: u32, ray: Ray, scene: &Scene, rng: &mut Rng) -> Vector3 {
+    let world = scene.world();
 
     // Checks if anything was hit
-    if closest_raycast.is_none() {
-        // Background color
-        return Vector3::new(0.529, 0.808, 0.98);
-    } else {
-        let closest_hit = closest_raycast.unwrap();
-        let hit_bias = closest_hit.normal() * 0.001;
+    let closest_hit = match world.intersect(ray) {
+        Some(hit) => hit.material().shading_hit(&hit),
+        None => return scene.background(),
+    };
+    let material = closest_hit.material();
+    let outgoing = -ray.direction();
+    let normal = facing_normal(&closest_hit, ray);
+    let shadow_origin = offset_origin(&closest_hit, normal);
 
-        // Calculates shadow ray to see if we're in view of the light source
-        let shadow_origin = closest_hit.position() + hit_bias;
-        let shadow_ray = Ray::new(shadow_origin, light_dir);
+    // Calculates emitted light and ambient light, treating the ambient
+    // light as if it arrived along the normal
+    let ambient = material.evaluate(&closest_hit, outgoing, normal) * (PI * scene.ambient());
 
-        let mut closest_shadow_raycast: Option<RayHit> = None;
-        for object in world {
-            let shadow_raycast = object.intersect(shadow_ray);
+    // Final output color
+    let mut out_float = material.emission(&closest_hit, outgoing) + ambient;
 
-            // Determines the new closest intersection
-            closest_shadow_raycast = match shadow_raycast {
-                Some(shadow_hit) => match &closest_shadow_raycast {
-                    Some(closest_shadow_hit) => {
-                        if shadow_hit.distance() < closest_shadow_hit.distance() {
-                            Some(shadow_hit)
-                        } else {
-                            closest_shadow_raycast
-                        }
-                    }
-                    None => Some(shadow_hit),
-                },
-                None => closest_shadow_raycast,
+    // Adds the direct lighting of every light in view
+    for light in scene.lights() {
+        // Spreads the light's shadow rays over a grid of strata so area
+        // lights are covered evenly
+        let strata = strata(light.samples());
+        let mut light_float = Vector3::origin();
+        for i in 0..strata * strata {
+            let u = ((i % strata) as f32 + rng.next_f32()) / strata as f32;
+            let v = ((i / strata) as f32 + rng.next_f32()) / strata as f32;
+            let sample = match light.sample(shadow_origin, u, v) {
+                Some(sample) => sample,
+                None => continue,
             };
-        }
+            let cos_theta = normal.dot(sample.direction);
+            if cos_theta <= 0.0 {
+                continue;
+            }
 
-        // Final output color
-        let mut out_float;
+            // Calculates shadow ray to see if we're in view of the light source
+            let shadow_ray = Ray::new(shadow_origin, sample.direction);
+            let blocked = world
+                .intersect(shadow_ray)
+                .is_some_and(|shadow_hit| shadow_hit.distance() < sample.distance);
+            if blocked {
+                continue;
+            }
 
-        // Gets hit information and calculates ambient light
-        let color = closest_hit.color();
-        let normal = closest_hit.normal();
-        let ambient = color * ambient_strength;
+            let bsdf = material.evaluate(&closest_hit, outgoing, sample.direction);
+            light_float = light_float + bsdf * sample.radiance * (PI * cos_theta);
+        }
+        out_float = out_float + light_float * (1.0 / (strata * strata) as f32);
+    }
 
-        // Checks if we're in view of the light source
-        if closest_shadow_raycast.is_some() {
-            // View of light blocked
-            out_float = ambient;
-        } else {
-            // Calculates diffuse and specular lighting
-            let halfway_dir = (light_dir - ray.direction()).normalize();
-            let diffuse = color * normal.dot(light_dir).max(0.0) * light_color;
-            let specular =
-                light_color * specular_strength * normal.dot(halfway_dir).max(0.0).powf(64.0);
-            out_float = diffuse + specular + ambient;
+    // Follows every perfectly sharp reflection and refraction, as long as
+    // this raycast doesn't exceed our bounce limit
+    if depth < bounces {
+        for lobe in material.specular_lobes(&closest_hit, outgoing) {
+            let lobe_ray = Ray::new(offset_origin(&closest_hit, lobe.direction), lobe.direction);
+            out_float = out_float + lobe.weight * trace(depth + 1, bounces, lobe_ray, scene, rng);
         }
+    }
+    out_float
+}
 
-        // Checks if this raycast exceeds our bounce limit
-        if depth < BOUNCES {
-            // Gets reflect and refract information
-            let reflect_and_refract = closest_hit.reflection_and_refraction_index();
+/// Traces a path through the scene, bouncing at most `bounces` times
+///
+/// Each surface the path hits adds its emitted light and the light reaching
+/// it directly from every light, then the path continues in a direction
+/// sampled from the surface's material. Rays that escape the scene pick up
+/// the background as an environment light.
+fn trace_path(bounces: u32, ray: Ray, scene: &Scene, rng: &mut Rng) -> Vector3 {
+    let world = scene.world();
+    let mut radiance = Vector3::origin();
+    let mut throughput = Vector3::new_scalar(1.0);
+    let mut ray = ray;
 
-            // Checks if the surface is reflectable
-            if let Some((reflect_index, refract_option)) = reflect_and_refract {
-                // Checks if we're outside the surface
-                let outside = ray.direction().dot(closest_hit.normal()) < 0.0;
+    for depth in 0..=bounces {
+        let hit = match world.intersect(ray) {
+            Some(hit) => hit.material().shading_hit(&hit),
+            None => {
+                radiance = radiance + throughput * scene.background();
+                break;
+            }
+        };
+        let material = hit.material();
+        let outgoing = -ray.direction();
+        radiance = radiance + throughput * material.emission(&hit, outgoing);
+        radiance = radiance + throughput * direct_light(&hit, ray, scene, rng);
 
-                // Calculates the reflection vector and traces it
-                let reflect_origin = if outside {
-                    closest_hit.position() + hit_bias
-                } else {
-                    closest_hit.position() - hit_bias
-                };
-                let reflect_ray =
-                    Ray::new(reflect_origin, reflect(ray.direction(), normal).normalize());
-                let reflection_color = trace(depth + 1, reflect_ray, world);
+        // Chooses which way the path continues
+        let (lobe, u, v) = (rng.next_f32(), rng.next_f32(), rng.next_f32());
+        let sample = match material.sample(&hit, outgoing, lobe, u, v) {
+            Some(sample) => sample,
+            None => break,
+        };
+        throughput = throughput * sample.weight;
+        ray = Ray::new(offset_origin(&hit, sample.direction), sample.direction);
 
-                // Checks if the surface is refractable
-                if let Some(refract_index) = refract_option {
-                    // Calculates the reflective transmittance
-                    let kr = fresnel(ray.direction(), normal, refract_index);
-                    let mut refraction_color = Vector3::origin();
+        // Randomly ends dim paths, boosting the survivors to stay unbiased
+        if depth >= ROULETTE_DEPTH {
+            let survival = throughput
+                .x
+                .max(throughput.y)
+                .max(throughput.z)
+                .min(MAX_SURVIVAL);
+            if rng.next_f32() >= survival {
+                break;
+            }
+            throughput = throughput * (1.0 / survival);
+        }
+    }
+    radiance
+}
 
-                    // Checks if the surface has total internal reflection
-                    if kr < 1.0 {
-                        // Calculates the refraction vector and traces it
-                        let refract_origin = if outside {
-                            closest_hit.position() - hit_bias
-                        } else {
-                            closest_hit.position() + hit_bias
-                        };
-                        let refract_ray = Ray::new(
-                            refract_origin,
-                            refract(ray.direction(), normal, refract_index).normalize(),
-                        );
-                        refraction_color = trace(depth + 1, refract_ray, world);
-                    }
+/// Estimates the light reflected along the ray from every light shining
+/// directly on the hit, tracing one shadow ray towards each (next-event
+/// estimation)
+///
+/// Light samples are scaled by π to follow the same convention as the
+/// Whitted integrator.
+fn direct_light(hit: &RayHit, ray: Ray, scene: &Scene, rng: &mut Rng) -> Vector3 {
+    let material = hit.material();
+    let outgoing = -ray.direction();
+    let normal = facing_normal(hit, ray);
+    let point = offset_origin(hit, normal);
 
-                    // Adds the reflection and refraction color information
-                    out_float = out_float + reflection_color * kr + refraction_color * (1.0 - kr);
-                } else {
-                    // Adds the reflection color information
-                    out_float = out_float + reflection_color * reflect_index;
-                }
-            }
+    let mut total = Vector3::origin();
+    for light in scene.lights() {
+        let sample = match light.sample(point, rng.next_f32(), rng.next_f32()) {
+            Some(sample) => sample,
+            None => continue,
+        };
+        let cos_theta = normal.dot(sample.direction);
+        if cos_theta <= 0.0 {
+            continue;
+        }
+
+        // Skips the shadow ray when the material reflects none of the light
+        let bsdf = material.evaluate(hit, outgoing, sample.direction);
+        if bsdf.x <= 0.0 && bsdf.y <= 0.0 && bsdf.z <= 0.0 {
+            continue;
+        }
+
+        let shadow_ray = Ray::new(point, sample.direction);
+        let blocked = scene
+            .world()
+            .intersect(shadow_ray)
+            .is_some_and(|shadow_hit| shadow_hit.distance() < sample.distance);
+        if !blocked {
+            total = total + bsdf * sample.radiance * (PI * cos_theta);
         }
-        out_float
     }
+    total
+}
+
+/// Gets the number of strata along each side of the grid that `samples`
+/// are spread over, rounding the sample count up to a square number
+pub fn strata(samples: u32) -> u32 {
+    (samples as f32).sqrt().ceil().max(1.0) as u32
 }
 
-/// Traces a given pixel of the viewport
+/// Traces a given pixel of the viewport for one pass of `samples` samples,
+/// gathering its filtered samples
 fn trace_pixel(
     x: u32,
     y: u32,
-    width: u32,
-    height: u32,
-    camera: &Camera,
-    world: &Vec<Box<Intersectable + Sync + Send>>,
-) -> Pixel {
-    // Calculates viewport information
-    let aspect = width as f32 / height as f32;
-    let cam_right = camera.up().cross(camera.direction().normalize());
-    let fov_radians = std::f32::consts::PI * (camera.fov() / 2.0) / 180.0;
-    let half_width = fov_radians.tan();
-    let half_height = (1.0 / aspect as f32) * half_width;
-    let camera_width = half_width * 2.0;
-    let camera_height = half_height * 2.0;
-    let pixel_width = camera_width / (width - 1) as f32;
-    let pixel_height = camera_height / (height - 1) as f32;
+    pass: u32,
+    samples: u32,
+    settings: &RenderSettings,
+    sampler: &FilterSampler,
+    scene: &Scene,
+) -> FilmPixel {
+    let (width, height) = (settings.width, settings.height);
+    let camera = scene.camera();
 
-    // Calculates x and y vector offsets
-    let x_vec = cam_right * (x as f32 * pixel_width - half_width);
-    let y_vec = -camera.up() * (y as f32 * pixel_height - half_height);
+    // Each pixel draws from its own random sequence in every pass
+    let pixels = u64::from(width) * u64::from(height);
+    let stream = u64::from(pass) * pixels + u64::from(y * width + x);
+    let mut rng = Rng::new(settings.seed, stream);
 
-    // Generates pixel ray
-    let ray = Ray::new(
-        camera.position(),
-        (camera.direction() + x_vec + y_vec).normalize(),
-    );
+    // Spreads the samples over a grid of strata, which the sampler maps
+    // onto the filter's footprint
+    let strata = strata(samples);
 
-    // Traces the ray, converts the color from 0..1 to 0..256, and returns it
-    let mut color = trace(0, ray, world) * 255.0;
-    color.x = color.x.min(255.0);
-    color.y = color.y.min(255.0);
-    color.z = color.z.min(255.0);
-    Pixel::new(color.x as u8, color.y as u8, color.z as u8, 255)
-}
+    let mut film_pixel = FilmPixel::new();
+    for i in 0..strata * strata {
+        // A single sample goes through the pixel center, while multiple
+        // samples, and every sample of a progressive render, are jittered
+        // inside their stratum
+        let (dx, dy, weight) = if strata > 1 || settings.progressive {
+            let sx = ((i % strata) as f32 + rng.next_f32()) / strata as f32;
+            let sy = ((i / strata) as f32 + rng.next_f32()) / strata as f32;
+            sampler.sample(sx, sy)
+        } else {
+            (0.0, 0.0, 1.0)
+        };
 
-/// Traces a given chunk of pixels
-pub fn trace_chunk(
-    chunk: &mut [Pixel],
-    start: u32,
-    width: u32,
-    height: u32,
-    camera: &Camera,
-    world: &Vec<Box<Intersectable + Sync + Send>>,
-) {
-    for i in start..(start + chunk.len() as u32) {
-        chunk[(i - start) as usize] =
-            trace_pixel(i % width, i / width, width, height, camera, world);
+        let (px, py) = (x as f32 + dx, y as f32 + dy);
+        let ray = if camera.aperture() > 0.0 {
+            camera.lens_ray(px, py, width, height, rng.next_f32(), rng.next_f32())
+        } else {
+            camera.ray(px, py, width, height)
+        };
+        // Points outside a fisheye's image circle stay black
+        let color = match (ray, settings.integrator) {
+            (Some(ray), Integrator::Whitted) => trace(0, settings.bounces, ray, scene, &mut rng),
+            (Some(ray), Integrator::Path) => trace_path(settings.bounces, ray, scene, &mut rng),
+            (None, _) => Vector3::origin(),
+        };
+        film_pixel.add_sample(color, weight);
     }
+    film_pixel
+}
+
+/// Traces every pixel of a tile for one pass of `samples` samples, in rows
+/// from the top left
+pub fn trace_tile(
+    tile: Tile,
+    pass: u32,
+    samples: u32,
+    settings: &RenderSettings,
+    sampler: &FilterSampler,
+    scene: &Scene,
+) -> Vec<FilmPixel> {
+    tile.pixels()
+        .map(|(x, y)| trace_pixel(x, y, pass, samples, settings, sampler, scene))
+        .collect()
 }
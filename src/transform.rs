@@ -0,0 +1,297 @@
+use std::ops::Mul;
+
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::vector::Vector3;
+
+/// A 4x4 matrix acting on points and vectors in homogeneous coordinates
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Matrix4 {
+    rows: [[f32; 4]; 4],
+}
+
+impl Matrix4 {
+    /// Creates a matrix from its rows
+    pub fn new(rows: [[f32; 4]; 4]) -> Matrix4 {
+        Matrix4 { rows }
+    }
+
+    /// Creates the matrix that leaves everything where it is
+    pub fn identity() -> Matrix4 {
+        Matrix4::scaling(Vector3::new_scalar(1.0))
+    }
+
+    /// Creates a matrix that moves points by `offset`
+    pub fn translation(offset: Vector3) -> Matrix4 {
+        Matrix4::new([
+            [1.0, 0.0, 0.0, offset.x],
+            [0.0, 1.0, 0.0, offset.y],
+            [0.0, 0.0, 1.0, offset.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Creates a matrix that scales each axis about the origin
+    pub fn scaling(scale: Vector3) -> Matrix4 {
+        Matrix4::new([
+            [scale.x, 0.0, 0.0, 0.0],
+            [0.0, scale.y, 0.0, 0.0],
+            [0.0, 0.0, scale.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Creates a matrix that rotates `degrees` about `axis`, turning
+    /// counter-clockwise when looking back along the axis
+    pub fn rotation(axis: Vector3, degrees: f32) -> Matrix4 {
+        let axis = axis.normalize();
+        let (x, y, z) = (axis.x, axis.y, axis.z);
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+        Matrix4::new([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Gets the rows of the matrix
+    pub fn rows(&self) -> [[f32; 4]; 4] {
+        self.rows
+    }
+
+    /// Swaps the rows and columns of the matrix
+    pub fn transpose(&self) -> Matrix4 {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.rows[j][i];
+            }
+        }
+        Matrix4::new(rows)
+    }
+
+    /// Calculates the inverse of the matrix, or returns None if the matrix
+    /// flattens space and can't be undone
+    pub fn inverse(&self) -> Option<Matrix4> {
+        // Gauss-Jordan elimination with partial pivoting, in double
+        // precision so long chains of transforms stay accurate
+        let mut a = [[0.0f64; 8]; 4];
+        for (i, row) in a.iter_mut().enumerate() {
+            for (value, &entry) in row.iter_mut().zip(&self.rows[i]) {
+                *value = f64::from(entry);
+            }
+            row[4 + i] = 1.0;
+        }
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            let scale = 1.0 / a[column][column];
+            for value in a[column].iter_mut() {
+                *value *= scale;
+            }
+            for row in 0..4 {
+                if row != column {
+                    let factor = a[row][column];
+                    let pivot_row = a[column];
+                    for (value, pivot_value) in a[row].iter_mut().zip(&pivot_row) {
+                        *value -= factor * pivot_value;
+                    }
+                }
+            }
+        }
+
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = a[i][4 + j] as f32;
+            }
+        }
+        Some(Matrix4::new(rows))
+    }
+
+    /// Transforms a point, which is moved by translations
+    pub fn transform_point(&self, point: Vector3) -> Vector3 {
+        let r = &self.rows;
+        let x = r[0][0] * point.x + r[0][1] * point.y + r[0][2] * point.z + r[0][3];
+        let y = r[1][0] * point.x + r[1][1] * point.y + r[1][2] * point.z + r[1][3];
+        let z = r[2][0] * point.x + r[2][1] * point.y + r[2][2] * point.z + r[2][3];
+        let w = r[3][0] * point.x + r[3][1] * point.y + r[3][2] * point.z + r[3][3];
+        if w == 1.0 {
+            Vector3::new(x, y, z)
+        } else {
+            Vector3::new(x / w, y / w, z / w)
+        }
+    }
+
+    /// Transforms a direction, which is not moved by translations
+    pub fn transform_vector(&self, vector: Vector3) -> Vector3 {
+        let r = &self.rows;
+        Vector3::new(
+            r[0][0] * vector.x + r[0][1] * vector.y + r[0][2] * vector.z,
+            r[1][0] * vector.x + r[1][1] * vector.y + r[1][2] * vector.z,
+            r[2][0] * vector.x + r[2][1] * vector.y + r[2][2] * vector.z,
+        )
+    }
+}
+
+impl Default for Matrix4 {
+    fn default() -> Matrix4 {
+        Matrix4::identity()
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    /// Multiplies two matrices, giving the matrix that applies `rhs` first
+    /// and then `self`
+    fn mul(self, rhs: Matrix4) -> Matrix4 {
+        let mut rows = [[0.0; 4]; 4];
+        for (i, row) in rows.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.rows[i][k] * rhs.rows[k][j]).sum();
+            }
+        }
+        Matrix4::new(rows)
+    }
+}
+
+/// An invertible affine transform, kept alongside its inverse so both
+/// directions are cheap
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Transform {
+    matrix: Matrix4,
+    inverse: Matrix4,
+}
+
+impl Transform {
+    /// Creates a transform from a matrix, or returns None if the matrix
+    /// can't be inverted
+    pub fn new(matrix: Matrix4) -> Option<Transform> {
+        let inverse = matrix.inverse()?;
+        Some(Transform { matrix, inverse })
+    }
+
+    /// Creates the transform that leaves everything where it is
+    pub fn identity() -> Transform {
+        Transform::default()
+    }
+
+    /// Creates a transform that moves everything by `offset`
+    pub fn translate(offset: Vector3) -> Transform {
+        Transform {
+            matrix: Matrix4::translation(offset),
+            inverse: Matrix4::translation(-offset),
+        }
+    }
+
+    /// Creates a transform that scales each axis about the origin, or
+    /// returns None if any axis is scaled by 0
+    pub fn scale(scale: Vector3) -> Option<Transform> {
+        if scale.x == 0.0 || scale.y == 0.0 || scale.z == 0.0 {
+            return None;
+        }
+        Some(Transform {
+            matrix: Matrix4::scaling(scale),
+            inverse: Matrix4::scaling(Vector3::new(1.0 / scale.x, 1.0 / scale.y, 1.0 / scale.z)),
+        })
+    }
+
+    /// Creates a transform that rotates `degrees` about `axis` through the
+    /// origin
+    pub fn rotate(axis: Vector3, degrees: f32) -> Transform {
+        let matrix = Matrix4::rotation(axis, degrees);
+        Transform {
+            matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    /// Chains two transforms, giving one that applies this transform first
+    /// and then `next`
+    pub fn then(self, next: Transform) -> Transform {
+        Transform {
+            matrix: next.matrix * self.matrix,
+            inverse: self.inverse * next.inverse,
+        }
+    }
+
+    /// Gets the transform that undoes this one
+    pub fn inverse(self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// Gets the matrix of the transform
+    pub fn matrix(&self) -> Matrix4 {
+        self.matrix
+    }
+
+    /// Transforms a point
+    pub fn point(&self, point: Vector3) -> Vector3 {
+        self.matrix.transform_point(point)
+    }
+
+    /// Transforms a direction, keeping its length scaled along with space
+    pub fn vector(&self, vector: Vector3) -> Vector3 {
+        self.matrix.transform_vector(vector)
+    }
+
+    /// Transforms a surface normal, which has to stay perpendicular to the
+    /// surface under non-uniform scales, and normalizes it
+    pub fn normal(&self, normal: Vector3) -> Vector3 {
+        self.inverse
+            .transpose()
+            .transform_vector(normal)
+            .normalize()
+    }
+
+    /// Transforms a ray, leaving its direction unnormalized so distances
+    /// along it stay the same in both spaces
+    pub fn ray(&self, ray: Ray) -> Ray {
+        Ray::new(self.point(ray.origin()), self.vector(ray.direction()))
+    }
+
+    /// Calculates the box around a transformed box, keeping infinite boxes
+    /// infinite
+    pub fn bounds(&self, bounds: Aabb) -> Aabb {
+        if !bounds.is_finite() {
+            return Aabb::infinite();
+        }
+        let (min, max) = (bounds.min(), bounds.max());
+        (0..8).fold(Aabb::empty(), |transformed, corner| {
+            let pick = |axis: usize| {
+                if corner & (1 << axis) == 0 {
+                    min[axis]
+                } else {
+                    max[axis]
+                }
+            };
+            transformed.grow(self.point(Vector3::new(pick(0), pick(1), pick(2))))
+        })
+    }
+}
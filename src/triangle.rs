@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::intersectable::Intersectable;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::rayhit::RayHit;
+use crate::vector::Vector3;
+
+/// Smallest determinant accepted before a ray is considered parallel to a triangle
+const EPSILON: f32 = 1e-7;
+
+/// A single triangle with three vertices, a material, and a flat normal
+#[derive(Debug)]
+pub struct Triangle {
+    v0: Vector3,
+    v1: Vector3,
+    v2: Vector3,
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    /// Creates a new triangle from three counter-clockwise vertices
+    pub fn new(v0: Vector3, v1: Vector3, v2: Vector3, material: Arc<dyn Material>) -> Triangle {
+        Triangle {
+            v0,
+            v1,
+            v2,
+            material,
+        }
+    }
+}
+
+impl Intersectable for Triangle {
+    /// Determines whether the given ray has intersected with the triangle
+    /// and generates a RayHit
+    fn intersect(&self, ray: Ray) -> Option<RayHit<'_>> {
+        let (t, u, v) = intersect_triangle(ray, self.v0, self.v1, self.v2)?;
+
+        // Calculates ray hit position and the flat face normal
+        let position = ray.origin() + ray.direction() * t;
+        let normal = (self.v1 - self.v0).cross(self.v2 - self.v0).normalize();
+
+        // Uses the barycentric coordinates as texture coordinates, placing
+        // v0 at (0, 0), v1 at (1, 0), and v2 at (0, 1)
+        let hit = RayHit::new(position, normal, (u, v), t, &*self.material);
+        Some(hit.with_tangents(self.v1 - self.v0, self.v2 - self.v0))
+    }
+
+    /// Gets the box around the triangle's vertices
+    fn bounds(&self) -> Aabb {
+        Aabb::empty().grow(self.v0).grow(self.v1).grow(self.v2)
+    }
+}
+
+/// Intersects a ray with the triangle (v0, v1, v2) using the Möller–Trumbore
+/// algorithm, returning the distance and the barycentric coordinates (u, v)
+/// of the hit relative to v1 and v2
+pub fn intersect_triangle(
+    ray: Ray,
+    v0: Vector3,
+    v1: Vector3,
+    v2: Vector3,
+) -> Option<(f32, f32, f32)> {
+    let edge1 = v1 - v0;
+    let edge2 = v2 - v0;
+
+    // Rejects rays parallel to the triangle's plane
+    let p = ray.direction().cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    // Calculates the first barycentric coordinate and checks its bounds
+    let to_origin = ray.origin() - v0;
+    let u = to_origin.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    // Calculates the second barycentric coordinate and checks its bounds
+    let q = to_origin.cross(edge1);
+    let v = ray.direction().dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    // Ensures that the triangle is in front of the ray's origin
+    let t = edge2.dot(q) * inv_det;
+    if t < 0.0 {
+        return None;
+    }
+
+    Some((t, u, v))
+}
@@ -1,5 +1,5 @@
 use std::f32;
-use std::ops::{Add, Mul, Neg, Sub};
+use std::ops::{Add, Index, Mul, Neg, Sub};
 
 /// Vector3 struct holds three 32-bit floats
 #[derive(Debug, Copy, Clone)]
@@ -52,6 +52,38 @@ impl Vector3 {
     pub fn normalize(self) -> Vector3 {
         self * (1.0f32 / self.len())
     }
+
+    /// Takes the componentwise minimum of two vectors
+    pub fn min(self, other: Vector3) -> Vector3 {
+        Vector3 {
+            x: self.x.min(other.x),
+            y: self.y.min(other.y),
+            z: self.z.min(other.z),
+        }
+    }
+
+    /// Takes the componentwise maximum of two vectors
+    pub fn max(self, other: Vector3) -> Vector3 {
+        Vector3 {
+            x: self.x.max(other.x),
+            y: self.y.max(other.y),
+            z: self.z.max(other.z),
+        }
+    }
+}
+
+impl Index<usize> for Vector3 {
+    type Output = f32;
+
+    /// Gets a component by axis, where 0, 1, and 2 are x, y, and z
+    fn index(&self, axis: usize) -> &f32 {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vector3 axis {} out of range", axis),
+        }
+    }
 }
 
 impl Add for Vector3 {
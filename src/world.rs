@@ -0,0 +1,63 @@
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::intersectable::Intersectable;
+use crate::ray::Ray;
+use crate::rayhit::RayHit;
+
+/// The list of objects in a scene, with a bounding volume hierarchy over
+/// every object that has finite bounds
+pub struct World {
+    objects: Vec<Box<dyn Intersectable + Sync + Send>>,
+    bounded: Vec<usize>,
+    unbounded: Vec<usize>,
+    bvh: Bvh,
+}
+
+impl World {
+    /// Creates a world from a list of objects and builds its hierarchy
+    pub fn new(objects: Vec<Box<dyn Intersectable + Sync + Send>>) -> World {
+        let mut bounded = Vec::new();
+        let mut unbounded = Vec::new();
+        let mut bounds: Vec<Aabb> = Vec::new();
+        for (i, object) in objects.iter().enumerate() {
+            let object_bounds = object.bounds();
+            if object_bounds.is_finite() {
+                bounded.push(i);
+                bounds.push(object_bounds);
+            } else {
+                unbounded.push(i);
+            }
+        }
+
+        World {
+            objects,
+            bounded,
+            unbounded,
+            bvh: Bvh::new(&bounds),
+        }
+    }
+
+    /// Finds the closest intersection of the ray with any object
+    pub fn intersect(&self, ray: Ray) -> Option<RayHit<'_>> {
+        let mut closest = self.bvh.closest_hit(
+            ray,
+            |i| self.objects[self.bounded[i]].intersect(ray),
+            |hit| hit.distance(),
+        );
+
+        // Unbounded objects can't be placed in the hierarchy, so they're
+        // tested one at a time
+        for &i in &self.unbounded {
+            if let Some(hit) = self.objects[i].intersect(ray) {
+                if closest
+                    .as_ref()
+                    .is_none_or(|c| hit.distance() < c.distance())
+                {
+                    closest = Some(hit);
+                }
+            }
+        }
+
+        closest
+    }
+}
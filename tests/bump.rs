@@ -0,0 +1,88 @@
+//! Bump mapping tests, which check that flat normal and height maps leave
+//! the normal alone and that slopes tilt it the way they should.
+
+use std::sync::Arc;
+
+use raytracer::bump::{Bump, Bumped};
+use raytracer::material::{Lambertian, Material};
+use raytracer::rayhit::RayHit;
+use raytracer::texture::Texture;
+use raytracer::vector::Vector3;
+
+/// A height map rising along u at the given slope, and flat along v
+#[derive(Debug)]
+struct Slope(f32);
+
+impl Texture for Slope {
+    fn evaluate(&self, hit: &RayHit) -> Vector3 {
+        Vector3::new_scalar(self.0 * hit.uv().0)
+    }
+}
+
+fn assert_same(actual: Vector3, expected: Vector3) {
+    assert!(
+        (actual - expected).len() < 1e-3,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// Gets the shading normal, geometric normal, and tangent of a bumped
+/// material on a surface facing +z, whose texture coordinates grow along x
+/// and y
+fn bend(bump: Bump, strength: f32) -> (Vector3, Vector3, Vector3) {
+    let material: Arc<dyn Material> = Arc::new(Lambertian::new(Vector3::new_scalar(0.5)));
+    let bumped = Bumped::new(material, bump, strength);
+    let hit = RayHit::new(
+        Vector3::new(0.2, 0.3, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+        (0.4, 0.6),
+        1.0,
+        &bumped,
+    )
+    .with_tangents(Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+    let hit = bumped.shading_hit(&hit);
+    (hit.normal(), hit.geometric_normal(), hit.tangent())
+}
+
+#[test]
+fn flat_maps_leave_the_normal_unchanged() {
+    let flat = Arc::new(Vector3::new(0.5, 0.5, 1.0));
+    let (normal, _, _) = bend(Bump::NormalMap(flat), 1.0);
+    assert_same(normal, Vector3::new(0.0, 0.0, 1.0));
+
+    let level = Arc::new(Vector3::new_scalar(0.7));
+    let (normal, _, _) = bend(Bump::HeightMap(level), 5.0);
+    assert_same(normal, Vector3::new(0.0, 0.0, 1.0));
+}
+
+#[test]
+fn normal_maps_tilt_along_the_tangent_frame() {
+    // Red above one half leans towards the tangent, green below one half
+    // leans away from the bitangent
+    let map = Arc::new(Vector3::new(0.75, 0.5, 1.0));
+    let (normal, geometric, _) = bend(Bump::NormalMap(map.clone()), 1.0);
+    assert_same(normal, Vector3::new(0.5, 0.0, 1.0).normalize());
+    assert_same(geometric, Vector3::new(0.0, 0.0, 1.0));
+
+    // Strength scales the tilt but not the part along the normal
+    let (normal, _, _) = bend(Bump::NormalMap(map), 2.0);
+    assert_same(normal, Vector3::new(1.0, 0.0, 1.0).normalize());
+
+    let map = Arc::new(Vector3::new(0.5, 0.25, 1.0));
+    let (normal, _, tangent) = bend(Bump::NormalMap(map), 1.0);
+    assert_same(normal, Vector3::new(0.0, -0.5, 1.0).normalize());
+    // The frame is bent along with the normal
+    assert!(tangent.dot(normal).abs() < 1e-5);
+}
+
+#[test]
+fn height_maps_tilt_away_from_rising_ground() {
+    let (normal, geometric, _) = bend(Bump::HeightMap(Arc::new(Slope(0.2))), 1.0);
+    assert_same(normal, Vector3::new(-0.2, 0.0, 1.0).normalize());
+    assert_same(geometric, Vector3::new(0.0, 0.0, 1.0));
+
+    let (normal, _, _) = bend(Bump::HeightMap(Arc::new(Slope(0.2))), 3.0);
+    assert_same(normal, Vector3::new(-0.6, 0.0, 1.0).normalize());
+}
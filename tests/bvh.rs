@@ -0,0 +1,155 @@
+//! Bounding volume hierarchy tests, which check that traversal finds the
+//! same closest hit as testing every object in turn, for rays that hit and
+//! rays that miss, including rays running along the faces of flat boxes.
+
+use std::sync::Arc;
+
+use raytracer::material::{Lambertian, Material};
+use raytracer::ray::Ray;
+use raytracer::sphere::Sphere;
+use raytracer::triangle::Triangle;
+use raytracer::vector::Vector3;
+use raytracer::world::World;
+use raytracer::Intersectable;
+
+type Objects = Vec<Box<dyn Intersectable + Sync + Send>>;
+
+/// Generates deterministic pseudo-random numbers in 0..1
+struct Lcg(u64);
+
+impl Lcg {
+    fn next_f32(&mut self) -> f32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Gets a random point in a cube of the given size around the origin
+fn point(rng: &mut Lcg, size: f32) -> Vector3 {
+    Vector3::new(
+        rng.next_f32() - 0.5,
+        rng.next_f32() - 0.5,
+        rng.next_f32() - 0.5,
+    ) * size
+}
+
+/// Scatters overlapping spheres and triangles of many sizes through a cube,
+/// so some leaves hold several objects and some bounds nest in others
+fn scatter(seed: u64) -> Objects {
+    let mut rng = Lcg(seed);
+    let material: Arc<dyn Material> = Arc::new(Lambertian::new(Vector3::new_scalar(0.5)));
+    (0..500)
+        .map(|i| {
+            let center = point(&mut rng, 20.0);
+            let size = 0.05 + 2.0 * rng.next_f32().powi(3);
+            let object: Box<dyn Intersectable + Sync + Send> = if i % 2 == 0 {
+                Box::new(Sphere::new(center, size, Arc::clone(&material)))
+            } else {
+                Box::new(Triangle::new(
+                    center + point(&mut rng, size),
+                    center + point(&mut rng, size),
+                    center + point(&mut rng, size),
+                    Arc::clone(&material),
+                ))
+            };
+            object
+        })
+        .collect()
+}
+
+/// Finds the closest hit distance and position by testing every object
+fn linear(objects: &Objects, ray: Ray) -> Option<(f32, Vector3)> {
+    objects
+        .iter()
+        .filter_map(|object| object.intersect(ray))
+        .map(|hit| (hit.distance(), hit.position()))
+        .fold(None, |closest, hit| match closest {
+            Some(closest) if closest.0 <= hit.0 => Some(closest),
+            _ => Some(hit),
+        })
+}
+
+#[test]
+fn traversal_finds_the_same_closest_hit_as_a_linear_scan() {
+    let world = World::new(scatter(5));
+    let objects = scatter(5);
+    let mut rng = Lcg(9);
+
+    let mut hits = 0;
+    let mut misses = 0;
+    for i in 0..4000 {
+        // Rays start inside and outside the cloud, some along the axes
+        let origin = point(&mut rng, 40.0);
+        let direction = match i % 8 {
+            0 => Vector3::new(0.0, 0.0, 1.0),
+            1 => Vector3::new(-1.0, 0.0, 0.0),
+            2 => Vector3::new(0.0, 1.0, 1.0).normalize(),
+            _ => point(&mut rng, 2.0).normalize(),
+        };
+        let ray = Ray::new(origin, direction);
+
+        let expected = linear(&objects, ray);
+        let actual = world
+            .intersect(ray)
+            .map(|hit| (hit.distance(), hit.position()));
+        match (expected, actual) {
+            (Some((expected, position)), Some((actual, hit))) => {
+                hits += 1;
+                assert_eq!(actual, expected, "{:?}", ray);
+                assert!((hit - position).len() < 1e-4, "{:?}", ray);
+            }
+            (None, None) => misses += 1,
+            (expected, actual) => panic!(
+                "expected {:?} but got {:?} for {:?}",
+                expected.map(|e| e.0),
+                actual.map(|a| a.0),
+                ray
+            ),
+        }
+    }
+    assert!(
+        hits > 300 && misses > 300,
+        "{} hits, {} misses",
+        hits,
+        misses
+    );
+}
+
+#[test]
+fn rays_pointing_away_miss_everything() {
+    let world = World::new(scatter(6));
+    let mut rng = Lcg(3);
+    for _ in 0..200 {
+        // Starts outside the cloud's cube and heads further out
+        let direction = point(&mut rng, 2.0).normalize();
+        let origin = direction * 40.0;
+        assert!(world.intersect(Ray::new(origin, direction)).is_none());
+    }
+    assert!(World::new(Vec::new())
+        .intersect(Ray::new(Vector3::origin(), Vector3::new(0.0, 0.0, 1.0)))
+        .is_none());
+}
+
+#[test]
+fn rays_along_the_faces_of_flat_boxes_hit() {
+    // A triangle flat in z, so its box has no depth, hit straight on at its
+    // corners and edges where the ray runs along the faces of the box
+    let material: Arc<dyn Material> = Arc::new(Lambertian::new(Vector3::new_scalar(0.5)));
+    let triangle: Box<dyn Intersectable + Sync + Send> = Box::new(Triangle::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(2.0, 0.0, 0.0),
+        Vector3::new(0.0, 2.0, 0.0),
+        material,
+    ));
+    let world = World::new(vec![triangle]);
+    for &(x, y) in &[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (2.0, 0.0), (0.0, 2.0)] {
+        let ray = Ray::new(Vector3::new(x, y, -1.0), Vector3::new(0.0, 0.0, 1.0));
+        let hit = world.intersect(ray);
+        assert!(hit.is_some(), "missed ({}, {})", x, y);
+    }
+    let outside = Ray::new(Vector3::new(-1e-3, 0.0, -1.0), Vector3::new(0.0, 0.0, 1.0));
+    assert!(world.intersect(outside).is_none());
+}
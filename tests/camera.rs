@@ -0,0 +1,252 @@
+//! Camera tests, which check that the camera's basis stays orthonormal, that
+//! the ray through the middle of the image looks where the camera does for
+//! every projection, and that rays through a lens focus on the focal plane.
+
+use raytracer::camera::Projection;
+use raytracer::vector::Vector3;
+use raytracer::Camera;
+
+/// Image sizes with a pixel exactly at the center
+const SIZES: [(u32, u32); 3] = [(65, 33), (101, 101), (1, 1)];
+
+fn assert_close(a: Vector3, b: Vector3, what: &str) {
+    let difference = (a - b).len();
+    assert!(
+        difference < 1e-5,
+        "{}: {:?} differs from {:?} by {}",
+        what,
+        a,
+        b,
+        difference
+    );
+}
+
+/// Cameras pointing in a spread of directions, built every supported way
+fn cameras() -> Vec<(String, Camera)> {
+    let eye = Vector3::new(1.0, 2.0, -3.0);
+    let up = Vector3::new(0.0, 1.0, 0.0);
+    let mut cameras = Vec::new();
+    for &(pitch, yaw) in &[(0.0, 0.0), (30.0, 0.0), (-45.0, 120.0), (89.0, -60.0)] {
+        cameras.push((
+            format!("pitch {} yaw {}", pitch, yaw),
+            Camera::new(eye, up, 60.0, pitch, yaw),
+        ));
+    }
+    for target in &[
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(5.0, -1.0, 4.0),
+        // Straight down, where `up` is parallel to the view direction
+        Vector3::new(1.0, -8.0, -3.0),
+    ] {
+        cameras.push((
+            format!("look at {:?}", target),
+            Camera::look_at(eye, *target, up, 45.0),
+        ));
+    }
+    for &roll in &[15.0, 90.0, -170.0] {
+        cameras.push((
+            format!("look at with roll {}", roll),
+            Camera::look_at(eye, Vector3::origin(), up, 45.0).with_roll(roll),
+        ));
+    }
+    for &projection in &[
+        Projection::Orthographic { width: 4.0 },
+        Projection::Fisheye,
+        Projection::Equirectangular,
+    ] {
+        cameras.push((
+            format!("{:?} with pitch and yaw", projection),
+            Camera::new(eye, up, 150.0, -20.0, 35.0).with_projection(projection),
+        ));
+    }
+    cameras
+}
+
+#[test]
+fn center_ray_matches_view_direction() {
+    for (name, camera) in cameras() {
+        for &(width, height) in &SIZES {
+            let center_x = (width - 1) as f32 / 2.0;
+            let center_y = (height - 1) as f32 / 2.0;
+            let ray = camera.ray(center_x, center_y, width, height).unwrap();
+            assert_close(ray.origin(), camera.position(), &name);
+            assert_close(ray.direction(), camera.direction(), &name);
+        }
+    }
+}
+
+#[test]
+fn basis_is_orthonormal() {
+    for (name, camera) in cameras() {
+        let (forward, right, up) = (camera.direction(), camera.right(), camera.up());
+        for (axis, length) in &[(forward, 1.0), (right, 1.0), (up, 1.0)] {
+            assert!((axis.len() - length).abs() < 1e-5, "{}: not unit", name);
+        }
+        assert!(forward.dot(right).abs() < 1e-5, "{}: right skews", name);
+        assert!(forward.dot(up).abs() < 1e-5, "{}: up skews", name);
+        assert!(right.dot(up).abs() < 1e-5, "{}: right and up skew", name);
+        // Right is up crossed with forward, the handedness images are drawn in
+        assert_close(up.cross(forward), right, &name);
+    }
+}
+
+#[test]
+fn look_at_faces_target() {
+    let eye = Vector3::new(-2.0, 1.0, 4.0);
+    let target = Vector3::new(3.0, 0.5, -1.0);
+    let camera = Camera::look_at(eye, target, Vector3::new(0.0, 1.0, 0.0), 50.0);
+    assert_close(
+        camera.direction(),
+        (target - eye).normalize(),
+        "look at direction",
+    );
+
+    // The ray through the target's pixel passes through the target
+    let ray = camera.ray(50.0, 25.0, 101, 51).unwrap();
+    let along = (target - eye).dot(ray.direction());
+    assert_close(eye + ray.direction() * along, target, "ray to target");
+}
+
+#[test]
+fn pitch_and_yaw_match_look_at() {
+    // Pitching up 30 degrees after turning right 90 degrees looks along +x
+    // and up
+    let eye = Vector3::origin();
+    let up = Vector3::new(0.0, 1.0, 0.0);
+    let turned = Camera::new(eye, up, 60.0, 30.0, 90.0);
+    let (sin, cos) = 30f32.to_radians().sin_cos();
+    let aimed = Camera::look_at(eye, Vector3::new(cos, sin, 0.0), up, 60.0);
+    assert_close(turned.direction(), aimed.direction(), "direction");
+    assert_close(turned.right(), aimed.right(), "right");
+    assert_close(turned.up(), aimed.up(), "up");
+}
+
+#[test]
+fn roll_turns_the_image() {
+    let camera = Camera::look_at(
+        Vector3::origin(),
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        60.0,
+    );
+    let rolled = camera.with_roll(90.0);
+    assert_close(rolled.direction(), camera.direction(), "rolled direction");
+    assert_close(rolled.up(), camera.right(), "rolled up");
+    assert_close(rolled.right(), -camera.up(), "rolled right");
+
+    // Rolling all the way around changes nothing
+    let around = camera.with_roll(360.0);
+    assert_close(around.right(), camera.right(), "full roll right");
+    assert_close(around.up(), camera.up(), "full roll up");
+}
+
+#[test]
+fn lens_rays_meet_on_focal_plane() {
+    let eye = Vector3::new(0.5, 1.0, -4.0);
+    let target = Vector3::new(0.0, 0.0, 2.0);
+    let focus_distance = (target - eye).len();
+    for &blades in &[0, 3, 6] {
+        let camera = Camera::look_at(eye, target, Vector3::new(0.0, 1.0, 0.0), 60.0)
+            .with_lens(0.25, focus_distance)
+            .with_blades(blades);
+        for &(x, y) in &[(32.0, 16.0), (3.0, 27.5), (60.0, 2.0)] {
+            // Where the pinhole ray crosses the focal plane
+            let pinhole = camera.ray(x, y, 65, 33).unwrap();
+            let t = focus_distance / pinhole.direction().dot(camera.direction());
+            let focus = eye + pinhole.direction() * t;
+
+            for &(u, v) in &[(0.1, 0.9), (0.5, 0.5), (0.99, 0.01), (0.7, 0.3)] {
+                let name = format!("{} blades at ({}, {}) lens ({}, {})", blades, x, y, u, v);
+                let ray = camera.lens_ray(x, y, 65, 33, u, v).unwrap();
+
+                // Rays leave from the lens, which lies across the view
+                let offset = ray.origin() - eye;
+                assert!(offset.len() <= 0.25 + 1e-5, "{}: off the lens", name);
+                assert!(offset.dot(camera.direction()).abs() < 1e-5, "{}", name);
+
+                let along = (focus - ray.origin()).dot(ray.direction());
+                assert_close(ray.origin() + ray.direction() * along, focus, &name);
+            }
+        }
+    }
+}
+
+#[test]
+fn orthographic_rays_are_parallel() {
+    let width = 6.0;
+    let camera = Camera::look_at(
+        Vector3::new(0.0, 2.0, -5.0),
+        Vector3::origin(),
+        Vector3::new(0.0, 1.0, 0.0),
+        60.0,
+    )
+    .with_projection(Projection::Orthographic { width });
+
+    // The left and right edges of the image are the view width apart
+    let left = camera.ray(0.0, 20.0, 81, 41).unwrap();
+    let right = camera.ray(80.0, 20.0, 81, 41).unwrap();
+    assert_close(left.direction(), camera.direction(), "left direction");
+    assert_close(right.direction(), camera.direction(), "right direction");
+    assert_close(
+        right.origin() - left.origin(),
+        camera.right() * width,
+        "view width",
+    );
+}
+
+#[test]
+fn fisheye_angle_grows_with_radius() {
+    let fov = 180.0;
+    let camera = Camera::new(
+        Vector3::origin(),
+        Vector3::new(0.0, 1.0, 0.0),
+        fov,
+        0.0,
+        0.0,
+    )
+    .with_projection(Projection::Fisheye);
+
+    // The image circle touches the top and bottom of a wide image, where
+    // rays are half the field of view from the view direction
+    let top = camera.ray(40.0, 0.0, 81, 41).unwrap();
+    let halfway = camera.ray(40.0, 10.0, 81, 41).unwrap();
+    assert_close(top.direction(), camera.up(), "top of circle");
+    let angle = halfway
+        .direction()
+        .dot(camera.direction())
+        .acos()
+        .to_degrees();
+    assert!(
+        (angle - fov / 4.0).abs() < 1e-3,
+        "halfway is {} degrees",
+        angle
+    );
+
+    // Corners are outside the circle
+    assert!(camera.ray(0.0, 0.0, 81, 41).is_none());
+}
+
+#[test]
+fn equirectangular_covers_every_direction() {
+    let camera = Camera::new(
+        Vector3::origin(),
+        Vector3::new(0.0, 1.0, 0.0),
+        60.0,
+        0.0,
+        0.0,
+    )
+    .with_projection(Projection::Equirectangular);
+    let (width, height) = (360, 180);
+
+    // A quarter of the way across looks left, and three quarters looks right
+    let left = camera.ray(89.5, 89.5, width, height).unwrap();
+    let right = camera.ray(269.5, 89.5, width, height).unwrap();
+    assert_close(left.direction(), -camera.right(), "left");
+    assert_close(right.direction(), camera.right(), "right");
+
+    // The top and bottom edges look straight up and down
+    let top = camera.ray(179.5, -0.5, width, height).unwrap();
+    let bottom = camera.ray(179.5, 179.5, width, height).unwrap();
+    assert_close(top.direction(), camera.up(), "top");
+    assert_close(bottom.direction(), -camera.up(), "bottom");
+}
@@ -0,0 +1,179 @@
+//! Checkpoint tests, which check that a render resumed from a checkpoint
+//! matches one that was never interrupted and that unusable checkpoints,
+//! including those of scenes that have since changed, are turned away.
+
+use std::fs;
+use std::path::Path;
+
+use raytracer::checkpoint::{Checkpoint, CheckpointError};
+use raytracer::renderer::Progress;
+use raytracer::vector::Vector3;
+use raytracer::{Film, RenderSettings, Renderer, Scene, SceneFile};
+
+mod common;
+
+use common::sphere_on_floor as scene;
+
+fn settings(samples: u32) -> RenderSettings {
+    RenderSettings {
+        threads: 3,
+        samples,
+        pass_samples: 4,
+        ..common::progressive_settings()
+    }
+}
+
+/// Renders a few passes and round trips them through a checkpoint
+fn checkpoint(settings: RenderSettings) -> Checkpoint {
+    checkpoint_scene(settings, &scene())
+}
+
+/// Renders a few passes of a scene and round trips them through a
+/// checkpoint
+fn checkpoint_scene(settings: RenderSettings, scene: &Scene) -> Checkpoint {
+    let mut last = Progress::default();
+    let film = Renderer::new(settings).render_progressive(scene, |_, progress| {
+        last = *progress;
+    });
+    let mut bytes = Vec::new();
+    Checkpoint::new(&settings, scene, film, last)
+        .write(&mut bytes)
+        .unwrap();
+    Checkpoint::read(&bytes[..]).unwrap()
+}
+
+fn assert_mismatch(result: Result<Film, CheckpointError>) {
+    match result {
+        Err(CheckpointError::Mismatch) => {}
+        other => panic!("expected a mismatch, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn resumed_render_matches_uninterrupted_render() {
+    let renderer = Renderer::new(settings(20));
+    let reference = renderer.render_progressive(&scene(), |_, _| {});
+
+    let checkpoint = checkpoint(settings(8));
+    assert_eq!(checkpoint.progress().passes(), 2);
+    assert_eq!(checkpoint.progress().samples(), 8);
+    let mut passes = Vec::new();
+    let film = renderer
+        .resume(&scene(), checkpoint, |_, progress| passes.push(*progress))
+        .unwrap();
+
+    let samples: Vec<u32> = passes.iter().map(Progress::samples).collect();
+    assert_eq!(samples, [12, 16, 20]);
+    assert_eq!(passes.last().unwrap().passes(), 5);
+    assert_same(&film, &reference);
+}
+
+#[test]
+fn checkpoint_round_trips_the_film() {
+    let checkpoint = checkpoint(settings(8));
+    let mut bytes = Vec::new();
+    checkpoint.write(&mut bytes).unwrap();
+    let read = Checkpoint::read(&bytes[..]).unwrap();
+
+    assert_same(read.film(), checkpoint.film());
+    assert_eq!(read.progress().elapsed(), checkpoint.progress().elapsed());
+    assert_eq!(read.progress().noise(), checkpoint.progress().noise());
+}
+
+#[test]
+fn mismatched_settings_are_rejected() {
+    let renderer = Renderer::new(RenderSettings {
+        seed: 7,
+        ..settings(20)
+    });
+    assert_mismatch(renderer.resume(&scene(), checkpoint(settings(8)), |_, _| {}));
+}
+
+#[test]
+fn changed_scenes_are_rejected() {
+    let renderer = Renderer::new(settings(20));
+    let changed = scene().with_fingerprint(1);
+    assert_mismatch(renderer.resume(&changed, checkpoint(settings(8)), |_, _| {}));
+}
+
+/// Writes a scene file placing an OBJ mesh whose MTL library colors it
+fn write_scene(directory: &Path, color: &str) {
+    let scene = r#"
+        [render]
+        width = 8
+        height = 8
+
+        [camera]
+        position = [0.0, 0.0, -3.0]
+        up = [0.0, 1.0, 0.0]
+        target = [0.0, 0.0, 0.0]
+        fov = 60.0
+
+        [[lights]]
+        type = "point"
+        position = [0.0, 0.0, -3.0]
+
+        [[objects]]
+        type = "mesh"
+        path = "triangle.obj"
+    "#;
+    let obj = "mtllib triangle.mtl\nv -1 -1 0\nv 1 -1 0\nv 0 1 0\nusemtl paint\nf 1 2 3\n";
+    fs::write(directory.join("scene.toml"), scene).unwrap();
+    fs::write(directory.join("triangle.obj"), obj).unwrap();
+    fs::write(
+        directory.join("triangle.mtl"),
+        format!("newmtl paint\nKd {}\n", color),
+    )
+    .unwrap();
+}
+
+#[test]
+fn scenes_whose_files_changed_are_rejected() {
+    let directory = common::temp_dir("checkpoint-scene-files");
+    let load = || {
+        SceneFile::load(&directory.join("scene.toml"))
+            .unwrap()
+            .scene
+    };
+    write_scene(&directory, "0.8 0.2 0.2");
+    let before = load();
+    assert_eq!(load().fingerprint(), before.fingerprint());
+    let checkpoint = checkpoint_scene(settings(8), &before);
+
+    // Only the material library the mesh loads has changed
+    write_scene(&directory, "0.2 0.2 0.8");
+    let after = load();
+    assert_ne!(after.fingerprint(), before.fingerprint());
+    assert_mismatch(Renderer::new(settings(20)).resume(&after, checkpoint, |_, _| {}));
+}
+
+#[test]
+fn corrupt_checkpoints_are_rejected() {
+    assert!(Checkpoint::read(&b"not a checkpoint at all"[..]).is_err());
+
+    // Cuts a valid checkpoint off partway through its pixels
+    let mut bytes = Vec::new();
+    checkpoint(settings(4)).write(&mut bytes).unwrap();
+    bytes.truncate(bytes.len() / 2);
+    assert!(Checkpoint::read(&bytes[..]).is_err());
+}
+
+fn assert_same(film: &Film, reference: &Film) {
+    let bits = |color: Vector3| [color.x.to_bits(), color.y.to_bits(), color.z.to_bits()];
+    assert_eq!(film.width(), reference.width());
+    assert_eq!(film.height(), reference.height());
+    for y in 0..film.height() {
+        for x in 0..film.width() {
+            let (pixel, expected) = (film.pixel(x, y), reference.pixel(x, y));
+            assert_eq!(bits(film.color(x, y)), bits(reference.color(x, y)));
+            assert_eq!(pixel.samples(), expected.samples(), "({}, {})", x, y);
+            assert_eq!(
+                pixel.relative_error().map(f32::to_bits),
+                expected.relative_error().map(f32::to_bits),
+                "({}, {})",
+                x,
+                y
+            );
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Scenes shared by the integration tests.
+//!
+//! Each test binary uses only some of these, so the rest would otherwise be
+//! reported as dead code.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use raytracer::intersectable::Intersectable;
+use raytracer::light::{Light, SphereLight};
+use raytracer::material::{Lambertian, Material};
+use raytracer::plane::Plane;
+use raytracer::sphere::Sphere;
+use raytracer::vector::Vector3;
+use raytracer::world::World;
+use raytracer::{Camera, Integrator, RenderSettings, Scene};
+
+/// Objects as the world takes them
+pub type Objects = Vec<Box<dyn Intersectable + Sync + Send>>;
+
+/// Creates a camera at `eye` looking along +z with y up
+pub fn camera(eye: Vector3, fov: f32) -> Camera {
+    Camera::new(eye, Vector3::new(0.0, 1.0, 0.0), fov, 0.0, 0.0)
+}
+
+/// Creates a scene without ambient light
+pub fn scene(
+    camera: Camera,
+    objects: Objects,
+    lights: Vec<Box<dyn Light>>,
+    background: Vector3,
+) -> Scene {
+    Scene::new(camera, World::new(objects), lights, 0.0, background)
+}
+
+/// An empty scene, so every pixel is the background color
+pub fn empty(background: Vector3) -> Scene {
+    scene(
+        camera(Vector3::origin(), 60.0),
+        Vec::new(),
+        Vec::new(),
+        background,
+    )
+}
+
+/// A unit sphere at the origin, seen from 3 units away against a uniformly
+/// bright background
+pub fn sphere(material: Arc<dyn Material>, fov: f32, background: Vector3) -> Scene {
+    scene(
+        camera(Vector3::new(0.0, 0.0, -3.0), fov),
+        vec![Box::new(Sphere::new(Vector3::origin(), 1.0, material))],
+        Vec::new(),
+        background,
+    )
+}
+
+/// A sphere on a floor under a small light, whose soft shadows keep the
+/// path tracer noisy for a while
+pub fn sphere_on_floor() -> Scene {
+    let gray = Arc::new(Lambertian::new(Vector3::new_scalar(0.7)));
+    scene(
+        Camera::new(
+            Vector3::new(0.0, 0.5, -3.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            60.0,
+            -10.0,
+            0.0,
+        ),
+        vec![
+            Box::new(Sphere::new(Vector3::origin(), 0.5, gray.clone())),
+            Box::new(Plane::new(
+                Vector3::new(0.0, -0.5, 0.0),
+                Vector3::new(0.0, 1.0, 0.0),
+                gray,
+            )),
+        ],
+        vec![Box::new(SphereLight::new(
+            Vector3::new(1.0, 2.0, -1.0),
+            0.3,
+            Vector3::new_scalar(1.0),
+            10.0,
+            1,
+        ))],
+        Vector3::new_scalar(0.2),
+    )
+}
+
+/// Small path traced progressive renders of `sphere_on_floor`
+pub fn progressive_settings() -> RenderSettings {
+    RenderSettings {
+        width: 24,
+        height: 16,
+        threads: 2,
+        tile_size: 8,
+        bounces: 4,
+        integrator: Integrator::Path,
+        progressive: true,
+        ..RenderSettings::default()
+    }
+}
+
+/// Creates an empty directory for the files a test writes, named after the
+/// test so tests running at the same time don't share one
+pub fn temp_dir(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("raytracer-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
@@ -0,0 +1,115 @@
+//! Reconstruction filter tests, which check that filters with negative lobes
+//! never leave pixels negative, black, or undefined, however few samples
+//! they have.
+
+use std::sync::Arc;
+
+use raytracer::filter::FilterSampler;
+use raytracer::material::Lambertian;
+use raytracer::vector::Vector3;
+use raytracer::{Film, Filter, Integrator, RenderSettings, Renderer};
+
+mod common;
+
+const FILTERS: [&str; 4] = ["box", "tent", "gaussian", "mitchell"];
+
+/// Renders a black sphere against a bright background, whose edges pull
+/// hardest on a filter's negative lobes
+fn render_edges(filter: Filter, samples: u32, progressive: bool) -> Film {
+    let scene = common::sphere(
+        Arc::new(Lambertian::new(Vector3::origin())),
+        50.0,
+        Vector3::new_scalar(10.0),
+    );
+    let settings = RenderSettings {
+        width: 32,
+        height: 32,
+        threads: 2,
+        bounces: 2,
+        samples,
+        progressive,
+        filter,
+        integrator: Integrator::Path,
+        ..RenderSettings::default()
+    };
+    if progressive {
+        Renderer::new(settings).render_progressive(&scene, |_, _| {})
+    } else {
+        Renderer::new(settings).render_film(&scene)
+    }
+}
+
+#[test]
+fn pixels_are_never_negative_or_undefined() {
+    for &name in &FILTERS {
+        let filter = Filter::from_name(name).unwrap();
+        for &samples in &[1, 4, 16] {
+            for &progressive in &[false, true] {
+                let film = render_edges(filter, samples, progressive);
+                for y in 0..film.height() {
+                    for x in 0..film.width() {
+                        let color = film.color(x, y);
+                        for channel in 0..3 {
+                            assert!(
+                                color[channel].is_finite() && color[channel] >= 0.0,
+                                "{} filter at {} samples gave {:?} at ({}, {})",
+                                name,
+                                samples,
+                                color,
+                                x,
+                                y
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn background_stays_its_color() {
+    // Pixels whose whole footprint sees the background must come out as
+    // exactly that bright, whichever lobes their samples landed in
+    for &name in &FILTERS {
+        let film = render_edges(Filter::from_name(name).unwrap(), 4, false);
+        let corner = film.color(0, 0);
+        assert!(
+            (corner.x - 10.0).abs() < 1e-3,
+            "{} filter gave {:?} in the corner",
+            name,
+            corner
+        );
+    }
+}
+
+#[test]
+fn sample_weights_average_to_one() {
+    for &name in &FILTERS {
+        let filter = Filter::from_name(name).unwrap();
+        let sampler = FilterSampler::new(filter);
+        let steps = 1000;
+        let mut total = 0.0;
+        let mut negative = 0;
+        for i in 0..steps {
+            for j in 0..steps {
+                let u = (i as f32 + 0.5) / steps as f32;
+                let v = (j as f32 + 0.5) / steps as f32;
+                let (dx, dy, weight) = sampler.sample(u, v);
+                assert!(dx.abs() <= filter.radius() && dy.abs() <= filter.radius());
+                total += f64::from(weight);
+                if weight < 0.0 {
+                    negative += 1;
+                }
+            }
+        }
+        let average = total / f64::from(steps * steps);
+        assert!(
+            (average - 1.0).abs() < 0.01,
+            "{} weights average {}",
+            name,
+            average
+        );
+        assert_eq!(negative > 0, name == "mitchell", "{}", name);
+    }
+}
@@ -0,0 +1,309 @@
+//! White furnace tests, which check that materials never reflect more light
+//! than reaches them.
+//!
+//! Inside a furnace every direction is equally bright, so a surface that
+//! conserves energy can at most match the background, and one that reflects
+//! everything disappears into it entirely.
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use raytracer::material::{
+    Conductor, Dielectric, Lambertian, Material, Metal, Phong, RoughDielectric,
+};
+use raytracer::principled::{Principled, PrincipledParameters};
+use raytracer::rayhit::RayHit;
+use raytracer::vector::Vector3;
+use raytracer::{Film, Integrator, RenderSettings, Renderer};
+
+mod common;
+
+/// Generates deterministic pseudo-random numbers in 0..1
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> f32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Gets a unit direction at the given angle in degrees from the +z normal
+fn direction_at(degrees: f32) -> Vector3 {
+    let theta = degrees.to_radians();
+    Vector3::new(theta.sin(), 0.0, theta.cos())
+}
+
+/// Creates a hit on a surface facing +z
+fn surface_hit(material: &dyn Material) -> RayHit<'_> {
+    RayHit::new(
+        Vector3::origin(),
+        Vector3::new(0.0, 0.0, 1.0),
+        (0.0, 0.0),
+        1.0,
+        material,
+    )
+}
+
+/// Estimates the fraction of light a material reflects and transmits
+/// towards `outgoing` under uniform lighting, using its own sampling
+fn sampled_albedo(material: &dyn Material, outgoing: Vector3, samples: usize) -> Vector3 {
+    let hit = surface_hit(material);
+    let mut rng = Lcg(3);
+    let mut total = Vector3::origin();
+    for _ in 0..samples {
+        let (lobe, u, v) = (rng.next(), rng.next(), rng.next());
+        if let Some(sample) = material.sample(&hit, outgoing, lobe, u, v) {
+            total = total + sample.weight;
+        }
+    }
+    total * (1.0 / samples as f32)
+}
+
+/// Estimates the same albedo by evaluating the material over uniformly
+/// chosen directions on the sphere, independently of its sampling
+fn evaluated_albedo(material: &dyn Material, outgoing: Vector3, samples: usize) -> Vector3 {
+    let hit = surface_hit(material);
+    let mut rng = Lcg(5);
+    let mut total = Vector3::origin();
+    for _ in 0..samples {
+        let z = 1.0 - 2.0 * rng.next();
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng.next();
+        let incoming = Vector3::new(r * phi.cos(), r * phi.sin(), z);
+        let bsdf = material.evaluate(&hit, outgoing, incoming);
+        total = total + bsdf * (z.abs() * 4.0 * PI);
+    }
+    total * (1.0 / samples as f32)
+}
+
+/// Physically based materials covering every model, from smooth to very
+/// rough
+///
+/// Blinn-Phong is left out, since its normalization only conserves energy
+/// approximately, and so is the principled material, whose layers are
+/// simply added together and brighten at grazing angles by design.
+fn materials() -> Vec<(String, Box<dyn Material>)> {
+    let mut materials: Vec<(String, Box<dyn Material>)> = vec![
+        (
+            "lambertian".to_string(),
+            Box::new(Lambertian::new(Vector3::new_scalar(1.0))),
+        ),
+        (
+            "dielectric".to_string(),
+            Box::new(Dielectric::new(1.5, Vector3::new_scalar(1.0))),
+        ),
+    ];
+    for &roughness in &[0.0, 0.1, 0.3, 0.6, 1.0] {
+        for &metal in &[Metal::Aluminium, Metal::Gold, Metal::Silver] {
+            materials.push((
+                format!("{:?} conductor with roughness {}", metal, roughness),
+                Box::new(Conductor::from_metal(metal, roughness)),
+            ));
+        }
+    }
+    for &roughness in &[0.1, 0.3, 0.6, 1.0] {
+        materials.push((
+            format!("rough dielectric with roughness {}", roughness),
+            Box::new(RoughDielectric::new(
+                1.5,
+                Vector3::new_scalar(1.0),
+                roughness,
+            )),
+        ));
+    }
+    materials
+}
+
+#[test]
+fn materials_never_gain_energy() {
+    for (name, material) in materials() {
+        for &angle in &[0.0, 30.0, 60.0, 85.0] {
+            let albedo = sampled_albedo(&*material, direction_at(angle), 100_000);
+            for channel in 0..3 {
+                assert!(
+                    albedo[channel] <= 1.01,
+                    "{} reflects {} of the light at {} degrees",
+                    name,
+                    albedo[channel],
+                    angle
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn smooth_rough_dielectric_keeps_its_energy() {
+    // Barely rough glass loses almost nothing to masking, so everything it
+    // doesn't reflect is refracted
+    let glass = RoughDielectric::new(1.5, Vector3::new_scalar(1.0), 0.1);
+    for &angle in &[0.0, 45.0, 80.0] {
+        let albedo = sampled_albedo(&glass, direction_at(angle), 100_000);
+        assert!(
+            albedo.x > 0.97,
+            "rough glass keeps {} of the light at {} degrees",
+            albedo.x,
+            angle
+        );
+    }
+}
+
+#[test]
+fn sampling_matches_evaluation() {
+    // Rough surfaces spread their light widely enough that uniform
+    // directions estimate the albedo well
+    let rough: Vec<(&str, Box<dyn Material>)> = vec![
+        (
+            "lambertian",
+            Box::new(Lambertian::new(Vector3::new(0.9, 0.5, 0.1))),
+        ),
+        (
+            "phong",
+            Box::new(Phong::new(
+                Vector3::new_scalar(0.4),
+                Vector3::new_scalar(0.4),
+                8.0,
+                0.0,
+            )),
+        ),
+        (
+            "copper conductor",
+            Box::new(Conductor::from_metal(Metal::Copper, 0.6)),
+        ),
+        (
+            "iron conductor",
+            Box::new(Conductor::from_metal(Metal::Iron, 0.9)),
+        ),
+        (
+            "rough dielectric",
+            Box::new(RoughDielectric::new(1.5, Vector3::new_scalar(1.0), 0.8)),
+        ),
+        (
+            "principled plastic",
+            Box::new(Principled::new(PrincipledParameters {
+                base_color: Vector3::new(0.8, 0.3, 0.1).into(),
+                roughness: 0.7,
+                sheen: 1.0,
+                clearcoat: 1.0,
+                clearcoat_roughness: 0.6,
+                ..PrincipledParameters::default()
+            })),
+        ),
+        (
+            "principled frosted glass",
+            Box::new(Principled::new(PrincipledParameters {
+                roughness: 0.8,
+                metallic: 0.3,
+                transmission: 0.7,
+                ..PrincipledParameters::default()
+            })),
+        ),
+    ];
+
+    for (name, material) in rough {
+        for &angle in &[0.0, 40.0, 70.0] {
+            let outgoing = direction_at(angle);
+            let sampled = sampled_albedo(&*material, outgoing, 200_000);
+            let evaluated = evaluated_albedo(&*material, outgoing, 400_000);
+            for channel in 0..3 {
+                assert!(
+                    (sampled[channel] - evaluated[channel]).abs() < 0.03,
+                    "{} at {} degrees samples {} but evaluates {}",
+                    name,
+                    angle,
+                    sampled[channel],
+                    evaluated[channel]
+                );
+            }
+        }
+    }
+}
+
+/// Renders a sphere of the given material inside a white furnace with the
+/// path tracer
+fn render_furnace(material: Arc<dyn Material>) -> Film {
+    let scene = common::sphere(material, 40.0, Vector3::new_scalar(1.0));
+    let settings = RenderSettings {
+        width: 16,
+        height: 16,
+        threads: 2,
+        bounces: 64,
+        samples: 64,
+        integrator: Integrator::Path,
+        ..RenderSettings::default()
+    };
+    Renderer::new(settings).render_film(&scene)
+}
+
+/// Gets the average brightness of a film's pixels
+fn average_brightness(film: &Film) -> f32 {
+    let mut total = 0.0;
+    for y in 0..film.height() {
+        for x in 0..film.width() {
+            let color = film.color(x, y);
+            total += (color.x + color.y + color.z) / 3.0;
+        }
+    }
+    total / (film.width() * film.height()) as f32
+}
+
+#[test]
+fn white_lambertian_sphere_disappears_in_furnace() {
+    let film = render_furnace(Arc::new(Lambertian::new(Vector3::new_scalar(1.0))));
+    for y in 0..film.height() {
+        for x in 0..film.width() {
+            let color = film.color(x, y);
+            for channel in 0..3 {
+                assert!(
+                    (color[channel] - 1.0).abs() < 1e-3,
+                    "pixel ({}, {}) is {:?}",
+                    x,
+                    y,
+                    color
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn glass_spheres_conserve_energy_in_furnace() {
+    let smooth = average_brightness(&render_furnace(Arc::new(Dielectric::new(
+        1.5,
+        Vector3::new_scalar(1.0),
+    ))));
+    assert!(
+        (smooth - 1.0).abs() < 0.01,
+        "smooth glass averages {}",
+        smooth
+    );
+
+    let rough = average_brightness(&render_furnace(Arc::new(RoughDielectric::new(
+        1.5,
+        Vector3::new_scalar(1.0),
+        0.3,
+    ))));
+    assert!(
+        rough <= 1.01 && rough > 0.9,
+        "rough glass averages {}",
+        rough
+    );
+}
+
+#[test]
+fn metal_spheres_never_brighten_furnace() {
+    for &roughness in &[0.0, 0.5, 1.0] {
+        let film = render_furnace(Arc::new(Conductor::from_metal(Metal::Silver, roughness)));
+        let brightness = average_brightness(&film);
+        assert!(
+            brightness <= 1.01,
+            "silver with roughness {} averages {}",
+            roughness,
+            brightness
+        );
+    }
+}
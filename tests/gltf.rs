@@ -0,0 +1,400 @@
+//! glTF loader tests, which check that meshes are placed by their nodes,
+//! that binary and JSON files with embedded or separate buffers load alike,
+//! that materials map onto principled parameters, and that files using
+//! unsupported features or missing parts are turned away.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use png::HasParameters;
+use serde_json::{json, Value};
+
+use raytracer::gltf::{load_gltf, GltfError, GltfMesh};
+use raytracer::vector::Vector3;
+use raytracer::{RenderSettings, Renderer, SceneFile};
+
+mod common;
+
+/// Gets the bytes of one triangle: three positions, then three texture
+/// coordinates, then three 16-bit indices padded to a multiple of four
+fn triangle_buffer(scale: f32) -> Vec<u8> {
+    let floats = [
+        0.0, 0.0, 0.0, scale, 0.0, 0.0, 0.0, scale, 0.0, // positions
+        0.0, 0.0, 1.0, 0.0, 0.0, 1.0, // texture coordinates
+    ];
+    let mut bytes: Vec<u8> = floats.iter().flat_map(|f: &f32| f.to_le_bytes()).collect();
+    for index in &[0u16, 1, 2, 0] {
+        bytes.extend_from_slice(&index.to_le_bytes());
+    }
+    bytes
+}
+
+/// Describes the triangle placed twice under a parent node moved along x:
+/// once moved along z and doubled, and once turned a quarter about z
+fn triangle_document(buffer: Value, length: usize) -> Value {
+    let half = std::f32::consts::FRAC_1_SQRT_2;
+    json!({
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [
+            { "translation": [1.0, 0.0, 0.0], "children": [1, 2] },
+            { "mesh": 0, "translation": [0.0, 0.0, 5.0], "scale": [2.0, 2.0, 2.0] },
+            { "mesh": 0, "rotation": [0.0, 0.0, half, half] }
+        ],
+        "meshes": [{
+            "primitives": [{
+                "attributes": { "POSITION": 0, "TEXCOORD_0": 1 },
+                "indices": 2,
+                "material": 0
+            }]
+        }],
+        "materials": [{
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [0.5, 0.25, 1.0, 1.0],
+                "metallicFactor": 0.3,
+                "roughnessFactor": 0.7
+            },
+            "extensions": {
+                "KHR_materials_transmission": { "transmissionFactor": 0.4 },
+                "KHR_materials_ior": { "ior": 1.45 },
+                "KHR_materials_clearcoat": {
+                    "clearcoatFactor": 0.2,
+                    "clearcoatRoughnessFactor": 0.1
+                }
+            }
+        }],
+        "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3" },
+            { "bufferView": 0, "byteOffset": 36, "componentType": 5126, "count": 3, "type": "VEC2" },
+            { "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }
+        ],
+        "bufferViews": [
+            { "buffer": 0, "byteLength": 60 },
+            { "buffer": 0, "byteOffset": 60, "byteLength": 6 }
+        ],
+        "buffers": [{ "byteLength": length, "uri": buffer }]
+    })
+}
+
+/// Encodes bytes as standard base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+            bits | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                text.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Writes a JSON glTF file with its buffer embedded as a data URI
+fn write_gltf(directory: &Path, document: impl Fn(Value, usize) -> Value) -> Vec<GltfMesh> {
+    let buffer = triangle_buffer(1.0);
+    let uri = format!("data:application/octet-stream;base64,{}", base64(&buffer));
+    let path = directory.join("triangle.gltf");
+    fs::write(&path, document(json!(uri), buffer.len()).to_string()).unwrap();
+    load_gltf(&path).unwrap()
+}
+
+/// Writes a binary glTF file with its buffer in the binary chunk
+fn write_glb(path: &Path) {
+    let buffer = triangle_buffer(1.0);
+    let mut document = triangle_document(Value::Null, buffer.len());
+    document["buffers"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("uri");
+    let mut json = document.to_string().into_bytes();
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+
+    let mut bytes = b"glTF".to_vec();
+    let length = 12 + 8 + json.len() + 8 + buffer.len();
+    for word in &[2, length as u32, json.len() as u32, 0x4E4F_534A] {
+        bytes.extend_from_slice(&u32::to_le_bytes(*word));
+    }
+    bytes.extend_from_slice(&json);
+    for word in &[buffer.len() as u32, 0x004E_4942] {
+        bytes.extend_from_slice(&u32::to_le_bytes(*word));
+    }
+    bytes.extend_from_slice(&buffer);
+    fs::write(path, bytes).unwrap();
+}
+
+fn assert_close(actual: Vector3, expected: Vector3) {
+    let difference = actual - expected;
+    assert!(
+        difference.dot(difference) < 1e-10,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn meshes_are_placed_by_their_nodes() {
+    let directory = common::temp_dir("gltf-nodes");
+    let meshes = write_gltf(&directory, triangle_document);
+
+    assert_eq!(meshes.len(), 2);
+    assert!(Arc::ptr_eq(&meshes[0].mesh, &meshes[1].mesh));
+    let mesh = &meshes[0].mesh;
+    assert_eq!(mesh.len(), 1);
+    assert_eq!(mesh.indices(), &[[0, 1, 2]]);
+    assert_close(mesh.positions()[1], Vector3::new(1.0, 0.0, 0.0));
+    assert!(mesh.normals().is_none());
+
+    // Children are placed within their parent, and scaled before moving
+    let corner = Vector3::new(1.0, 0.0, 0.0);
+    assert_close(
+        meshes[0].transform.point(corner),
+        Vector3::new(3.0, 0.0, 5.0),
+    );
+    assert_close(
+        meshes[1].transform.point(corner),
+        Vector3::new(1.0, 1.0, 0.0),
+    );
+}
+
+#[test]
+fn texture_coordinates_start_at_the_bottom() {
+    let directory = common::temp_dir("gltf-uvs");
+    let meshes = write_gltf(&directory, triangle_document);
+    assert_eq!(
+        meshes[0].mesh.uvs().unwrap(),
+        &[(0.0, 1.0), (1.0, 1.0), (0.0, 0.0)]
+    );
+}
+
+#[test]
+fn materials_map_onto_principled_parameters() {
+    let directory = common::temp_dir("gltf-materials");
+    let material = &write_gltf(&directory, triangle_document)[0].material;
+    assert_close(material.base_color, Vector3::new(0.5, 0.25, 1.0));
+    assert_eq!(material.metallic, 0.3);
+    assert_eq!(material.roughness, 0.7);
+    assert_eq!(material.transmission, 0.4);
+    assert_eq!(material.clearcoat, 0.2);
+    assert_eq!(material.clearcoat_roughness, 0.1);
+
+    let parameters = material.principled_parameters();
+    assert_eq!(parameters.metallic, 0.3);
+    assert_eq!(parameters.roughness, 0.7);
+    assert_eq!(parameters.transmission, 0.4);
+    assert_eq!(parameters.ior, 1.45);
+    // The index of refraction sets how strongly dielectrics reflect, where
+    // a specular of 0.5 reflects 4% like an index of 1.5
+    let reflectance = (0.45f32 / 2.45).powi(2);
+    assert!((parameters.specular - reflectance / 0.08).abs() < 1e-6);
+}
+
+#[test]
+fn primitives_without_a_material_are_rough_white_metal() {
+    let directory = common::temp_dir("gltf-default-material");
+    let meshes = write_gltf(&directory, |buffer, length| {
+        let mut document = triangle_document(buffer, length);
+        let primitive = &mut document["meshes"][0]["primitives"][0];
+        primitive.as_object_mut().unwrap().remove("material");
+        document
+    });
+    let material = &meshes[0].material;
+    assert_close(material.base_color, Vector3::new_scalar(1.0));
+    assert_eq!(material.metallic, 1.0);
+    assert_eq!(material.roughness, 1.0);
+}
+
+#[test]
+fn binary_files_load_like_json_files() {
+    let directory = common::temp_dir("gltf-binary");
+    let json = write_gltf(&directory, triangle_document);
+    let path = directory.join("triangle.glb");
+    write_glb(&path);
+    let binary = load_gltf(&path).unwrap();
+
+    assert_eq!(binary.len(), json.len());
+    for (binary, json) in binary.iter().zip(&json) {
+        for (&binary, &json) in binary.mesh.positions().iter().zip(json.mesh.positions()) {
+            assert_close(binary, json);
+        }
+        assert_eq!(binary.mesh.uvs(), json.mesh.uvs());
+        assert_eq!(binary.mesh.indices(), json.mesh.indices());
+        assert_eq!(binary.transform, json.transform);
+    }
+}
+
+#[test]
+fn separate_files_are_found_next_to_the_model() {
+    let directory = common::temp_dir("gltf-files");
+    let buffer = triangle_buffer(1.0);
+    fs::write(directory.join("triangle data.bin"), &buffer).unwrap();
+
+    // A single white texel, which comes out as the base color
+    let image = fs::File::create(directory.join("white.png")).unwrap();
+    let mut encoder = png::Encoder::new(image, 1, 1);
+    encoder.set(png::ColorType::RGB).set(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&[255, 255, 255]).unwrap();
+    drop(writer);
+
+    let mut document = triangle_document(json!("triangle%20data.bin"), buffer.len());
+    document["images"] = json!([{ "uri": "white.png" }]);
+    document["samplers"] = json!([{ "wrapS": 33071 }]);
+    document["textures"] = json!([{ "source": 0, "sampler": 0 }]);
+    document["materials"][0]["pbrMetallicRoughness"]["baseColorTexture"] = json!({ "index": 0 });
+    let path = directory.join("triangle.gltf");
+    fs::write(&path, document.to_string()).unwrap();
+
+    let meshes = load_gltf(&path).unwrap();
+    assert_close(meshes[0].mesh.positions()[2], Vector3::new(0.0, 1.0, 0.0));
+    let texture = meshes[0].material.base_color_texture.as_ref().unwrap();
+    assert_close(texture.sample(0.5, 0.5), Vector3::new(0.5, 0.25, 1.0));
+}
+
+/// Writes the triangle document after an edit, and loads it
+fn load_edited(
+    directory: &Path,
+    name: &str,
+    edit: impl Fn(&mut Value),
+) -> Result<Vec<GltfMesh>, GltfError> {
+    let buffer = triangle_buffer(1.0);
+    let uri = format!("data:application/octet-stream;base64,{}", base64(&buffer));
+    let mut document = triangle_document(json!(uri), buffer.len());
+    edit(&mut document);
+    let path = directory.join(name);
+    fs::write(&path, document.to_string()).unwrap();
+    load_gltf(&path)
+}
+
+fn assert_invalid(result: Result<Vec<GltfMesh>, GltfError>, expected: &str) {
+    match result {
+        Err(GltfError::Invalid(_, message)) => {
+            assert!(message.contains(expected), "unexpected error: {}", message)
+        }
+        other => panic!("expected an invalid file, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn unsupported_files_are_rejected() {
+    let directory = common::temp_dir("gltf-unsupported");
+    let load = |name: &str, edit: &dyn Fn(&mut Value)| load_edited(&directory, name, edit);
+
+    assert_invalid(
+        load("lines.gltf", &|document| {
+            document["meshes"][0]["primitives"][0]["mode"] = json!(1);
+        }),
+        "mode 1",
+    );
+    assert_invalid(
+        load("draco.gltf", &|document| {
+            document["extensionsRequired"] = json!(["KHR_draco_mesh_compression"]);
+        }),
+        "KHR_draco_mesh_compression",
+    );
+    assert_invalid(
+        load("version.gltf", &|document| {
+            document["asset"]["version"] = json!("1.0");
+        }),
+        "1.0",
+    );
+    assert_invalid(
+        load("short.gltf", &|document| {
+            document["accessors"][0]["count"] = json!(30);
+        }),
+        "accessor 0",
+    );
+    match load_gltf(&directory.join("missing.gltf")) {
+        Err(GltfError::Io(..)) => {}
+        other => panic!("expected a read error, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[test]
+fn missing_references_are_rejected() {
+    let directory = common::temp_dir("gltf-missing");
+    assert_invalid(
+        load_edited(&directory, "accessor.gltf", |document| {
+            document["meshes"][0]["primitives"][0]["attributes"]["POSITION"] = json!(7);
+            document["accessors"] = json!([]);
+        }),
+        "accessor 7 doesn't exist",
+    );
+    assert_invalid(
+        load_edited(&directory, "view.gltf", |document| {
+            document["accessors"][0]["bufferView"] = json!(3);
+            document["bufferViews"] = json!([]);
+        }),
+        "buffer view 3 doesn't exist",
+    );
+    // Counts are checked against the view before any room is made for them
+    assert_invalid(
+        load_edited(&directory, "count.gltf", |document| {
+            document["accessors"][0]["count"] = json!(usize::MAX);
+        }),
+        "accessor 0 runs past",
+    );
+    assert_invalid(
+        load_edited(&directory, "offset.gltf", |document| {
+            document["bufferViews"][0]["byteOffset"] = json!(usize::MAX);
+        }),
+        "buffer view 0 is out of range",
+    );
+}
+
+#[test]
+fn scenes_place_gltf_meshes() {
+    // A large glowing triangle, turned to face the camera and moved in
+    // front of it by the scene,
+    // whose right angle sits just below and to the side of the center so
+    // it covers only one corner of the image
+    let directory = common::temp_dir("gltf-scene");
+    let buffer = triangle_buffer(20.0);
+    let uri = format!("data:application/octet-stream;base64,{}", base64(&buffer));
+    let mut document = triangle_document(json!(uri), buffer.len());
+    document["scenes"] = json!([{ "nodes": [2] }]);
+    document["nodes"][2] = json!({ "mesh": 0, "translation": [-1.0, -1.0, 0.0] });
+    document["materials"][0]["emissiveFactor"] = json!([1.0, 1.0, 1.0]);
+    fs::write(directory.join("glow.gltf"), document.to_string()).unwrap();
+
+    let scene = r#"
+        [camera]
+        position = [0.0, 0.0, 0.0]
+        up = [0.0, 1.0, 0.0]
+        target = [0.0, 0.0, 1.0]
+        fov = 60.0
+
+        [[objects]]
+        type = "mesh"
+        path = "glow.gltf"
+        transform = [{ rotate = [0.0, 180.0, 0.0] }, { translate = [0.0, 0.0, 5.0] }]
+    "#;
+    let path = directory.join("scene.toml");
+    fs::write(&path, scene).unwrap();
+    let SceneFile { scene, .. } = SceneFile::load(&path).unwrap();
+
+    let film = Renderer::new(RenderSettings {
+        width: 8,
+        height: 8,
+        threads: 1,
+        ..RenderSettings::default()
+    })
+    .render_film(&scene);
+    assert!(film.color(4, 4).x > 0.5, "{:?}", film.color(4, 4));
+    let corners = [(0, 0), (7, 0), (0, 7), (7, 7)];
+    let background = corners
+        .iter()
+        .filter(|&&(x, y)| film.color(x, y).x == scene.background().x)
+        .count();
+    assert_eq!(background, 3);
+}
@@ -0,0 +1,238 @@
+//! Light tests, which check that point and spot lights fall off with the
+//! square of distance, that spot lights fade out towards their cone's edge
+//! and go dark past it, and that sampling area lights adds up to the
+//! irradiance they give in closed form, softening shadows at their edges.
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use raytracer::light::{
+    DiskLight, Light, LightSample, PointLight, RectangleLight, SphereLight, SpotLight,
+};
+use raytracer::material::Lambertian;
+use raytracer::ray::Ray;
+use raytracer::sphere::Sphere;
+use raytracer::vector::Vector3;
+use raytracer::Intersectable;
+
+/// Number of samples along each side of the grid area lights are sampled on
+const GRID: usize = 200;
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() <= 1e-4 * expected.abs().max(1.0),
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+/// Samples a light with a single position, which ignores u and v
+fn sample(light: &dyn Light, point: Vector3) -> Option<LightSample> {
+    light.sample(point, 0.5, 0.5)
+}
+
+/// Gets a point `distance` away from `apex` at `angle` degrees from -y,
+/// as seen from the apex
+fn below(apex: Vector3, angle: f32, distance: f32) -> Vector3 {
+    let angle = angle.to_radians();
+    apex + Vector3::new(angle.sin(), -angle.cos(), 0.0) * distance
+}
+
+#[test]
+fn point_lights_fall_off_with_the_square_of_distance() {
+    let position = Vector3::new(1.0, 2.0, 3.0);
+    let light = PointLight::new(position, Vector3::new(1.0, 0.5, 0.25), 8.0);
+    for &distance in &[0.5, 1.0, 2.0, 4.0, 10.0] {
+        let point = position - Vector3::new(0.0, 0.6, 0.8) * distance;
+        let sample = sample(&light, point).unwrap();
+        assert_close(sample.distance, distance);
+        assert!((sample.direction - Vector3::new(0.0, 0.6, 0.8)).len() < 1e-5);
+        let expected = 8.0 / (distance * distance);
+        assert_close(sample.radiance.x, expected);
+        assert_close(sample.radiance.y, expected * 0.5);
+        assert_close(sample.radiance.z, expected * 0.25);
+    }
+}
+
+#[test]
+fn spot_lights_fade_out_towards_their_edge() {
+    // A 30 degree cone whose outer 10 degrees fade out
+    let apex = Vector3::new(0.0, 5.0, 0.0);
+    let white = Vector3::new_scalar(1.0);
+    let light = SpotLight::new(apex, Vector3::new(0.0, -2.0, 0.0), white, 4.0, 30.0, 10.0);
+    let radiance = |angle: f32, distance: f32| {
+        sample(&light, below(apex, angle, distance)).map(|sample| sample.radiance.x)
+    };
+
+    // The inner cone is lit fully, with inverse-square falloff
+    for &angle in &[0.0, 10.0, 19.9] {
+        assert_close(radiance(angle, 2.0).unwrap(), 1.0);
+        assert_close(radiance(angle, 4.0).unwrap(), 0.25);
+    }
+
+    // The edge fades smoothly from full to nothing
+    let mut last = 1.0;
+    for step in 1..10 {
+        let angle = 20.0 + step as f32;
+        let faded = radiance(angle, 2.0).unwrap();
+        assert!(
+            faded < last && faded > 0.0,
+            "{} at {} degrees",
+            faded,
+            angle
+        );
+        last = faded;
+    }
+    let cos = |degrees: f32| degrees.to_radians().cos();
+    let t = (cos(25.0) - cos(30.0)) / (cos(20.0) - cos(30.0));
+    assert_close(radiance(25.0, 2.0).unwrap(), t * t * (3.0 - 2.0 * t));
+    assert!(radiance(29.9, 2.0).unwrap() < 1e-3);
+
+    // Nothing reaches past the cone, or behind the light
+    assert!(radiance(30.1, 2.0).is_none());
+    assert!(radiance(90.0, 2.0).is_none());
+    assert!(radiance(180.0, 2.0).is_none());
+}
+
+#[test]
+fn spot_lights_without_a_falloff_have_a_hard_edge() {
+    let apex = Vector3::origin();
+    let light = SpotLight::new(
+        apex,
+        Vector3::new(0.0, -1.0, 0.0),
+        Vector3::new_scalar(1.0),
+        1.0,
+        45.0,
+        0.0,
+    );
+    assert_close(
+        sample(&light, below(apex, 44.9, 1.0)).unwrap().radiance.x,
+        1.0,
+    );
+    assert!(sample(&light, below(apex, 45.1, 1.0)).is_none());
+}
+
+/// Sums the samples of a light over an even grid, weighting each by how
+/// much of it a surface at `point` facing `normal` receives and whether it
+/// is `visible`
+fn irradiance(
+    light: &dyn Light,
+    point: Vector3,
+    normal: Vector3,
+    visible: impl Fn(&LightSample) -> bool,
+) -> f32 {
+    let mut total = 0.0;
+    for i in 0..GRID {
+        for j in 0..GRID {
+            let u = (i as f32 + 0.5) / GRID as f32;
+            let v = (j as f32 + 0.5) / GRID as f32;
+            if let Some(sample) = light.sample(point, u, v) {
+                if visible(&sample) {
+                    total += sample.radiance.x * sample.direction.dot(normal).max(0.0);
+                }
+            }
+        }
+    }
+    total / (GRID * GRID) as f32
+}
+
+/// Gets the irradiance under a corner of a `width` by `depth` rectangle of
+/// unit radiance `height` above and parallel to the surface
+fn corner_irradiance(width: f32, depth: f32, height: f32) -> f32 {
+    let (x, y) = (width / height, depth / height);
+    let (sx, sy) = ((1.0 + x * x).sqrt(), (1.0 + y * y).sqrt());
+    0.5 * (x / sx * (y / sx).atan() + y / sy * (x / sy).atan())
+}
+
+fn assert_near(actual: f32, expected: f32, tolerance: f32) {
+    assert!(
+        (actual - expected).abs() <= tolerance * expected,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn area_lights_integrate_to_their_irradiance() {
+    let up = Vector3::new(0.0, 1.0, 0.0);
+    let down = Vector3::new(0.0, -1.0, 0.0);
+    let radiance = Vector3::new_scalar(1.0);
+    let everything = |_: &LightSample| true;
+
+    // A 2 by 1 rectangle facing down, 1.5 above the point, is four corner
+    // rectangles meeting over it
+    let rectangle = RectangleLight::new(
+        Vector3::new(0.0, 1.5, 0.0),
+        Vector3::new(2.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+        radiance * 3.0,
+        1.0,
+        16,
+    );
+    let expected = 4.0 * corner_irradiance(1.0, 0.5, 1.5) * 3.0;
+    let actual = irradiance(&rectangle, Vector3::origin(), up, everything);
+    assert_near(actual, expected, 1e-3);
+    assert_eq!(rectangle.samples(), 16);
+
+    // A disk of radius r at height h straight overhead gives π r² / (h² + r²)
+    let disk = DiskLight::new(Vector3::new(0.0, 2.0, 0.0), down, 0.75, radiance, 2.0, 9);
+    let expected = PI * 0.75 * 0.75 / (2.0 * 2.0 + 0.75 * 0.75) * 2.0;
+    let actual = irradiance(&disk, Vector3::origin(), up, everything);
+    assert_near(actual, expected, 1e-3);
+    assert_eq!(disk.samples(), 9);
+
+    // A sphere of radius r at distance d straight overhead gives π r² / d²
+    let sphere = SphereLight::new(Vector3::new(0.0, 3.0, 0.0), 0.5, radiance, 1.0, 4);
+    let expected = PI * 0.5 * 0.5 / (3.0 * 3.0);
+    let actual = irradiance(&sphere, Vector3::origin(), up, everything);
+    assert_near(actual, expected, 1e-3);
+    assert_eq!(sphere.samples(), 4);
+
+    // Points behind one sided lights, or inside a sphere light, get nothing
+    let above = Vector3::new(0.0, 5.0, 0.0);
+    assert_eq!(irradiance(&rectangle, above, down, everything), 0.0);
+    assert_eq!(irradiance(&disk, above, down, everything), 0.0);
+    assert_eq!(
+        irradiance(&sphere, Vector3::new(0.0, 3.1, 0.0), up, everything),
+        0.0
+    );
+}
+
+#[test]
+fn area_lights_cast_soft_shadows() {
+    // A blocker halfway between the floor and a disk light overhead casts a
+    // shadow whose edge fades from fully lit to fully dark
+    let up = Vector3::new(0.0, 1.0, 0.0);
+    let light = DiskLight::new(
+        Vector3::new(0.0, 4.0, 0.0),
+        -up,
+        1.0,
+        Vector3::new_scalar(1.0),
+        1.0,
+        16,
+    );
+    let material = Arc::new(Lambertian::new(Vector3::new_scalar(0.5)));
+    let blocker = Sphere::new(Vector3::new(0.0, 2.0, 0.0), 1.0, material);
+    let lit_fraction = |x: f32| {
+        let point = Vector3::new(x, 0.0, 0.0);
+        let unblocked = |sample: &LightSample| {
+            blocker
+                .intersect(Ray::new(point, sample.direction))
+                .is_none_or(|hit| hit.distance() > sample.distance)
+        };
+        irradiance(&light, point, up, unblocked) / irradiance(&light, point, up, |_| true)
+    };
+
+    assert_eq!(lit_fraction(0.0), 0.0);
+    assert_eq!(lit_fraction(6.0), 1.0);
+    let mut last = 0.0;
+    for step in 0..8 {
+        let fraction = lit_fraction(0.8 + 0.2 * step as f32);
+        assert!(fraction >= last, "{} after {}", fraction, last);
+        last = fraction;
+    }
+    let penumbra = lit_fraction(1.6);
+    assert!(penumbra > 0.05 && penumbra < 0.95, "{}", penumbra);
+}
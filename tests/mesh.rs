@@ -0,0 +1,171 @@
+//! Triangle mesh tests, which check hits on edges and back faces, misses for
+//! parallel rays and triangles behind the ray, that normals are interpolated
+//! by barycentric coordinates, and that smooth meshes keep the face's normal
+//! as the geometric normal.
+
+use std::sync::Arc;
+
+use raytracer::material::Lambertian;
+use raytracer::mesh::TriangleMesh;
+use raytracer::ray::Ray;
+use raytracer::vector::Vector3;
+use raytracer::Intersectable;
+
+fn assert_same(actual: Vector3, expected: Vector3) {
+    assert!(
+        (actual - expected).len() < 1e-5,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+fn gray() -> Arc<Lambertian> {
+    Arc::new(Lambertian::new(Vector3::new_scalar(0.5)))
+}
+
+/// Creates a flat unit square in the z = 0 plane facing +z, split along its
+/// diagonal from (0, 0) to (1, 1)
+fn square() -> TriangleMesh {
+    TriangleMesh::new(
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        ],
+        vec![[0, 1, 2], [0, 2, 3]],
+        gray(),
+    )
+}
+
+/// Shoots a ray at the square from `z`, straight towards its plane
+fn shoot(mesh: &TriangleMesh, x: f32, y: f32, z: f32) -> Option<(f32, Vector3)> {
+    let direction = Vector3::new(0.0, 0.0, -z.signum());
+    let hit = mesh.intersect(Ray::new(Vector3::new(x, y, z), direction))?;
+    Some((hit.distance(), hit.normal()))
+}
+
+#[test]
+fn edges_and_corners_are_hit() {
+    let mesh = square();
+    // The outer edges, the corners, and the diagonal the triangles share
+    for &(x, y) in &[
+        (0.5, 0.0),
+        (1.0, 0.5),
+        (0.0, 0.0),
+        (1.0, 1.0),
+        (0.0, 1.0),
+        (0.5, 0.5),
+        (0.3, 0.3),
+    ] {
+        let (distance, _) =
+            shoot(&mesh, x, y, 2.0).unwrap_or_else(|| panic!("missed ({}, {})", x, y));
+        assert!((distance - 2.0).abs() < 1e-6);
+    }
+    for &(x, y) in &[(0.5, -1e-3), (1.001, 0.5), (-1e-3, -1e-3), (0.5, 1.001)] {
+        assert!(shoot(&mesh, x, y, 2.0).is_none(), "hit ({}, {})", x, y);
+    }
+}
+
+#[test]
+fn back_faces_are_hit_with_the_same_normal() {
+    let mesh = square();
+    let (front, front_normal) = shoot(&mesh, 0.25, 0.6, 3.0).unwrap();
+    let (back, back_normal) = shoot(&mesh, 0.25, 0.6, -3.0).unwrap();
+    assert!((front - 3.0).abs() < 1e-6 && (back - 3.0).abs() < 1e-6);
+    assert_same(front_normal, Vector3::new(0.0, 0.0, 1.0));
+    assert_same(back_normal, Vector3::new(0.0, 0.0, 1.0));
+}
+
+#[test]
+fn parallel_rays_miss() {
+    let mesh = square();
+    let along = Vector3::new(1.0, 0.0, 0.0);
+    // In the square's plane, and just above it
+    assert!(mesh
+        .intersect(Ray::new(Vector3::new(-1.0, 0.5, 0.0), along))
+        .is_none());
+    assert!(mesh
+        .intersect(Ray::new(Vector3::new(-1.0, 0.5, 0.1), along))
+        .is_none());
+}
+
+#[test]
+fn triangles_behind_the_ray_are_missed() {
+    let mesh = square();
+    let down = Vector3::new(0.0, 0.0, -1.0);
+    // Hits start at the ray's origin, so a ray starting on the surface
+    // hits it at once, and one starting just past it misses
+    let on = mesh.intersect(Ray::new(Vector3::new(0.4, 0.2, 0.0), down));
+    assert!(on.unwrap().distance().abs() < 1e-6);
+    let past = Ray::new(Vector3::new(0.4, 0.2, -1e-3), down);
+    assert!(mesh.intersect(past).is_none());
+    let away = Ray::new(Vector3::new(0.4, 0.2, 1.0), -down);
+    assert!(mesh.intersect(away).is_none());
+}
+
+#[test]
+fn vertex_normals_are_blended_by_barycentric_coordinates() {
+    let normals = [
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(1.0, 0.0, 1.0).normalize(),
+        Vector3::new(0.0, 1.0, 1.0).normalize(),
+    ];
+    let mesh = TriangleMesh::with_attributes(
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        ],
+        Some(normals.to_vec()),
+        Some(vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]),
+        vec![[0, 1, 2]],
+        gray(),
+    );
+    let hit_at = |x: f32, y: f32| {
+        let ray = Ray::new(Vector3::new(x, y, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        mesh.intersect(ray).unwrap()
+    };
+
+    // Each corner has its own normal
+    assert_same(hit_at(0.0, 0.0).normal(), normals[0]);
+    assert_same(hit_at(1.0, 0.0).normal(), normals[1]);
+    assert_same(hit_at(0.0, 1.0).normal(), normals[2]);
+
+    // In between they are weighted by the hit's barycentric coordinates
+    let hit = hit_at(0.25, 0.5);
+    let expected = (normals[0] * 0.25 + normals[1] * 0.25 + normals[2] * 0.5).normalize();
+    assert_same(hit.normal(), expected);
+    let (u, v) = hit.uv();
+    assert!((u - 0.25).abs() < 1e-6 && (v - 0.5).abs() < 1e-6);
+}
+
+/// Creates a triangle in the z = 0 plane, facing -z, whose vertex normals
+/// all lean towards +x
+fn leaning_triangle() -> TriangleMesh {
+    let normal = Vector3::new(1.0, 0.0, -1.0).normalize();
+    TriangleMesh::with_normals(
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+        ],
+        vec![normal; 3],
+        vec![[0, 1, 2]],
+        gray(),
+    )
+}
+
+#[test]
+fn smooth_meshes_keep_the_face_as_the_geometric_normal() {
+    let mesh = leaning_triangle();
+    let ray = Ray::new(Vector3::new(0.25, 0.25, -1.0), Vector3::new(0.0, 0.0, 1.0));
+    let hit = mesh.intersect(ray).unwrap();
+    assert_same(hit.normal(), Vector3::new(1.0, 0.0, -1.0).normalize());
+    assert_same(hit.geometric_normal(), Vector3::new(0.0, 0.0, -1.0));
+
+    // The tangent frame follows the shading normal
+    assert!(hit.tangent().dot(hit.normal()).abs() < 1e-5);
+    assert!(hit.bitangent().dot(hit.normal()).abs() < 1e-5);
+}
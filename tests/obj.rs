@@ -0,0 +1,238 @@
+//! OBJ loader tests, which check that polygons are split into triangles,
+//! that relative indices count back from the latest vertex, that every
+//! material gets its own mesh, that transparency follows `d` and `Ni`, that
+//! only used materials are loaded, and that parse errors point at their
+//! line.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use raytracer::mesh::TriangleMesh;
+use raytracer::obj::{load_obj, load_obj_with_dependencies, ObjError};
+use raytracer::vector::Vector3;
+
+mod common;
+
+/// Writes an OBJ file, and an MTL file next to it if one is given
+fn write(directory: &Path, obj: &str, mtl: Option<&str>) -> PathBuf {
+    if let Some(mtl) = mtl {
+        fs::write(directory.join("materials.mtl"), mtl).unwrap();
+    }
+    let path = directory.join("model.obj");
+    fs::write(&path, obj).unwrap();
+    path
+}
+
+/// Gets the corners of a mesh's triangle
+fn corners(mesh: &TriangleMesh, triangle: usize) -> [Vector3; 3] {
+    let [a, b, c] = mesh.indices()[triangle];
+    let positions = mesh.positions();
+    [
+        positions[a as usize],
+        positions[b as usize],
+        positions[c as usize],
+    ]
+}
+
+fn assert_same(actual: Vector3, expected: Vector3) {
+    assert!(
+        (actual - expected).len() < 1e-6,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// Loads a file that should fail to parse, returning the line and message
+fn parse_error(result: Result<Vec<TriangleMesh>, ObjError>) -> (PathBuf, usize, String) {
+    match result {
+        Err(ObjError::Parse {
+            path,
+            line,
+            message,
+        }) => (path, line, message),
+        other => panic!("expected a parse error, got {:?}", other.map(|m| m.len())),
+    }
+}
+
+#[test]
+fn polygons_are_split_into_triangles() {
+    let directory = common::temp_dir("obj-triangles");
+    let obj = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+v -1 0.5 0
+# A triangle, a quad, and a pentagon
+f 1 2 3
+f 1 2 3 4
+f 1 2 3 4 5
+";
+    let meshes = load_obj(&write(&directory, obj, None)).unwrap();
+    assert_eq!(meshes.len(), 1);
+    assert_eq!(meshes[0].len(), 1 + 2 + 3);
+    // Shared corners are stored once
+    assert_eq!(meshes[0].positions().len(), 5);
+}
+
+#[test]
+fn negative_indices_count_back_from_the_latest_vertex() {
+    let directory = common::temp_dir("obj-negative");
+    let obj = "\
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 0 0 1
+f -1 -2 -3
+v 5 5 5
+f -4 -3 -1
+";
+    let meshes = load_obj(&write(&directory, obj, None)).unwrap();
+    let mesh = &meshes[0];
+    assert_eq!(mesh.len(), 2);
+    let expected = [
+        [(0.0, 0.0, 1.0), (0.0, 1.0, 0.0), (1.0, 0.0, 0.0)],
+        [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (5.0, 5.0, 5.0)],
+    ];
+    for (triangle, expected) in expected.iter().enumerate() {
+        for (&corner, &(x, y, z)) in corners(mesh, triangle).iter().zip(expected) {
+            assert_same(corner, Vector3::new(x, y, z));
+        }
+    }
+}
+
+#[test]
+fn each_material_gets_one_mesh() {
+    let directory = common::temp_dir("obj-materials");
+    let mtl = "\
+newmtl red
+Kd 1 0 0
+newmtl blue
+Kd 0 0 1
+";
+    let obj = "\
+mtllib materials.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1 1 0
+usemtl red
+f 1 2 3
+usemtl blue
+f 2 4 3
+f 1 2 4
+usemtl red
+f 1 3 4
+";
+    let meshes = load_obj(&write(&directory, obj, Some(mtl))).unwrap();
+    assert_eq!(meshes.len(), 2);
+    // Going back to a material adds to its mesh rather than starting another
+    assert_eq!(meshes[0].len(), 2);
+    assert_eq!(meshes[1].len(), 2);
+    assert!(!Arc::ptr_eq(meshes[0].material(), meshes[1].material()));
+}
+
+#[test]
+fn transparency_blends_by_dissolve() {
+    let directory = common::temp_dir("obj-transparency");
+    let mtl = "\
+newmtl veil
+Kd 1 1 1
+d 0.5
+newmtl water
+Kd 0.8 0.9 1
+Tr 0.75
+Ni 1.33
+newmtl glass
+illum 7
+Ni 1.0
+";
+    let obj = "\
+mtllib materials.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+usemtl veil
+f 1 2 3
+usemtl water
+f 1 2 3
+usemtl glass
+f 1 2 3
+";
+    let meshes = load_obj(&write(&directory, obj, Some(mtl))).unwrap();
+    let describe = |mesh: &TriangleMesh| format!("{:?}", mesh.material());
+
+    // Partly transparent materials let through as much light as `d` says,
+    // bending it like glass unless `Ni` says otherwise
+    let veil = describe(&meshes[0]);
+    assert!(veil.starts_with("Principled"), "{}", veil);
+    assert!(veil.contains("transmission: 0.5,"), "{}", veil);
+    assert!(veil.contains("ior: 1.5 "), "{}", veil);
+    let water = describe(&meshes[1]);
+    assert!(water.contains("transmission: 0.75,"), "{}", water);
+    assert!(water.contains("ior: 1.33 "), "{}", water);
+
+    // Refraction models are glass too when `Ni` is missing or 1
+    let glass = describe(&meshes[2]);
+    assert!(glass.starts_with("Dielectric { ior: 1.5,"), "{}", glass);
+}
+
+#[test]
+fn only_used_materials_are_loaded() {
+    let directory = common::temp_dir("obj-unused");
+    let mtl = "\
+newmtl paint
+Kd 1 0 0
+newmtl poster
+map_Kd missing.png
+";
+    let obj = "\
+mtllib materials.mtl
+v 0 0 0
+v 1 0 0
+v 0 1 0
+usemtl paint
+f 1 2 3
+";
+    // The unused material's texture is neither read nor a dependency
+    let path = write(&directory, obj, Some(mtl));
+    let (meshes, files) = load_obj_with_dependencies(&path).unwrap();
+    assert_eq!(meshes.len(), 1);
+    assert_eq!(files, vec![directory.join("materials.mtl")]);
+
+    // Using it reads the texture, which fails
+    let obj = obj.replace("usemtl paint", "usemtl poster");
+    let path = write(&directory, &obj, Some(mtl));
+    assert!(matches!(load_obj(&path), Err(ObjError::Texture(_))));
+}
+
+#[test]
+fn parse_errors_report_their_line() {
+    let directory = common::temp_dir("obj-errors");
+    let cases = [
+        ("v 0 0 0\nv 1 0 0\nv 1 x 0\n", 3, "invalid"),
+        (
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\n\n# comment\nf 1 2 4\n",
+            6,
+            "out of range",
+        ),
+        ("v 0 0 0\nv 1 0 0\nf 1 2\n", 3, "at least three"),
+        ("v 0 0 0\nf -2 1 1\n", 2, "out of range"),
+    ];
+    for &(obj, expected, message) in &cases {
+        let path = write(&directory, obj, None);
+        let (file, line, text) = parse_error(load_obj(&path));
+        assert_eq!(file, path);
+        assert_eq!(line, expected, "{:?}: {}", obj, text);
+        assert!(text.contains(message), "{:?}: {}", obj, text);
+    }
+
+    // Errors in a material library point into the library
+    let mtl = "newmtl paint\nKd 1 0 0\nNs lots\n";
+    let path = write(&directory, "mtllib materials.mtl\n", Some(mtl));
+    let (file, line, _) = parse_error(load_obj(&path));
+    assert_eq!(file, directory.join("materials.mtl"));
+    assert_eq!(line, 3);
+}
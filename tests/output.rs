@@ -0,0 +1,269 @@
+//! Output tests, which check that floating point images keep colors brighter
+//! than white and lay out their bytes as each format expects, and that tone
+//! mappers squeeze every brightness into the range of 8-bit images.
+
+use raytracer::output::{self, ExrChannel, ExrPrecision};
+use raytracer::tonemap::{srgb_decode, srgb_encode};
+use raytracer::vector::Vector3;
+use raytracer::{Film, OutputTransform, RenderSettings, Renderer, ToneMap};
+
+mod common;
+
+/// Renders an empty scene, so every pixel is the background color
+fn render_background(background: Vector3) -> Film {
+    let scene = common::empty(background);
+    let settings = RenderSettings {
+        width: 3,
+        height: 2,
+        threads: 1,
+        ..RenderSettings::default()
+    };
+    Renderer::new(settings).render_film(&scene)
+}
+
+/// Reads a little-endian value at an offset
+fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes([
+        bytes[at],
+        bytes[at + 1],
+        bytes[at + 2],
+        bytes[at + 3],
+        bytes[at + 4],
+        bytes[at + 5],
+        bytes[at + 6],
+        bytes[at + 7],
+    ])
+}
+
+#[test]
+fn exr_rounds_to_nearest_half() {
+    let values = [
+        (0.0, 0x0000),
+        (-0.0, 0x8000),
+        (1.0, 0x3c00),
+        (-2.0, 0xc000),
+        (0.5, 0x3800),
+        (1.0 / 3.0, 0x3555),
+        (65504.0, 0x7bff),
+        (1e6, 0x7c00),
+        (f32::INFINITY, 0x7c00),
+        // The smallest subnormal half, and a value that rounds to it
+        (5.960_464_5e-8, 0x0001),
+        (4e-8, 0x0001),
+        (1e-9, 0x0000),
+        // Halfway between 1 and the next half rounds to even
+        (1.0 + 1.0 / 2048.0, 0x3c00),
+    ];
+    let floats: Vec<f32> = values.iter().map(|&(value, _)| value).collect();
+    let mut bytes = Vec::new();
+    let channels = [ExrChannel {
+        name: "Y",
+        values: &floats,
+    }];
+    output::write_exr(
+        &mut bytes,
+        values.len() as u32,
+        1,
+        &channels,
+        ExrPrecision::Half,
+    )
+    .unwrap();
+
+    // The only scanline holds the values after its y and size
+    let line = bytes.len() - 2 * values.len();
+    assert_eq!(read_u32(&bytes, line - 8), 0);
+    assert_eq!(read_u32(&bytes, line - 4) as usize, 2 * values.len());
+    for (i, &(value, half)) in values.iter().enumerate() {
+        let written = read_u16(&bytes, line + 2 * i);
+        assert_eq!(written, half, "{} became {:#06x}", value, written);
+    }
+}
+
+#[test]
+fn exr_scanlines_follow_offset_table() {
+    let (width, height) = (3, 4);
+    let pixels = (width * height) as usize;
+    let red: Vec<f32> = (0..pixels).map(|i| i as f32).collect();
+    let green = vec![0.25; pixels];
+    let channels = [
+        ExrChannel {
+            name: "R",
+            values: &red,
+        },
+        ExrChannel {
+            name: "G",
+            values: &green,
+        },
+    ];
+    let mut bytes = Vec::new();
+    output::write_exr(&mut bytes, width, height, &channels, ExrPrecision::Float).unwrap();
+    assert_eq!(&bytes[..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+
+    // The last attribute is followed by an empty name ending the header,
+    // then the table of scanline offsets
+    let last = b"screenWindowWidth\0float\0";
+    let table = bytes
+        .windows(last.len())
+        .position(|window| window == last)
+        .unwrap()
+        + last.len()
+        + 4
+        + 4
+        + 1;
+    let line_size = 2 * width as usize * 4;
+    for y in 0..height as usize {
+        let offset = read_u64(&bytes, table + 8 * y) as usize;
+        assert_eq!(read_u32(&bytes, offset) as usize, y);
+        assert_eq!(read_u32(&bytes, offset + 4) as usize, line_size);
+
+        // Channels are stored alphabetically, so green comes before red
+        for x in 0..width as usize {
+            let green = f32::from_bits(read_u32(&bytes, offset + 8 + 4 * x));
+            let red = f32::from_bits(read_u32(&bytes, offset + 8 + 4 * (width as usize + x)));
+            assert_eq!(green, 0.25);
+            assert_eq!(red, (y * width as usize + x) as f32);
+        }
+    }
+    assert_eq!(
+        read_u64(&bytes, table + 8 * (height as usize - 1)) as usize + 8 + line_size,
+        bytes.len()
+    );
+}
+
+#[test]
+fn hdr_keeps_colors_brighter_than_white() {
+    let film = render_background(Vector3::new(4.0, 0.5, 0.25));
+    let mut bytes = Vec::new();
+    output::write_hdr(&mut bytes, &film).unwrap();
+
+    let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 2 +X 3\n";
+    assert_eq!(&bytes[..header.len()], &header[..]);
+    let pixels = &bytes[header.len()..];
+    assert_eq!(pixels.len(), 3 * 2 * 4);
+
+    // 4 is 0.5 times 2 to the 3, so the mantissas are scaled by 256 / 8
+    for pixel in pixels.chunks(4) {
+        assert_eq!(pixel, &[128, 16, 8, 128 + 3]);
+    }
+}
+
+#[test]
+fn pfm_stores_exact_floats() {
+    let film = render_background(Vector3::new(2.5, 0.125, 1e3));
+    let mut bytes = Vec::new();
+    output::write_pfm(&mut bytes, &film).unwrap();
+
+    let header = b"PF\n3 2\n-1.0\n";
+    assert_eq!(&bytes[..header.len()], &header[..]);
+    let floats: Vec<f32> = bytes[header.len()..]
+        .chunks(4)
+        .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+        .collect();
+    assert_eq!(floats.len(), 3 * 2 * 3);
+    for color in floats.chunks(3) {
+        assert_eq!(color, &[2.5, 0.125, 1e3]);
+    }
+}
+
+#[test]
+fn srgb_round_trips() {
+    for i in 0..=100 {
+        let value = i as f32 / 100.0;
+        let round_trip = srgb_decode(srgb_encode(value));
+        assert!(
+            (round_trip - value).abs() < 1e-5,
+            "{} became {}",
+            value,
+            round_trip
+        );
+    }
+    // Middle gray is encoded about halfway up
+    assert!((srgb_encode(0.214) - 0.5).abs() < 1e-3);
+}
+
+#[test]
+fn tone_maps_stay_in_range_and_keep_order() {
+    let tone_maps = [
+        ToneMap::Clip,
+        ToneMap::Reinhard,
+        ToneMap::ExtendedReinhard,
+        ToneMap::Hable,
+        ToneMap::Aces,
+        ToneMap::Agx,
+    ];
+    for &tone_map in &tone_maps {
+        for &srgb in &[false, true] {
+            let transform = OutputTransform {
+                tone_map,
+                srgb,
+                ..OutputTransform::default()
+            };
+            let mut previous = -1.0;
+            for i in 0..=400 {
+                // Grays from black to 2^12, stepping a tenth of a stop
+                let gray = if i == 0 {
+                    0.0
+                } else {
+                    (i as f32 / 10.0 - 28.0).exp2()
+                };
+                let mapped = transform.apply(Vector3::new_scalar(gray), 16.0);
+                for channel in 0..3 {
+                    let name = format!("{:?} of {} with srgb {}", tone_map, gray, srgb);
+                    assert!(
+                        (0.0..=1.0).contains(&mapped[channel]),
+                        "{} is {:?}",
+                        name,
+                        mapped
+                    );
+                    assert!(
+                        (mapped[channel] - mapped[0]).abs() < 1e-3,
+                        "{} is tinted",
+                        name
+                    );
+                }
+                assert!(
+                    mapped.x >= previous - 1e-6,
+                    "{:?} darkens at {}",
+                    tone_map,
+                    gray
+                );
+                previous = mapped.x;
+            }
+            assert!(previous > 0.95, "{:?} never reaches white", tone_map);
+        }
+    }
+}
+
+#[test]
+fn extended_reinhard_maps_white_point_to_white() {
+    let transform = OutputTransform {
+        tone_map: ToneMap::ExtendedReinhard,
+        srgb: false,
+        ..OutputTransform::default()
+    };
+    let white = transform.apply(Vector3::new_scalar(5.0), 5.0);
+    assert!((white.x - 1.0).abs() < 1e-5);
+    let below = transform.apply(Vector3::new_scalar(2.5), 5.0);
+    assert!(below.x < 1.0);
+}
+
+#[test]
+fn exposure_doubles_light_per_stop() {
+    let transform = OutputTransform {
+        exposure: 2.0,
+        srgb: false,
+        ..OutputTransform::default()
+    };
+    let mapped = transform.apply(Vector3::new(0.05, 0.1, 0.2), 1.0);
+    assert!((mapped.x - 0.2).abs() < 1e-6);
+    assert!((mapped.y - 0.4).abs() < 1e-6);
+    assert!((mapped.z - 0.8).abs() < 1e-6);
+}
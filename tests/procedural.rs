@@ -0,0 +1,107 @@
+//! Procedural texture tests, which check that color ramps clamp outside their
+//! stops, that checkerboards keep alternating on negative coordinates, and
+//! that simplex noise is smooth, bounded, and repeatable.
+
+use raytracer::material::Lambertian;
+use raytracer::noise::Perlin;
+use raytracer::procedural::{Checker, ColorRamp, Mapping, NoiseKind};
+use raytracer::rayhit::RayHit;
+use raytracer::texture::Texture;
+use raytracer::vector::Vector3;
+
+fn assert_color(actual: Vector3, expected: Vector3) {
+    assert!(
+        (actual - expected).len() < 1e-6,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+/// Gets a point spread over a few lattice cells on either side of the origin
+fn point(i: u32) -> Vector3 {
+    let f = |k: u32| ((i * k) % 997) as f32 / 997.0 * 8.0 - 4.0;
+    Vector3::new(f(31), f(57), f(89))
+}
+
+#[test]
+fn color_ramps_clamp_to_their_end_stops() {
+    let red = Vector3::new(1.0, 0.0, 0.0);
+    let green = Vector3::new(0.0, 1.0, 0.0);
+    let blue = Vector3::new(0.0, 0.0, 1.0);
+    // Stops are sorted, so they may be given in any order
+    let ramp = ColorRamp::new(vec![(0.8, blue), (0.2, red), (0.5, green)]);
+
+    for &t in &[-10.0, 0.0, 0.2] {
+        assert_color(ramp.color(t), red);
+    }
+    for &t in &[0.8, 1.0, 10.0] {
+        assert_color(ramp.color(t), blue);
+    }
+    assert_color(ramp.color(0.5), green);
+    assert_color(ramp.color(0.35), (red + green) * 0.5);
+    assert_color(ramp.color(0.65), (green + blue) * 0.5);
+
+    // A single stop is the same color everywhere
+    let single = ColorRamp::new(vec![(0.5, green)]);
+    for &t in &[-1.0, 0.5, 2.0] {
+        assert_color(single.color(t), green);
+    }
+}
+
+#[test]
+fn checkers_alternate_across_negative_coordinates() {
+    let black = Vector3::origin();
+    let white = Vector3::new_scalar(1.0);
+    let checker = Checker::new(Mapping::Position, white, black);
+    let material = Lambertian::new(white);
+    let color = |x: f32, y: f32, z: f32| {
+        let normal = Vector3::new(0.0, 1.0, 0.0);
+        let hit = RayHit::new(Vector3::new(x, y, z), normal, (0.0, 0.0), 1.0, &material);
+        checker.evaluate(&hit)
+    };
+
+    // Cells sharing a face always differ across zero, where truncating the
+    // coordinates would repeat a cell and a plain remainder of a negative
+    // sum would be -1 rather than 1
+    let cases = [
+        ((0.5, 0.5, 0.5), white),
+        ((-0.5, 0.5, 0.5), black),
+        ((-1.5, 0.5, 0.5), white),
+        ((-0.5, -0.5, 0.5), white),
+        ((-0.5, -0.5, -0.5), black),
+        ((-2.5, -0.5, -0.5), black),
+        ((-3.5, 0.5, 0.5), white),
+        ((-0.5, 0.5, -3.5), black),
+        ((-0.5, 0.5, -2.5), white),
+    ];
+    for &((x, y, z), expected) in &cases {
+        assert_color(color(x, y, z), expected);
+    }
+}
+
+#[test]
+fn simplex_noise_is_bounded_and_repeatable() {
+    assert_eq!(NoiseKind::from_name("simplex"), Some(NoiseKind::Simplex));
+
+    let noise = Perlin::new(7);
+    let same = Perlin::new(7);
+    let other = Perlin::new(8);
+    let (mut min, mut max) = (f32::MAX, f32::MIN);
+    let mut differs = false;
+    for i in 0..2000 {
+        let p = point(i);
+        let value = noise.simplex(p);
+        assert!(value.abs() <= 1.0, "{} at {:?}", value, p);
+        assert_eq!(value.to_bits(), same.simplex(p).to_bits());
+        differs |= value != other.simplex(p);
+        min = min.min(value);
+        max = max.max(value);
+
+        // Nearby points have nearby values
+        let step = noise.simplex(p + Vector3::new(1e-3, 0.0, 0.0));
+        assert!((step - value).abs() < 0.05, "{} then {}", value, step);
+    }
+    assert!(differs, "the seed doesn't change the pattern");
+    assert!(min < -0.3 && max > 0.3, "values only span {}..{}", min, max);
+}
@@ -0,0 +1,70 @@
+//! Progressive rendering tests, which check that passes add up to the
+//! requested samples and that renders stop at whichever limit comes first.
+
+use raytracer::renderer::Progress;
+use raytracer::{RenderSettings, Renderer};
+
+mod common;
+
+use common::{progressive_settings as settings, sphere_on_floor as scene};
+
+#[test]
+fn passes_add_up_to_sample_count() {
+    let renderer = Renderer::new(RenderSettings {
+        samples: 16,
+        pass_samples: 4,
+        ..settings()
+    });
+    let mut passes = Vec::new();
+    let film = renderer.render_progressive(&scene(), |_, progress| passes.push(*progress));
+
+    let samples: Vec<u32> = passes.iter().map(Progress::samples).collect();
+    assert_eq!(samples, [4, 8, 12, 16]);
+    for y in 0..film.height() {
+        for x in 0..film.width() {
+            assert_eq!(film.pixel(x, y).samples(), 16);
+        }
+    }
+}
+
+#[test]
+fn noise_falls_with_more_passes() {
+    let renderer = Renderer::new(RenderSettings {
+        samples: 64,
+        pass_samples: 4,
+        ..settings()
+    });
+    let mut noise = Vec::new();
+    renderer.render_progressive(&scene(), |_, progress| {
+        noise.push(progress.noise().unwrap())
+    });
+    assert!(
+        noise.last().unwrap() < &(noise[0] * 0.5),
+        "noise only fell from {} to {}",
+        noise[0],
+        noise.last().unwrap()
+    );
+}
+
+#[test]
+fn noise_threshold_stops_early() {
+    let threshold = 0.1;
+    let renderer = Renderer::new(RenderSettings {
+        samples: 4096,
+        pass_samples: 1,
+        noise_threshold: Some(threshold),
+        ..settings()
+    });
+    let mut last = Progress::default();
+    let film = renderer.render_progressive(&scene(), |_, progress| last = *progress);
+
+    assert!(last.samples() < 4096, "never got below the threshold");
+    assert!(last.noise().unwrap() <= threshold);
+    assert_eq!(film.noise(), last.noise());
+    for y in 0..film.height() {
+        for x in 0..film.width() {
+            let error = film.pixel(x, y).relative_error().unwrap();
+            assert!(error <= threshold, "({}, {}) has noise {}", x, y, error);
+        }
+    }
+}
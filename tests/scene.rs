@@ -0,0 +1,154 @@
+//! Scene file tests, which check that mistakes in a description are reported
+//! at the line and column of the key or value that caused them, and that
+//! values the renderer can't use are turned away.
+
+use std::fs;
+
+use raytracer::{SceneError, SceneFile};
+
+mod common;
+
+/// Writes a scene with a red material and a sphere, followed by `extra`,
+/// after replacing `from` with `to` to introduce a mistake
+fn scene(name: &str, from: &str, to: &str, extra: &str) -> String {
+    let source = format!(
+        "\
+[camera]
+position = [0.0, 0.0, -3.0]
+fov = 60.0
+
+[materials.red]
+type = \"lambertian\"
+# a comment
+color = [1.0, 0.0, 0.0]
+
+[[objects]]
+type = \"sphere\"
+center = [0.0, 0.0, 0.0]
+radius = 1.0
+material = \"red\"
+{}",
+        extra
+    );
+    assert!(source.contains(from), "{:?} isn't in the scene", from);
+    let source = source.replacen(from, to, 1);
+
+    let path = common::temp_dir("scene").join(format!("{}.toml", name));
+    fs::write(&path, &source).unwrap();
+    match SceneFile::load(&path) {
+        Err(SceneError::Parse {
+            line,
+            column,
+            message,
+            ..
+        }) => format!("{}:{}: {}", line, column, message),
+        Err(err) => panic!("expected a parse error, got {}", err),
+        Ok(_) => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn valid_scenes_load() {
+    let path = common::temp_dir("scene").join("valid.toml");
+    fs::write(
+        &path,
+        "[camera]\nposition = [0.0, 0.0, -3.0]\nfov = 60.0\n\n[render]\nthreads = 2\n",
+    )
+    .unwrap();
+    assert_eq!(SceneFile::load(&path).unwrap().settings.threads, 2);
+}
+
+#[test]
+fn unknown_keys_point_at_the_key() {
+    let error = scene("key", "color =", "colr =", "");
+    assert!(error.starts_with("8:1: unknown field `colr`"), "{}", error);
+
+    // Keys in later tables are found within their own table
+    let error = scene("object-key", "radius = 1.0", "radus = 1.0", "");
+    assert!(
+        error.starts_with("13:1: unknown field `radus`"),
+        "{}",
+        error
+    );
+
+    let error = scene("indented-key", "color =", "  colr =", "");
+    assert!(error.starts_with("8:3: unknown field `colr`"), "{}", error);
+}
+
+#[test]
+fn unknown_materials_point_at_the_reference() {
+    let error = scene("material", "material = \"red\"", "material = \"blue\"", "");
+    assert_eq!(error, "14:1: unknown material `blue`");
+}
+
+#[test]
+fn thread_counts_must_be_positive() {
+    let error = scene("zero-threads", "", "", "\n[render]\nthreads = 0\n");
+    assert_eq!(error, "17:11: threads must be at least 1");
+
+    let error = scene("negative-threads", "", "", "\n[render]\nthreads = -2\n");
+    assert!(error.starts_with("17:11: invalid value"), "{}", error);
+}
+
+#[test]
+fn filters_must_weight_samples() {
+    let filter = |name: &str, filter: &str| {
+        let extra = format!("\n[render]\nfilter = {{ {} }}\n", filter);
+        scene(name, "", "", &extra)
+    };
+
+    let error = filter("flat-gaussian", "type = \"gaussian\", alpha = 0.0");
+    assert_eq!(error, "17:31: gaussian filter alpha must be greater than 0");
+    let error = filter("growing-gaussian", "type = \"gaussian\", alpha = -1.0");
+    assert!(
+        error.starts_with("17:31: gaussian filter alpha"),
+        "{}",
+        error
+    );
+
+    let error = filter("nan-gaussian", "type = \"gaussian\", alpha = nan");
+    assert!(
+        error.starts_with("17:31: gaussian filter weights"),
+        "{}",
+        error
+    );
+    let error = filter("nan-mitchell", "type = \"mitchell\", c = nan");
+    assert!(
+        error.starts_with("17:31: mitchell filter b = "),
+        "{}",
+        error
+    );
+    let error = filter("wide-tent", "type = \"tent\", radius = inf");
+    assert!(error.starts_with("17:27: tent filter weights"), "{}", error);
+    let error = filter("empty-box", "type = \"box\", radius = 0.0");
+    assert!(error.starts_with("17:26: filter radius"), "{}", error);
+}
+
+#[test]
+fn sizes_must_be_positive() {
+    let error = scene("negative-sphere", "radius = 1.0", "radius = -1.0", "");
+    assert_eq!(error, "13:1: radius must be greater than 0");
+    let error = scene("flat-sphere", "radius = 1.0", "radius = 0.0", "");
+    assert_eq!(error, "13:1: radius must be greater than 0");
+
+    let disk = "\n[[objects]]\ntype = \"disk\"\ncenter = [0.0, 0.0, 0.0]\n\
+                normal = [0.0, 0.0, 1.0]\nradius = -2.0\nmaterial = \"red\"\n";
+    let error = scene("negative-disk", "", "", disk);
+    assert_eq!(error, "20:1: radius must be greater than 0");
+
+    let light = |kind: &str, size: &str, samples: u32| {
+        format!(
+            "\n[[lights]]\ntype = \"{}\"\nposition = [0.0, 2.0, 0.0]\n{}\nsamples = {}\n",
+            kind, size, samples
+        )
+    };
+    let error = scene("sphere-light", "", "", &light("sphere", "radius = -0.5", 4));
+    assert_eq!(error, "19:1: radius must be greater than 0");
+    let disk = light("disk", "normal = [0.0, -1.0, 0.0]\nradius = 0.0", 4);
+    let error = scene("disk-light", "", "", &disk);
+    assert_eq!(error, "20:1: radius must be greater than 0");
+
+    // Lights need at least one sample rather than being quietly given one
+    let error = scene("no-samples", "", "", &light("sphere", "radius = 0.5", 0));
+    assert_eq!(error, "20:1: samples must be at least 1");
+}
@@ -0,0 +1,132 @@
+//! Image texture tests, which check bilinear filtering and every wrap mode
+//! on a 2×2 image, and the texture coordinates spheres and planes give.
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use raytracer::material::Lambertian;
+use raytracer::plane::Plane;
+use raytracer::ray::Ray;
+use raytracer::sphere::Sphere;
+use raytracer::texture::{ImageTexture, Texture, Wrap};
+use raytracer::vector::Vector3;
+use raytracer::Intersectable;
+
+/// Creates a gray image with texels of 0 and 1 on the top row and 2 and 3
+/// on the bottom row, so the sampled brightness shows where it came from
+fn image(wrap: Wrap) -> ImageTexture {
+    let texels = [0.0, 1.0, 2.0, 3.0]
+        .iter()
+        .map(|&value| Vector3::new_scalar(value))
+        .collect();
+    ImageTexture::new(2, 2, texels, wrap)
+}
+
+fn assert_sample(texture: &ImageTexture, u: f32, v: f32, expected: f32) {
+    let color = texture.sample(u, v);
+    assert!(
+        (color - Vector3::new_scalar(expected)).len() < 1e-5,
+        "expected {} at ({}, {}), got {:?}",
+        expected,
+        u,
+        v,
+        color
+    );
+}
+
+fn assert_uv(actual: (f32, f32), expected: (f32, f32)) {
+    assert!(
+        (actual.0 - expected.0).abs() < 1e-5 && (actual.1 - expected.1).abs() < 1e-5,
+        "expected {:?}, got {:?}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn texel_centers_are_exact_and_blend_between() {
+    let texture = image(Wrap::Repeat);
+    // Texel centers, with v growing up the image
+    assert_sample(&texture, 0.25, 0.75, 0.0);
+    assert_sample(&texture, 0.75, 0.75, 1.0);
+    assert_sample(&texture, 0.25, 0.25, 2.0);
+    assert_sample(&texture, 0.75, 0.25, 3.0);
+
+    // Halfway between two and between all four texels
+    assert_sample(&texture, 0.5, 0.75, 0.5);
+    assert_sample(&texture, 0.25, 0.5, 1.0);
+    assert_sample(&texture, 0.5, 0.5, 1.5);
+    // A quarter of the way from the top left texel towards the bottom right
+    assert_sample(&texture, 0.375, 0.625, 0.75);
+}
+
+#[test]
+fn wrap_modes_map_coordinates_back_onto_the_image() {
+    // Along the top row: the left and right edges, then a texel center one
+    // and two texels past each edge
+    let cases = [
+        (Wrap::Repeat, [0.5, 0.5, 1.0, 0.0, 0.0, 1.0]),
+        (Wrap::Clamp, [0.0, 1.0, 0.0, 0.0, 1.0, 1.0]),
+        (Wrap::Mirror, [0.0, 1.0, 0.0, 1.0, 1.0, 0.0]),
+    ];
+    for &(wrap, expected) in &cases {
+        let texture = image(wrap);
+        for (&u, &expected) in [0.0, 1.0, -0.25, -0.75, 1.25, 1.75].iter().zip(&expected) {
+            assert_sample(&texture, u, 0.75, expected);
+        }
+    }
+
+    // Down the left column, past the top and bottom
+    assert_sample(&image(Wrap::Repeat), 0.25, 1.25, 2.0);
+    assert_sample(&image(Wrap::Clamp), 0.25, 1.25, 0.0);
+    assert_sample(&image(Wrap::Mirror), 0.25, -0.75, 0.0);
+    assert_sample(&image(Wrap::Clamp), 0.25, -5.0, 2.0);
+}
+
+#[test]
+fn spheres_map_longitude_and_latitude() {
+    let material = Arc::new(Lambertian::new(Vector3::new_scalar(0.5)));
+    let sphere = Sphere::new(Vector3::new(0.0, 1.0, 0.0), 1.0, material);
+    let uv = |direction: Vector3| {
+        // Shoots at the sphere's center from outside along the direction
+        let origin = Vector3::new(0.0, 1.0, 0.0) + direction * 5.0;
+        sphere.intersect(Ray::new(origin, -direction)).unwrap().uv()
+    };
+
+    assert_uv(uv(Vector3::new(1.0, 0.0, 0.0)), (0.5, 0.5));
+    assert_uv(uv(Vector3::new(0.0, 0.0, -1.0)), (0.25, 0.5));
+    assert_uv(uv(Vector3::new(0.0, 0.0, 1.0)), (0.75, 0.5));
+    assert_uv(uv(Vector3::new(0.0, 1.0, 0.0)), (0.5, 1.0));
+    let angle = PI / 6.0;
+    assert_uv(
+        uv(Vector3::new(angle.cos(), -angle.sin(), 0.0)),
+        (0.5, 0.5 - 1.0 / 6.0),
+    );
+}
+
+#[test]
+fn planes_tile_texture_coordinates_once_per_unit() {
+    let material = Arc::new(Lambertian::new(Vector3::new_scalar(0.5)));
+    let plane = Plane::new(Vector3::origin(), Vector3::new(0.0, 1.0, 0.0), material);
+    let hit = |x: f32, z: f32| {
+        let ray = Ray::new(Vector3::new(x, 3.0, z), Vector3::new(0.0, -1.0, 0.0));
+        plane.intersect(ray).unwrap()
+    };
+
+    // Texture coordinates follow the plane's tangent and bitangent
+    let corner = hit(0.3, -0.7);
+    assert_uv(corner.uv(), (0.3, 0.7));
+    let tangent = corner.tangent();
+    let bitangent = corner.bitangent();
+    let moved = hit(0.3 + 2.0 * tangent.x, -0.7 + 2.0 * tangent.z).uv();
+    assert_uv(moved, (2.3, 0.7));
+    let moved = hit(0.3 + bitangent.x, -0.7 + bitangent.z).uv();
+    assert_uv(moved, (0.3, 1.7));
+
+    // A repeating image shows the same texel a whole unit further along
+    let texture = image(Wrap::Repeat);
+    let near = texture.evaluate(&hit(0.25, -0.75));
+    let far = texture.evaluate(&hit(3.25, -2.75));
+    assert!((near - far).len() < 1e-4);
+    assert!((near - Vector3::new_scalar(0.0)).len() < 1e-4);
+}
@@ -0,0 +1,107 @@
+//! Tile scheduling tests, which check that tiles cover the image exactly
+//! once and that how the image is split between threads never changes it.
+
+use std::sync::Arc;
+
+use raytracer::material::Lambertian;
+use raytracer::tile::Tile;
+use raytracer::vector::Vector3;
+use raytracer::{Film, Integrator, RenderSettings, Renderer};
+
+mod common;
+
+#[test]
+fn grid_covers_every_pixel_once() {
+    for &(width, height, size) in &[(64, 64, 32), (70, 33, 32), (5, 3, 8), (17, 9, 1)] {
+        let tiles = Tile::grid(width, height, size);
+        let mut covered = vec![0; (width * height) as usize];
+        for tile in &tiles {
+            assert!(tile.width() <= size && tile.height() <= size);
+            assert_eq!(tile.pixels().count(), tile.len());
+            for (x, y) in tile.pixels() {
+                covered[(y * width + x) as usize] += 1;
+            }
+        }
+        assert!(
+            covered.iter().all(|&count| count == 1),
+            "{}x{} in tiles of {}",
+            width,
+            height,
+            size
+        );
+    }
+}
+
+fn render(tile_size: u32, threads: u32) -> Film {
+    let scene = common::sphere(
+        Arc::new(Lambertian::new(Vector3::new(0.8, 0.4, 0.2))),
+        60.0,
+        Vector3::new_scalar(1.0),
+    );
+    let settings = RenderSettings {
+        width: 45,
+        height: 30,
+        threads,
+        tile_size,
+        samples: 4,
+        integrator: Integrator::Path,
+        ..RenderSettings::default()
+    };
+    Renderer::new(settings).render_film(&scene)
+}
+
+#[test]
+fn tiling_does_not_change_the_image() {
+    let rgb = |color: Vector3| [color.x, color.y, color.z];
+    let reference = render(64, 1);
+    for &(tile_size, threads) in &[(1, 3), (7, 4), (16, 2), (32, 8)] {
+        let film = render(tile_size, threads);
+        for y in 0..film.height() {
+            for x in 0..film.width() {
+                assert_eq!(
+                    rgb(film.color(x, y)),
+                    rgb(reference.color(x, y)),
+                    "tiles of {} on {} threads at ({}, {})",
+                    tile_size,
+                    threads,
+                    x,
+                    y
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn every_tile_is_timed() {
+    let tiles = Tile::grid(45, 30, 8);
+    let renderer = Renderer::new(RenderSettings {
+        width: 45,
+        height: 30,
+        threads: 3,
+        tile_size: 8,
+        ..RenderSettings::default()
+    });
+    let scene = common::empty(Vector3::new_scalar(0.5));
+    let (_, times) = renderer.render_tiles(&scene);
+    assert_eq!(times.len(), tiles.len());
+    for tile in &tiles {
+        let timed = times.iter().filter(|time| time.tile() == *tile).count();
+        assert_eq!(timed, 1, "{:?}", tile);
+    }
+    assert!(times.iter().all(|time| time.thread() < 3));
+}
+
+#[test]
+fn zero_threads_renders_on_one() {
+    let renderer = Renderer::new(RenderSettings {
+        width: 9,
+        height: 5,
+        threads: 0,
+        ..RenderSettings::default()
+    });
+    assert_eq!(renderer.settings().threads, 1);
+    let (film, times) = renderer.render_tiles(&common::empty(Vector3::new_scalar(0.5)));
+    assert_eq!(film.color(8, 4).y, 0.5);
+    assert!(times.iter().all(|time| time.thread() == 0));
+}
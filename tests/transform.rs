@@ -0,0 +1,143 @@
+//! Transform and instance tests, which check that composed transforms invert
+//! cleanly, that normals stay perpendicular to surfaces under non-uniform
+//! scales, that instances report world space distances and keep both normals
+//! of smooth meshes, and that disks are bounded by their radius.
+
+use std::sync::Arc;
+
+use raytracer::disk::Disk;
+use raytracer::instance::Instance;
+use raytracer::material::Lambertian;
+use raytracer::mesh::TriangleMesh;
+use raytracer::ray::Ray;
+use raytracer::sphere::Sphere;
+use raytracer::transform::{Matrix4, Transform};
+use raytracer::vector::Vector3;
+use raytracer::Intersectable;
+
+/// Scales unevenly, turns about a tilted axis, and moves away from the
+/// origin, so no part of the matrix is trivial
+fn composed() -> Transform {
+    Transform::scale(Vector3::new(2.0, 0.5, 3.0))
+        .unwrap()
+        .then(Transform::rotate(Vector3::new(1.0, 2.0, -0.5), 37.0))
+        .then(Transform::rotate(Vector3::new(0.0, 1.0, 0.0), -110.0))
+        .then(Transform::translate(Vector3::new(4.0, -2.0, 7.5)))
+}
+
+fn gray() -> Arc<Lambertian> {
+    Arc::new(Lambertian::new(Vector3::new_scalar(0.5)))
+}
+
+fn assert_identity(matrix: Matrix4) {
+    let identity = Matrix4::identity().rows();
+    for (row, expected) in matrix.rows().iter().zip(&identity) {
+        for (value, expected) in row.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() < 1e-5,
+                "not the identity: {:?}",
+                matrix
+            );
+        }
+    }
+}
+
+#[test]
+fn composed_matrices_times_their_inverses_are_the_identity() {
+    let matrix = composed().matrix();
+    let inverse = matrix.inverse().unwrap();
+    assert_identity(matrix * inverse);
+    assert_identity(inverse * matrix);
+    assert_identity(composed().then(composed().inverse()).matrix());
+}
+
+#[test]
+fn normals_stay_perpendicular_under_non_uniform_scales() {
+    let transform = composed();
+    let normal = Vector3::new(1.0, 1.0, 0.0).normalize();
+    for &tangent in &[Vector3::new(1.0, -1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)] {
+        let tangent = transform.vector(tangent);
+        let normal = transform.normal(normal);
+        assert!(
+            normal.dot(tangent).abs() < 1e-5 * tangent.len(),
+            "normal {:?} isn't perpendicular to {:?}",
+            normal,
+            tangent
+        );
+        assert!((normal.len() - 1.0).abs() < 1e-5);
+    }
+
+    // Transforming the normal like any other direction would tilt it
+    let naive = transform.vector(normal);
+    let tangent = transform.vector(Vector3::new(1.0, -1.0, 0.0));
+    assert!(naive.dot(tangent).abs() > 0.1);
+}
+
+#[test]
+fn scaled_instances_report_world_distances() {
+    let scale = Transform::scale(Vector3::new_scalar(2.0)).unwrap();
+    let instance = Instance::new(Arc::new(Sphere::new(Vector3::origin(), 1.0, gray())), scale);
+    let sphere = Sphere::new(Vector3::origin(), 2.0, gray());
+
+    for &(x, y) in &[(0.0, 0.0), (0.7, -0.4), (1.5, 1.1)] {
+        let ray = Ray::new(Vector3::new(x, y, -10.0), Vector3::new(0.0, 0.0, 1.0));
+        let expected = sphere.intersect(ray).unwrap();
+        let hit = instance.intersect(ray).unwrap();
+        assert!((hit.distance() - expected.distance()).abs() < 1e-4);
+        assert!((hit.position() - expected.position()).len() < 1e-4);
+        assert!((hit.normal() - expected.normal()).len() < 1e-4);
+    }
+    let miss = Ray::new(Vector3::new(2.1, 0.0, -10.0), Vector3::new(0.0, 0.0, 1.0));
+    assert!(instance.intersect(miss).is_none());
+}
+
+#[test]
+fn instances_keep_the_geometric_normal_of_smooth_meshes() {
+    // A triangle facing -z whose vertex normals lean towards +x
+    let leaning = Vector3::new(1.0, 0.0, -1.0).normalize();
+    let mesh = TriangleMesh::with_normals(
+        vec![
+            Vector3::new(-1.0, -1.0, 0.0),
+            Vector3::new(-1.0, 1.0, 0.0),
+            Vector3::new(1.0, -1.0, 0.0),
+        ],
+        vec![leaning; 3],
+        vec![[0, 1, 2]],
+        gray(),
+    );
+    let transform = Transform::scale(Vector3::new(1.0, 1.0, 2.0))
+        .unwrap()
+        .then(Transform::rotate(Vector3::new(0.0, 1.0, 0.0), 90.0));
+    let instance = Instance::new(Arc::new(mesh), transform);
+
+    let origin = transform.point(Vector3::new(-0.5, -0.5, -1.0));
+    let direction = transform.vector(Vector3::new(0.0, 0.0, 1.0)).normalize();
+    let hit = instance.intersect(Ray::new(origin, direction)).unwrap();
+    assert!((hit.geometric_normal() - hit.normal()).len() > 0.1);
+    assert!((hit.geometric_normal() - transform.normal(Vector3::new(0.0, 0.0, -1.0))).len() < 1e-5);
+    assert!((hit.normal() - transform.normal(leaning)).len() < 1e-5);
+    assert!(hit.tangent().dot(hit.normal()).abs() < 1e-5);
+}
+
+#[test]
+fn disks_end_at_their_radius() {
+    let center = Vector3::new(1.0, 2.0, 3.0);
+    let disk = Disk::new(center, Vector3::new(0.0, 0.0, -2.0), 1.5, gray());
+
+    // Hits from both sides land on the plane at the same distance
+    for &(z, direction) in &[(-2.0, 1.0), (8.0, -1.0)] {
+        let ray = Ray::new(Vector3::new(1.5, 2.5, z), Vector3::new(0.0, 0.0, direction));
+        let hit = disk.intersect(ray).unwrap();
+        assert!((hit.distance() - 5.0).abs() < 1e-5);
+        assert!((hit.normal() - Vector3::new(0.0, 0.0, -1.0)).len() < 1e-6);
+    }
+    let outside = Ray::new(Vector3::new(2.2, 3.2, -2.0), Vector3::new(0.0, 0.0, 1.0));
+    assert!(disk.intersect(outside).is_none());
+    let parallel = Ray::new(Vector3::new(1.0, 2.0, 3.0), Vector3::new(1.0, 0.0, 0.0));
+    assert!(disk.intersect(parallel).is_none());
+
+    // The box is flat along the normal and as wide as the disk across it
+    let bounds = disk.bounds();
+    assert!((bounds.min() - Vector3::new(-0.5, 0.5, 3.0)).len() < 1e-5);
+    assert!((bounds.max() - Vector3::new(2.5, 3.5, 3.0)).len() < 1e-5);
+}